@@ -0,0 +1,129 @@
+//! Extraction throughput benchmarks
+//!
+//! `cargo bench` times `extract_all` on generated small, medium and large
+//! documents, each built-in kind on its own (`extract_types`) on the medium
+//! one, and `Extractor::extract_batch` over many documents at 1, 2, 4 and
+//! all-core thread pools, so the rayon scaling shows as the ratio between
+//! the `batch/threads=N` rows. Fixtures come from a seeded generator, so
+//! every run and every machine measures the same text.
+//!
+//! The benchmarks run under criterion, which reports each one's time and
+//! throughput per byte of input and keeps its results under
+//! `target/criterion`. Arguments filter by substring: `cargo bench --bench
+//! extraction -- batch`. `benches/guard.rs` compares those results with the
+//! checked-in baseline.
+
+use std::time::Duration;
+
+use criterion::{criterion_group, criterion_main, BenchmarkGroup, Criterion, Throughput};
+use criterion::measurement::WallTime;
+
+use l_extract::{extract_all, extract_types, EntityKind, Extractor};
+
+/// Documents in the batch benchmarks, and their size
+const BATCH_DOCUMENTS: usize = 1000;
+const BATCH_DOCUMENT_BYTES: usize = 4 * 1024;
+
+const PERSONS: &[&str] = &["Jeffrey Epstein", "Ghislaine Maxwell", "Bill Clinton", "Prince Andrew", "Jean-Luc Brunel", "Sarah Kellen"];
+const ORGANIZATIONS: &[&str] = &["Acme Holdings Inc", "Southern Trust Company", "Financial Trust Co", "Gratitude America Ltd", "JPMorgan Chase Bank"];
+const LOCATIONS: &[&str] = &["New York, NY", "Palm Beach, Florida", "Santa Fe, New Mexico", "Paris, France", "London, England"];
+const MONTHS: &[&str] = &["January", "March", "May", "July", "September", "November"];
+
+/// Deterministic pseudo-random numbers for the fixtures (64-bit LCG)
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> usize {
+        self.0 = self.0.wrapping_mul(6_364_136_223_846_793_005).wrapping_add(1_442_695_040_888_963_407);
+        (self.0 >> 33) as usize
+    }
+
+    fn pick<'a>(&mut self, items: &[&'a str]) -> &'a str {
+        items[self.next() % items.len()]
+    }
+}
+
+/// One sentence of deposition-like text with an entity or two of most kinds
+fn sentence(rng: &mut Rng) -> String {
+    let person = rng.pick(PERSONS);
+    let n = rng.next();
+    match n % 8 {
+        0 => format!(
+            "On {} {}, {} {} wired ${},{:03} to {} in {}. ",
+            rng.pick(MONTHS), 1 + n % 28, 1995 + n % 25, person, 1 + n % 900, n % 1000, rng.pick(ORGANIZATIONS), rng.pick(LOCATIONS),
+        ),
+        1 => format!("{} emailed {}@example.com and called ({:03}) 555-{:04} about the schedule. ", person, person.split(' ').next().unwrap().to_lowercase(), 200 + n % 700, n % 10_000),
+        2 => format!("See https://www.example.com/docs/{} and Case No. 1:{:02}-cv-{:05}, 123 F.3d {}. ", n % 10_000, n % 20, n % 100_000, n % 900),
+        3 => format!("The flight from {} carried {} passengers, {}% of capacity, and {} kg of luggage. ", rng.pick(LOCATIONS), 2 + n % 12, n % 100, 10 + n % 90),
+        4 => format!("{}, age {}, was seen with {} at {} on {}/{}/{}. ", person, 15 + n % 50, rng.pick(PERSONS), rng.pick(ORGANIZATIONS), 1 + n % 12, 1 + n % 28, 2000 + n % 20),
+        5 => format!("Exhibit {} lists {} mg of Xanax and ICD-10 F{:02}.{}, coordinates 26.{:04}, -80.{:04}. ", n % 500, 1 + n % 10, n % 99, n % 10, n % 10_000, n % 10_000),
+        6 => format!("[REDACTED] told {} that EIN {:02}-{:07} paid €{} million in Q{} {}. ", person, 10 + n % 89, n % 10_000_000, 1 + n % 20, 1 + n % 4, 2000 + n % 20),
+        _ => "The witness did not recall the name of the pilot or the tail number of the aircraft that night. ".to_string(),
+    }
+}
+
+/// A generated document of about `bytes` bytes
+fn fixture(seed: u64, bytes: usize) -> String {
+    let mut rng = Rng(seed);
+    let mut text = String::with_capacity(bytes + 256);
+    while text.len() < bytes {
+        text.push_str(&sentence(&mut rng));
+        if rng.next().is_multiple_of(6) {
+            text.push_str("\n\n");
+        }
+    }
+    text
+}
+
+/// The groups' sampling: the large and batch inputs take a second or more
+/// per iteration, so criterion's default 100 samples would run for minutes
+fn configure(group: &mut BenchmarkGroup<'_, WallTime>, bytes: usize) {
+    group.sample_size(10).warm_up_time(Duration::from_secs(1)).throughput(Throughput::Bytes(bytes as u64));
+}
+
+fn documents(c: &mut Criterion) {
+    l_extract::warmup();
+    let mut group = c.benchmark_group("extract_all");
+    for (label, seed, bytes) in [("small", 1, 2 * 1024), ("medium", 2, 64 * 1024), ("large", 3, 1024 * 1024)] {
+        let text = fixture(seed, bytes);
+        configure(&mut group, text.len());
+        group.bench_function(label, |b| b.iter(|| extract_all(&text)));
+    }
+    group.finish();
+}
+
+fn kinds(c: &mut Criterion) {
+    let medium = fixture(2, 64 * 1024);
+    let mut group = c.benchmark_group("type");
+    configure(&mut group, medium.len());
+    for kind in EntityKind::ALL {
+        group.bench_function(kind.as_str(), |b| b.iter(|| extract_types(&medium, &[kind.selectors()[0]])));
+    }
+    group.finish();
+}
+
+fn batches(c: &mut Criterion) {
+    let documents: Vec<String> = (0..BATCH_DOCUMENTS as u64).map(|seed| fixture(100 + seed, BATCH_DOCUMENT_BYTES)).collect();
+    let documents: Vec<&str> = documents.iter().map(String::as_str).collect();
+    let batch_bytes = documents.iter().map(|doc| doc.len()).sum();
+
+    let mut group = c.benchmark_group("extract_batch");
+    configure(&mut group, batch_bytes);
+    group.bench_function("default", |b| b.iter(|| l_extract::extract_batch(&documents)));
+    group.finish();
+
+    let cores = std::thread::available_parallelism().map_or(1, |n| n.get());
+    let mut pools = vec![1, 2, 4, cores];
+    pools.sort();
+    pools.dedup();
+    let mut group = c.benchmark_group("batch");
+    configure(&mut group, batch_bytes);
+    for threads in pools {
+        let extractor = Extractor::builder().threads(threads).build().unwrap();
+        group.bench_function(format!("threads={threads}"), |b| b.iter(|| extractor.extract_batch(&documents)));
+    }
+    group.finish();
+}
+
+criterion_group!(benches, documents, kinds, batches);
+criterion_main!(benches);
@@ -0,0 +1,98 @@
+//! Throughput regression guard over the criterion results
+//!
+//! Run after the benchmarks (`cargo bench --bench extraction`, then `cargo
+//! bench --bench guard`). Each benchmark's median time under
+//! `target/criterion` (or `$CRITERION_HOME`) becomes MB/s of input, printed
+//! with its change against `benches/baseline.json`. With `BENCH_GUARD=0.2`
+//! the run fails if any benchmark is more than 20% below its baseline;
+//! `BENCH_SAVE=1` writes this run's rows into it. Arguments filter by
+//! substring, as for the benchmarks.
+//!
+//! Throughput depends on the machine, so the guard only means something
+//! against a baseline saved on the same one; the checked-in numbers are
+//! from a single-core x86_64 VM (release profile), where the
+//! `batch/threads=N` rows are flat.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+const BASELINE_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/benches/baseline.json");
+
+/// `full_id` and MB/s of every benchmark criterion has a result for under `dir`
+fn results(dir: &Path, found: &mut BTreeMap<String, f64>) {
+    let Ok(entries) = std::fs::read_dir(dir) else { return };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if !path.is_dir() {
+            continue;
+        }
+        if path.file_name().is_some_and(|name| name == "new") {
+            if let Some((id, throughput)) = result(&path) {
+                found.insert(id, throughput);
+            }
+        } else {
+            results(&path, found);
+        }
+    }
+}
+
+/// From a criterion `new` directory: the benchmark's id, and its input bytes
+/// over its median time
+fn result(dir: &Path) -> Option<(String, f64)> {
+    let read = |name: &str| -> Option<serde_json::Value> {
+        serde_json::from_str(&std::fs::read_to_string(dir.join(name)).ok()?).ok()
+    };
+    let benchmark = read("benchmark.json")?;
+    let estimates = read("estimates.json")?;
+    let bytes = benchmark["throughput"]["Bytes"].as_f64()?;
+    let median_ns = estimates["median"]["point_estimate"].as_f64()?;
+    Some((benchmark["full_id"].as_str()?.to_string(), bytes / 1e6 / (median_ns / 1e9)))
+}
+
+fn main() {
+    // cargo passes `--bench`
+    let filters: Vec<String> = std::env::args().skip(1).filter(|arg| !arg.starts_with("--")).collect();
+    let home = std::env::var_os("CRITERION_HOME")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| Path::new(env!("CARGO_MANIFEST_DIR")).join("target/criterion"));
+    let baseline: BTreeMap<String, f64> = std::fs::read_to_string(BASELINE_PATH)
+        .ok()
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default();
+
+    let mut found = BTreeMap::new();
+    results(&home, &mut found);
+    found.retain(|name, _| filters.is_empty() || filters.iter().any(|filter| name.contains(filter.as_str())));
+    if found.is_empty() {
+        println!("no benchmark results under {}; run `cargo bench --bench extraction` first", home.display());
+        return;
+    }
+    for (name, &throughput) in &found {
+        let change = match baseline.get(name) {
+            Some(baseline) => format!("{:+.1}%", (throughput / baseline - 1.0) * 100.0),
+            None => "new".to_string(),
+        };
+        println!("{:<32} {:>10.2} MB/s {:>8}", name, throughput, change);
+    }
+
+    if std::env::var_os("BENCH_SAVE").is_some() {
+        let mut saved = baseline.clone();
+        saved.extend(found.clone());
+        std::fs::write(BASELINE_PATH, serde_json::to_string_pretty(&saved).unwrap() + "\n").unwrap();
+        println!("baseline written to {BASELINE_PATH}");
+    }
+
+    if let Some(tolerance) = std::env::var("BENCH_GUARD").ok().and_then(|t| t.parse::<f64>().ok()) {
+        let regressions: Vec<String> = found.iter()
+            .filter_map(|(name, &throughput)| {
+                let baseline = *baseline.get(name)?;
+                (throughput < baseline * (1.0 - tolerance))
+                    .then(|| format!("{name}: {throughput:.2} MB/s, baseline {baseline:.2}"))
+            })
+            .collect();
+        if !regressions.is_empty() {
+            eprintln!("throughput regressions beyond {:.0}%:\n  {}", tolerance * 100.0, regressions.join("\n  "));
+            std::process::exit(1);
+        }
+    }
+}
@@ -0,0 +1,265 @@
+//! Async client for the extraction HTTP server
+//!
+//! The request and response bodies here are the ones the server itself
+//! (de)serializes, so a client built against this crate can't drift from
+//! the API. Failed connections, timeouts, 429 and 5xx responses are retried
+//! with exponential backoff; any other status fails straight away.
+
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+use crate::{EntityKind, ExtractOptions, ExtractionResult, BUILTIN_EXTRACTORS};
+
+/// Body of `POST /extract`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ExtractRequest {
+    pub text: String,
+    /// Only these entity types, by selector ("persons", "email", ...)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub types: Option<Vec<String>>,
+    #[serde(flatten)]
+    pub options: ExtractOptions,
+}
+
+impl ExtractRequest {
+    pub fn new(text: impl Into<String>) -> Self {
+        ExtractRequest { text: text.into(), ..Default::default() }
+    }
+}
+
+/// Body of `POST /batch` and `POST /batch/summary`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BatchRequest {
+    pub documents: Vec<String>,
+    /// Extract only one document per `dedupe_batch` group at this
+    /// threshold; the others get their representative's result, offsets
+    /// and all. Must be in (0, 1]: `/batch` rejects anything else, NaN
+    /// included, with a 400
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dedupe_threshold: Option<f64>,
+}
+
+/// One `/batch` slot: the result, or `{"error": ...}` if that document failed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum BatchItem {
+    // Tried first when decoding: every result field has a default, so an
+    // error object would otherwise read as an empty result
+    Err { error: String },
+    Ok(Box<ExtractionResult>),
+}
+
+impl From<Result<ExtractionResult, String>> for BatchItem {
+    fn from(result: Result<ExtractionResult, String>) -> Self {
+        match result {
+            Ok(result) => BatchItem::Ok(Box::new(result)),
+            Err(error) => BatchItem::Err { error },
+        }
+    }
+}
+
+impl From<BatchItem> for Result<ExtractionResult, String> {
+    fn from(item: BatchItem) -> Self {
+        match item {
+            BatchItem::Ok(result) => Ok(*result),
+            BatchItem::Err { error } => Err(error),
+        }
+    }
+}
+
+/// Body of `GET /health`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HealthResponse {
+    pub status: String,
+    pub service: String,
+    pub version: String,
+}
+
+/// One entry of `GET /types`, built from `EntityKind`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TypeInfo {
+    /// `Entity::entity_type` of this kind's entities
+    pub kind: String,
+    /// Names for `ExtractRequest.types`, group selectors ("medical") included
+    pub selectors: Vec<String>,
+    pub default_confidence: f64,
+    /// Whether a request without `types` extracts it
+    pub enabled_by_default: bool,
+    /// Whether its entities carry context unless `context_types` says otherwise
+    pub context_by_default: bool,
+}
+
+impl TypeInfo {
+    /// Every built-in kind, in `EntityKind::ALL` order
+    pub fn all() -> Vec<TypeInfo> {
+        EntityKind::ALL.iter()
+            .map(|&kind| TypeInfo {
+                kind: kind.as_str().to_string(),
+                selectors: kind.selectors().iter().map(|s| s.to_string()).collect(),
+                default_confidence: kind.default_confidence(),
+                enabled_by_default: BUILTIN_EXTRACTORS.iter().any(|e| e.kind() == Some(kind)),
+                context_by_default: kind.context_by_default(),
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ClientError {
+    #[error("invalid server URL: {0}")]
+    InvalidUrl(#[from] url::ParseError),
+    #[error("request timed out")]
+    Timeout,
+    #[error("request failed: {0}")]
+    Http(#[from] reqwest::Error),
+    #[error("server returned status {status}: {body}")]
+    Status { status: u16, body: String },
+    #[error("invalid response body: {0}")]
+    Decode(#[from] serde_json::Error),
+}
+
+impl ClientError {
+    /// Whether another attempt could succeed
+    fn is_transient(&self) -> bool {
+        match self {
+            ClientError::Timeout => true,
+            ClientError::Http(err) => err.is_connect() || err.is_request(),
+            ClientError::Status { status, .. } => *status == 429 || *status >= 500,
+            ClientError::InvalidUrl(_) | ClientError::Decode(_) => false,
+        }
+    }
+}
+
+pub struct ExtractClientBuilder {
+    base_url: String,
+    timeout: Duration,
+    retries: u32,
+    backoff: Duration,
+}
+
+impl ExtractClientBuilder {
+    /// Budget for each attempt, connect and response included
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Attempts after the first for a transient failure
+    pub fn retries(mut self, retries: u32) -> Self {
+        self.retries = retries;
+        self
+    }
+
+    /// Wait before the first retry, doubled for each one after
+    pub fn backoff(mut self, backoff: Duration) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    pub fn build(self) -> Result<ExtractClient, ClientError> {
+        let mut base_url = url::Url::parse(&self.base_url)?;
+        // Keep a path prefix ("http://host/l-extract") when joining routes
+        if !base_url.path().ends_with('/') {
+            base_url.set_path(&format!("{}/", base_url.path()));
+        }
+        Ok(ExtractClient {
+            http: reqwest::Client::builder().timeout(self.timeout).build()?,
+            base_url,
+            retries: self.retries,
+            backoff: self.backoff,
+        })
+    }
+}
+
+pub struct ExtractClient {
+    http: reqwest::Client,
+    base_url: url::Url,
+    retries: u32,
+    backoff: Duration,
+}
+
+impl ExtractClient {
+    /// Builder for a server at `base_url` ("http://127.0.0.1:9001"), with
+    /// a 30 s timeout and 2 retries 200 ms apart by default
+    pub fn builder(base_url: impl Into<String>) -> ExtractClientBuilder {
+        ExtractClientBuilder {
+            base_url: base_url.into(),
+            timeout: Duration::from_secs(30),
+            retries: 2,
+            backoff: Duration::from_millis(200),
+        }
+    }
+
+    pub fn new(base_url: impl Into<String>) -> Result<Self, ClientError> {
+        Self::builder(base_url).build()
+    }
+
+    pub async fn health(&self) -> Result<HealthResponse, ClientError> {
+        self.send(reqwest::Method::GET, "health", None).await
+    }
+
+    /// The server's entity types and their selectors
+    pub async fn types(&self) -> Result<Vec<TypeInfo>, ClientError> {
+        self.send(reqwest::Method::GET, "types", None).await
+    }
+
+    pub async fn extract(&self, request: &ExtractRequest) -> Result<ExtractionResult, ClientError> {
+        self.send(reqwest::Method::POST, "extract", Some(serde_json::to_vec(request)?)).await
+    }
+
+    /// One result per document, in order; a document the server failed on
+    /// is its error message
+    pub async fn batch(&self, documents: &[&str]) -> Result<Vec<Result<ExtractionResult, String>>, ClientError> {
+        let request = BatchRequest { documents: documents.iter().map(|d| d.to_string()).collect(), ..Default::default() };
+        let items: Vec<BatchItem> =
+            self.send(reqwest::Method::POST, "batch", Some(serde_json::to_vec(&request)?)).await?;
+        Ok(items.into_iter().map(Into::into).collect())
+    }
+
+    async fn send<T: serde::de::DeserializeOwned>(
+        &self,
+        method: reqwest::Method,
+        route: &str,
+        body: Option<Vec<u8>>,
+    ) -> Result<T, ClientError> {
+        let url = self.base_url.join(route)?;
+        let mut attempt = 0;
+        loop {
+            match self.attempt(method.clone(), url.clone(), body.clone()).await {
+                Err(err) if attempt < self.retries && err.is_transient() => {
+                    tokio::time::sleep(self.backoff * 2u32.saturating_pow(attempt)).await;
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    async fn attempt<T: serde::de::DeserializeOwned>(
+        &self,
+        method: reqwest::Method,
+        url: url::Url,
+        body: Option<Vec<u8>>,
+    ) -> Result<T, ClientError> {
+        let mut request = self.http.request(method, url);
+        if let Some(body) = body {
+            request = request.header(reqwest::header::CONTENT_TYPE, "application/json").body(body);
+        }
+        let response = request.send().await.map_err(timeout_or_http)?;
+        let status = response.status();
+        let bytes = response.bytes().await.map_err(timeout_or_http)?;
+        if !status.is_success() {
+            let body = String::from_utf8_lossy(&bytes).into_owned();
+            return Err(ClientError::Status { status: status.as_u16(), body });
+        }
+        Ok(serde_json::from_slice(&bytes)?)
+    }
+}
+
+fn timeout_or_http(err: reqwest::Error) -> ClientError {
+    if err.is_timeout() {
+        ClientError::Timeout
+    } else {
+        ClientError::Http(err)
+    }
+}
@@ -0,0 +1,180 @@
+//! `Extractor` configuration from a JSON file
+//!
+//! The server reads one at startup (`CONFIG_PATH`) and again whenever it
+//! changes, so tuning doesn't need a redeploy:
+//!
+//! ```json
+//! {
+//!   "options": {"denylist": ["Acme Corp"], "coref": true},
+//!   "patterns": [
+//!     {"name": "case_number", "pattern": "\\b\\d{2}-cv-\\d{5}\\b", "entity_type": "case_number", "confidence": 0.9}
+//!   ]
+//! }
+//! ```
+//!
+//! `options` is an `ExtractOptions` in its JSON form, so the denylist and
+//! the drug gazetteer (`drugs`) are set there; absent fields keep their
+//! defaults. Each pattern becomes a `PatternExtractor`. The format is JSON
+//! rather than TOML so the options read exactly as `/extract` takes them.
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    compile_pattern, context_window, Entity, EntityExtractor, EntityKind, ExtractCtx, ExtractError, ExtractOptions,
+    ExtractorBuilder,
+};
+use std::collections::HashMap;
+
+/// Body of a config file
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ExtractorConfig {
+    /// Options the extractor uses when a call doesn't pass its own
+    #[serde(default)]
+    pub options: ExtractOptions,
+    /// Custom patterns, run after the built-ins
+    #[serde(default)]
+    pub patterns: Vec<PatternConfig>,
+}
+
+/// One custom pattern of an `ExtractorConfig`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PatternConfig {
+    /// Reported as `metadata["pattern"]`
+    pub name: String,
+    pub pattern: String,
+    /// `Entity::entity_type` of its matches; a built-in one ("person")
+    /// puts them in that kind's vector, any other in `custom`
+    pub entity_type: String,
+    #[serde(default = "default_pattern_confidence")]
+    pub confidence: f64,
+}
+
+/// `PatternConfig::confidence` when a config leaves it out
+pub const DEFAULT_PATTERN_CONFIDENCE: f64 = 0.8;
+
+fn default_pattern_confidence() -> f64 {
+    DEFAULT_PATTERN_CONFIDENCE
+}
+
+impl ExtractorConfig {
+    pub fn from_json(json: &str) -> Result<Self, ExtractError> {
+        Ok(serde_json::from_str(json)?)
+    }
+
+    pub fn load(path: impl AsRef<std::path::Path>) -> Result<Self, ExtractError> {
+        Self::from_json(&std::fs::read_to_string(path)?)
+    }
+
+    /// `builder` with these options and patterns; fails on the first
+    /// pattern that doesn't compile
+    pub fn apply(self, builder: ExtractorBuilder) -> Result<ExtractorBuilder, ExtractError> {
+        let mut builder = builder.options(self.options);
+        for pattern in self.patterns {
+            builder = builder.extractor(PatternExtractor::new(pattern)?);
+        }
+        Ok(builder)
+    }
+}
+
+/// Matches of one configured regex
+pub struct PatternExtractor {
+    name: String,
+    regex: Regex,
+    entity_type: String,
+    kind: Option<EntityKind>,
+    confidence: f64,
+}
+
+impl PatternExtractor {
+    pub fn new(config: PatternConfig) -> Result<Self, ExtractError> {
+        let regex = compile_pattern(&config.pattern)
+            .map_err(|source| ExtractError::Pattern { name: config.name.clone(), source })?;
+        let kind = EntityKind::ALL.iter().copied().find(|k| k.as_str() == config.entity_type);
+        Ok(PatternExtractor {
+            name: config.name,
+            regex,
+            entity_type: config.entity_type,
+            kind,
+            confidence: config.confidence,
+        })
+    }
+}
+
+impl EntityExtractor for PatternExtractor {
+    fn extract(&self, text: &str) -> Vec<Entity> {
+        self.extract_in(text, &ExtractCtx::new(&ExtractOptions::default(), text))
+    }
+
+    fn extract_in(&self, text: &str, ctx: &ExtractCtx) -> Vec<Entity> {
+        self.regex.find_iter(text)
+            .take_while(|_| !ctx.expired())
+            .map(|m| {
+                let context = match self.kind {
+                    Some(kind) => ctx.context(kind, text, m.start(), m.end()),
+                    None => ctx.opts.include_context.then(|| context_window(text, m.start(), m.end(), ctx.opts.context_chars)),
+                };
+                Entity {
+                    id: 0,
+                    utf16_start: None,
+                    utf16_end: None,
+                    value: m.as_str().to_string(),
+                    entity_type: self.entity_type.clone(),
+                    start: m.start(),
+                    end: m.end(),
+                    confidence: self.confidence,
+                    context,
+                    metadata: Some(HashMap::from([("pattern".to_string(), self.name.clone())])),
+                }
+            })
+            .collect()
+    }
+
+    fn kind(&self) -> Option<EntityKind> {
+        self.kind
+    }
+
+    fn entity_type(&self) -> Option<&str> {
+        Some(&self.entity_type)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Extractor;
+
+    #[test]
+    fn test_custom_pattern_config() {
+        let config = ExtractorConfig::from_json(r#"{
+            "options": {"denylist": ["Acme Corp"]},
+            "patterns": [{"name": "case_number", "pattern": "\\b\\d{1,2}:\\d{2}-cv-\\d{5}\\b", "entity_type": "case_number"}]
+        }"#).unwrap();
+        let extractor = config.apply(Extractor::builder().threads(1)).unwrap().build().unwrap();
+
+        let result = extractor.extract("Acme Corp was sued by Jeffrey Epstein in case 1:15-cv-07433.");
+        assert_eq!(result.custom.len(), 1);
+        let case = &result.custom[0];
+        assert_eq!((case.value.as_str(), case.entity_type.as_str()), ("1:15-cv-07433", "case_number"));
+        assert_eq!(case.metadata.as_ref().unwrap()["pattern"], "case_number");
+        assert!(result.organizations.is_empty());
+
+        // `types` selects the pattern by its entity type, under the config's options
+        let text = "Jeffrey Epstein and Bill Clinton were named in case 1:15-cv-07433.";
+        let config = ExtractorConfig::from_json(r#"{
+            "options": {"denylist": ["Jeffrey Epstein"]},
+            "patterns": [{"name": "case_number", "pattern": "\\b\\d{1,2}:\\d{2}-cv-\\d{5}\\b", "entity_type": "case_number"}]
+        }"#).unwrap();
+        let extractor = config.apply(Extractor::builder().threads(1)).unwrap().build().unwrap();
+        let typed = extractor.extract_types(text, &["persons", "case_number"]);
+        assert_eq!(typed.custom.iter().map(|e| e.value.as_str()).collect::<Vec<_>>(), ["1:15-cv-07433"]);
+        assert_eq!(typed.persons.iter().map(|e| e.value.as_str()).collect::<Vec<_>>(), ["Bill Clinton"]);
+        assert!(extractor.extract_types(text, &["persons"]).custom.is_empty());
+
+        let invalid = ExtractorConfig::from_json(r#"{"patterns": [{"name": "bad", "pattern": "(", "entity_type": "x"}]}"#)
+            .unwrap()
+            .apply(Extractor::builder());
+        assert!(matches!(invalid, Err(ExtractError::Pattern { name, .. }) if name == "bad"));
+        assert!(ExtractorConfig::from_json("{\"options\": 3}").is_err());
+    }
+}
@@ -0,0 +1,89 @@
+//! Near-duplicate documents in a batch
+//!
+//! Leaked sets hold many copies of one document with a different header or
+//! a fixed typo. `dedupe_batch` groups them by MinHash: each document is
+//! cut into overlapping runs of `SHINGLE_WORDS` lowercased words, and the
+//! share of `HASHES` minimum hashes two documents have in common estimates
+//! the Jaccard similarity of their shingle sets. A signature is one pass
+//! over the text; comparing two is `HASHES` integer compares.
+
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+/// Words per shingle
+const SHINGLE_WORDS: usize = 5;
+
+/// Minimum hashes per signature; the estimate's standard error is about
+/// 1 / sqrt(HASHES), so 0.06 here
+const HASHES: usize = 256;
+
+/// One hash function per signature slot, derived from a shingle's hash
+fn mix(hash: u64, slot: usize) -> u64 {
+    // splitmix64 finalizer
+    let mut z = hash ^ (slot as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+fn signature(text: &str) -> Vec<u64> {
+    let words: Vec<String> = text.split_whitespace().map(str::to_lowercase).collect();
+    let mut minimums = vec![u64::MAX; HASHES];
+    // A document shorter than one shingle is a single shingle
+    for shingle in words.windows(SHINGLE_WORDS.min(words.len()).max(1)) {
+        let mut hasher = DefaultHasher::new();
+        shingle.hash(&mut hasher);
+        let hash = hasher.finish();
+        for (slot, minimum) in minimums.iter_mut().enumerate() {
+            *minimum = (*minimum).min(mix(hash, slot));
+        }
+    }
+    minimums
+}
+
+/// Estimated Jaccard similarity of two signatures
+fn similarity(a: &[u64], b: &[u64]) -> f64 {
+    a.iter().zip(b).filter(|(x, y)| x == y).count() as f64 / HASHES as f64
+}
+
+/// For each document, the index of its canonical representative: the first
+/// earlier document at least `threshold` similar (estimated Jaccard over
+/// word shingles, case-insensitive), or its own index
+///
+/// Identical documents always share a representative; whitespace and case
+/// differences don't count. A threshold around 0.8 groups copies that
+/// differ by a line or two in a page of text. Representatives are compared
+/// against, not every document, so a chain of small edits may split into
+/// several groups.
+pub fn dedupe_batch(documents: &[&str], threshold: f64) -> Vec<usize> {
+    let signatures: Vec<Vec<u64>> = documents.iter().map(|text| signature(text)).collect();
+    let mut representatives: Vec<usize> = Vec::new();
+    let mut canonical = Vec::with_capacity(documents.len());
+    for (i, sig) in signatures.iter().enumerate() {
+        let found = representatives.iter().copied().find(|&r| similarity(&signatures[r], sig) >= threshold);
+        canonical.push(found.unwrap_or_else(|| {
+            representatives.push(i);
+            i
+        }));
+    }
+    canonical
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_identical_and_distinct() {
+        let memo: String = (1..=40)
+            .map(|i| format!("Entry {i}: Jeffrey Epstein wired ${i},000 to Acme Holdings, per the ledger. "))
+            .collect();
+        let edited = memo.replacen("ledger", "notebook", 1);
+        let other = "Ghislaine Maxwell emailed jeff@example.com about the flight logs on March 3, 2005.";
+        let documents = [memo.as_str(), other, memo.as_str(), edited.as_str()];
+
+        assert_eq!(dedupe_batch(&documents, 0.8), vec![0, 1, 0, 0]);
+        assert_eq!(dedupe_batch(&documents, 1.0), vec![0, 1, 0, 3]);
+        assert!(dedupe_batch(&[], 0.8).is_empty());
+    }
+}
@@ -0,0 +1,112 @@
+//! Compare the extractions of two versions of a document
+//!
+//! Entities are matched by `(entity_type, normalized value)` rather than by
+//! offset, since positions shift whenever text is inserted or removed.
+
+use crate::{extract_with_options, Entity, ExtractOptions, ExtractionResult};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExtractionDiff {
+    /// Present in `b` but not in `a`
+    pub added: Vec<Entity>,
+    /// Present in `a` but not in `b`
+    pub removed: Vec<Entity>,
+    /// Present in both (taken from `b`, so offsets point into the newer text)
+    pub unchanged: Vec<Entity>,
+    pub added_count: usize,
+    pub removed_count: usize,
+    pub unchanged_count: usize,
+}
+
+fn key(entity: &Entity) -> (String, String) {
+    (entity.entity_type.clone(), entity.normalized_value())
+}
+
+/// First occurrence of each distinct key, in result order
+fn distinct(result: &ExtractionResult) -> Vec<((String, String), &Entity)> {
+    let mut seen = HashSet::new();
+    result.entities()
+        .map(|e| (key(e), e))
+        .filter(|(k, _)| seen.insert(k.clone()))
+        .collect()
+}
+
+/// Diff the entities of document version `a` against version `b`
+pub fn diff(a: &ExtractionResult, b: &ExtractionResult) -> ExtractionDiff {
+    let a_entities = distinct(a);
+    let b_entities = distinct(b);
+    let a_keys: HashSet<_> = a_entities.iter().map(|(k, _)| k).collect();
+    let b_keys: HashSet<_> = b_entities.iter().map(|(k, _)| k).collect();
+
+    let mut added = Vec::new();
+    let mut unchanged = Vec::new();
+    for (k, e) in &b_entities {
+        if a_keys.contains(k) {
+            unchanged.push((*e).clone());
+        } else {
+            added.push((*e).clone());
+        }
+    }
+    let removed: Vec<Entity> = a_entities.iter()
+        .filter(|(k, _)| !b_keys.contains(k))
+        .map(|(_, e)| (*e).clone())
+        .collect();
+
+    ExtractionDiff {
+        added_count: added.len(),
+        removed_count: removed.len(),
+        unchanged_count: unchanged.len(),
+        added,
+        removed,
+        unchanged,
+    }
+}
+
+/// Extract `text` and keep only the entities whose key isn't in
+/// `baseline`, for polling a living document: pass the entities seen so far
+/// and get back what is new since
+pub fn extract_new(text: &str, baseline: &[Entity], options: &ExtractOptions) -> ExtractionResult {
+    let seen: HashSet<_> = baseline.iter().map(key).collect();
+    let mut result = extract_with_options(text, options);
+    result.retain(|e| !seen.contains(&key(e)));
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::extract_all;
+
+    #[test]
+    fn test_diff_added_person_removed_amount() {
+        let a = extract_all("Jeffrey Epstein wired $250,000 on January 15, 2024.");
+        let b = extract_all("On January 15, 2024, Jeffrey Epstein met Ghislaine Maxwell.");
+        let d = diff(&a, &b);
+
+        assert_eq!(d.added.iter().filter(|e| e.entity_type == "person").count(), 1);
+        assert!(d.added.iter().any(|e| e.value == "Ghislaine Maxwell"));
+        assert_eq!(d.removed.len(), 1);
+        assert_eq!(d.removed[0].entity_type, "amount");
+        assert!(d.unchanged.iter().any(|e| e.value == "Jeffrey Epstein"));
+        assert!(d.unchanged.iter().any(|e| e.entity_type == "date"));
+        assert_eq!(d.added_count, d.added.len());
+        assert_eq!(d.removed_count, 1);
+    }
+
+    #[test]
+    fn test_extract_new_returns_only_the_delta() {
+        let before = "Jeffrey Epstein wired $250,000 to Acme Holdings LLC on January 15, 2024.";
+        let baseline: Vec<Entity> = extract_all(before).entities().cloned().collect();
+        let after = "Jeffrey Epstein wired $250,000 to Acme Holdings LLC on January 15, 2024. \
+                     JEFFREY EPSTEIN later met Ghislaine Maxwell.";
+
+        let fresh = extract_new(after, &baseline, &ExtractOptions::default());
+        let values: Vec<(&str, &str)> = fresh.entities().map(|e| (e.entity_type.as_str(), e.value.as_str())).collect();
+        assert_eq!(values, vec![("person", "Ghislaine Maxwell")]);
+        assert_eq!(fresh.total_count, 1);
+
+        assert_eq!(extract_new(before, &baseline, &ExtractOptions::default()).total_count, 0);
+    }
+}
@@ -0,0 +1,277 @@
+//! Reusable extractor bound to its own rayon thread pool
+//!
+//! The free functions run on rayon's global pool, which grabs every core.
+//! An `Extractor` owns a pool sized at construction, so a service sharing the
+//! machine can cap how much CPU extraction takes. It also carries the
+//! extractor registry, so custom `EntityExtractor`s run next to the built-ins,
+//! and any per-type confidence functions and normalizers.
+
+use crate::{
+    batch_with_registry, builtin_extractors, count_all, extract_streaming_with_registry, extract_types_with_registry,
+    extract_with_registry, Entity, EntityExtractor, EntityKind, ExtractError, ExtractOptions,
+    ExtractionResult, Normalizer, Registry,
+};
+use std::collections::HashMap;
+
+/// Recomputes an entity's confidence from the entity and its document text
+pub type ConfidenceFn = Box<dyn Fn(&Entity, &str) -> f64 + Send + Sync>;
+
+pub struct Extractor {
+    pool: rayon::ThreadPool,
+    options: ExtractOptions,
+    extractors: Registry,
+    confidence: HashMap<EntityKind, ConfidenceFn>,
+    normalizers: HashMap<EntityKind, Box<dyn Normalizer>>,
+}
+
+pub struct ExtractorBuilder {
+    threads: Option<usize>,
+    options: ExtractOptions,
+    extractors: Registry,
+    confidence: HashMap<EntityKind, ConfidenceFn>,
+    normalizers: HashMap<EntityKind, Box<dyn Normalizer>>,
+}
+
+impl Default for ExtractorBuilder {
+    fn default() -> Self {
+        ExtractorBuilder {
+            threads: None,
+            options: ExtractOptions::default(),
+            extractors: builtin_extractors(),
+            confidence: HashMap::new(),
+            normalizers: HashMap::new(),
+        }
+    }
+}
+
+impl ExtractorBuilder {
+    /// Worker threads in the pool; rayon's default (one per core) if unset
+    pub fn threads(mut self, threads: usize) -> Self {
+        self.threads = Some(threads);
+        self
+    }
+
+    /// Options used by `Extractor::extract` and `Extractor::extract_batch`
+    pub fn options(mut self, options: ExtractOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Register a custom extractor, run after the built-ins
+    pub fn extractor(mut self, extractor: impl EntityExtractor + Sync + 'static) -> Self {
+        self.extractors.push(Box::new(extractor));
+        self
+    }
+
+    /// Replace the confidence of every `kind` entity with `f(entity, text)`,
+    /// once extraction is done; a later call for the same kind wins
+    ///
+    /// `f` runs once per entity of that kind, serially on the calling thread
+    /// after the parallel extraction, so keep it cheap: a dense document
+    /// yields thousands of entities, and slicing `text` around each one is
+    /// fine but rescanning it is not. Kinds without a function cost nothing.
+    pub fn confidence(mut self, kind: EntityKind, f: impl Fn(&Entity, &str) -> f64 + Send + Sync + 'static) -> Self {
+        self.confidence.insert(kind, Box::new(f));
+        self
+    }
+
+    /// Set `metadata["normalized"]` of every `kind` entity from `normalizer`,
+    /// once extraction is done; a later call for the same kind wins
+    ///
+    /// Runs before the confidence functions, so they see the registered
+    /// form. Like them, it runs serially once per entity of that kind.
+    pub fn normalizer(mut self, kind: EntityKind, normalizer: impl Normalizer + 'static) -> Self {
+        self.normalizers.insert(kind, Box::new(normalizer));
+        self
+    }
+
+    pub fn build(self) -> Result<Extractor, ExtractError> {
+        let mut pool = rayon::ThreadPoolBuilder::new()
+            .thread_name(|i| format!("l-extract-{}", i));
+        if let Some(threads) = self.threads {
+            pool = pool.num_threads(threads);
+        }
+        Ok(Extractor {
+            pool: pool.build()?,
+            options: self.options,
+            extractors: self.extractors,
+            confidence: self.confidence,
+            normalizers: self.normalizers,
+        })
+    }
+}
+
+impl Extractor {
+    pub fn builder() -> ExtractorBuilder {
+        ExtractorBuilder::default()
+    }
+
+    /// Number of threads in this extractor's pool
+    pub fn threads(&self) -> usize {
+        self.pool.current_num_threads()
+    }
+
+    pub fn options(&self) -> &ExtractOptions {
+        &self.options
+    }
+
+    /// Run `f` inside this extractor's pool
+    pub fn install<R: Send>(&self, f: impl FnOnce() -> R + Send) -> R {
+        self.pool.install(f)
+    }
+
+    pub fn extract(&self, text: &str) -> ExtractionResult {
+        self.extract_with(text, &self.options)
+    }
+
+    /// Extract with per-call options instead of the builder's
+    pub fn extract_with(&self, text: &str, options: &ExtractOptions) -> ExtractionResult {
+        let mut result = self.install(|| extract_with_registry(text, options, &self.extractors));
+        self.rescore(&mut result, text);
+        result
+    }
+
+    /// The registered extractors `types` selects: built-ins by selector,
+    /// custom ones by `EntityExtractor::entity_type`
+    pub fn extract_types(&self, text: &str, types: &[&str]) -> ExtractionResult {
+        self.extract_types_with(text, types, &self.options)
+    }
+
+    pub fn extract_types_with(&self, text: &str, types: &[&str], options: &ExtractOptions) -> ExtractionResult {
+        let mut result = self.install(|| extract_types_with_registry(text, types, options, &self.extractors));
+        self.rescore(&mut result, text);
+        result
+    }
+
+    /// `crate::count_all` in this extractor's pool; built-in kinds only
+    pub fn count_all(&self, text: &str, options: &ExtractOptions) -> HashMap<String, usize> {
+        self.install(|| count_all(text, options))
+    }
+
+    /// Apply the registered normalizers, then the confidence functions
+    fn rescore(&self, result: &mut ExtractionResult, text: &str) {
+        for (&kind, normalizer) in &self.normalizers {
+            for entity in result.field_mut(kind) {
+                normalize(entity, normalizer.as_ref());
+            }
+        }
+        for (&kind, f) in &self.confidence {
+            for entity in result.field_mut(kind) {
+                entity.confidence = f(entity, text);
+            }
+        }
+    }
+
+    fn rescore_entities(&self, entities: &mut [Entity], text: &str) {
+        for entity in entities {
+            let kind = EntityKind::ALL.iter().find(|k| k.as_str() == entity.entity_type);
+            if let Some(normalizer) = kind.and_then(|k| self.normalizers.get(k)) {
+                normalize(entity, normalizer.as_ref());
+            }
+            if let Some(f) = kind.and_then(|k| self.confidence.get(k)) {
+                entity.confidence = f(entity, text);
+            }
+        }
+    }
+
+    /// Extract with `options`, passing each extractor's entities to `sink`
+    /// as soon as it finishes; `kinds` limits the run to those kinds. The
+    /// returned result is the complete one, cross-type links included.
+    pub fn extract_streaming(
+        &self,
+        text: &str,
+        options: &ExtractOptions,
+        kinds: Option<&[EntityKind]>,
+        sink: impl Fn(&[Entity]) + Sync,
+    ) -> ExtractionResult {
+        let rescored = |entities: &[Entity]| {
+            if self.confidence.is_empty() && self.normalizers.is_empty() {
+                return sink(entities);
+            }
+            let mut entities = entities.to_vec();
+            self.rescore_entities(&mut entities, text);
+            sink(&entities)
+        };
+        let mut result = self.install(|| extract_streaming_with_registry(text, options, &self.extractors, kinds, &rescored));
+        self.rescore(&mut result, text);
+        result
+    }
+
+    /// Same per-document isolation as `crate::extract_batch`
+    pub fn extract_batch(&self, documents: &[&str]) -> Vec<Result<ExtractionResult, String>> {
+        let mut results = self.install(|| batch_with_registry(documents, &self.options, &self.extractors));
+        for (result, text) in results.iter_mut().zip(documents) {
+            if let Ok(result) = result {
+                self.rescore(result, text);
+            }
+        }
+        results
+    }
+}
+
+fn normalize(entity: &mut Entity, normalizer: &dyn Normalizer) {
+    if let Some(normalized) = normalizer.normalize(entity) {
+        entity.metadata.get_or_insert_with(HashMap::new).insert("normalized".to_string(), normalized);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_two_thread_pool() {
+        let extractor = Extractor::builder().threads(2).build().unwrap();
+        assert_eq!(extractor.threads(), 2);
+        assert_eq!(extractor.install(rayon::current_num_threads), 2);
+
+        let result = extractor.extract("Jeffrey Epstein wired $5 million on January 15, 2024.");
+        assert!(result.persons.iter().any(|e| e.value == "Jeffrey Epstein"));
+
+        let batch = extractor.extract_batch(&["John Smith", "jeff@example.com"]);
+        assert_eq!(batch.len(), 2);
+        assert!(batch.iter().all(|r| r.is_ok()));
+    }
+
+    #[test]
+    fn test_custom_confidence_function() {
+        let extractor = Extractor::builder()
+            .threads(1)
+            .confidence(EntityKind::Amount, |_, _| 1.0)
+            .build()
+            .unwrap();
+        let text = "Jeffrey Epstein wired $5 million and $20,000 on January 15, 2024.";
+
+        let result = extractor.extract(text);
+        assert!(!result.amounts.is_empty());
+        assert!(result.amounts.iter().all(|e| e.confidence == 1.0));
+        assert!(result.persons.iter().all(|e| e.confidence < 1.0));
+
+        let batch = extractor.extract_batch(&[text]);
+        assert!(batch[0].as_ref().unwrap().amounts.iter().all(|e| e.confidence == 1.0));
+    }
+
+    #[test]
+    fn test_custom_location_normalizer() {
+        let extractor = Extractor::builder()
+            .threads(1)
+            .normalizer(EntityKind::Location, |e: &Entity| match e.value.as_str() {
+                "Palm Beach" => Some("Palm Beach, FL".to_string()),
+                _ => None,
+            })
+            .build()
+            .unwrap();
+        let text = "Jeffrey Epstein flew to Palm Beach and New York with $5 million.";
+
+        let result = extractor.extract(text);
+        let normalized = |value: &str| result.locations.iter()
+            .find(|e| e.value == value)
+            .and_then(|e| e.metadata.as_ref())
+            .and_then(|m| m.get("normalized").cloned());
+        assert_eq!(normalized("Palm Beach").as_deref(), Some("Palm Beach, FL"));
+        assert_eq!(normalized("New York"), None);
+        // Built-in normalizers still apply to other kinds
+        let amount = result.amounts.iter().find(|e| e.value == "$5 million").unwrap();
+        assert_eq!(amount.metadata.as_ref().unwrap()["normalized"], "5000000");
+    }
+}
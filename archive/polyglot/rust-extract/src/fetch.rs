@@ -0,0 +1,307 @@
+//! Server-side fetch of a URL's text, guarded against SSRF
+//!
+//! Only http(s) is allowed, and the host must resolve exclusively to public
+//! addresses unless the policy says otherwise. The connection is pinned to
+//! the address that was checked, so a second DNS answer can't swap in an
+//! internal one. Redirects are not followed: the target would skip the checks.
+
+use lazy_static::lazy_static;
+use regex::Regex;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::time::Duration;
+
+use crate::compile_pattern;
+
+/// What `fetch_text` may fetch, and how much
+#[derive(Debug, Clone)]
+pub struct UrlPolicy {
+    /// If non-empty, only these hosts (and their subdomains) may be fetched
+    pub allow_hosts: Vec<String>,
+    /// Hosts (and their subdomains) that are always refused
+    pub deny_hosts: Vec<String>,
+    /// Permit loopback, private, link-local and other non-public addresses
+    pub allow_private: bool,
+    /// Budget for DNS, connect and the whole response
+    pub timeout: Duration,
+    /// Largest body accepted, in bytes
+    pub max_bytes: usize,
+}
+
+impl Default for UrlPolicy {
+    fn default() -> Self {
+        UrlPolicy {
+            allow_hosts: Vec::new(),
+            deny_hosts: Vec::new(),
+            allow_private: false,
+            timeout: Duration::from_secs(10),
+            max_bytes: 2 * 1024 * 1024,
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum FetchError {
+    #[error("invalid URL: {0}")]
+    InvalidUrl(#[from] url::ParseError),
+    #[error("unsupported scheme {0:?}; only http and https are allowed")]
+    Scheme(String),
+    #[error("host {0} is not allowed")]
+    Blocked(String),
+    #[error("could not resolve {0}")]
+    Resolve(String),
+    #[error("fetch timed out")]
+    Timeout,
+    #[error("fetch failed: {0}")]
+    Http(#[from] reqwest::Error),
+    #[error("upstream returned status {0}")]
+    Status(u16),
+    #[error("response exceeds {0} bytes")]
+    TooLarge(usize),
+}
+
+/// A fetched page, reduced to plain text
+#[derive(Debug, Clone)]
+pub struct FetchedPage {
+    pub url: String,
+    /// Bytes received, before HTML stripping
+    pub content_length: usize,
+    pub text: String,
+}
+
+fn host_matches(host: &str, patterns: &[String]) -> bool {
+    patterns.iter().any(|p| {
+        let p = p.trim_start_matches('.');
+        host.eq_ignore_ascii_case(p)
+            || (host.len() > p.len()
+                && host[host.len() - p.len()..].eq_ignore_ascii_case(p)
+                && host.as_bytes()[host.len() - p.len() - 1] == b'.')
+    })
+}
+
+fn is_public_v4(ip: Ipv4Addr) -> bool {
+    let [a, b, c, _] = ip.octets();
+    !(ip.is_private()
+        || ip.is_loopback()
+        || ip.is_link_local()
+        || ip.is_unspecified()
+        || ip.is_broadcast()
+        || ip.is_multicast()
+        || ip.is_documentation()
+        || a == 0
+        // Carrier-grade NAT, 100.64.0.0/10
+        || (a == 100 && (64..128).contains(&b))
+        // Benchmarking, 198.18.0.0/15
+        || (a == 198 && (18..20).contains(&b))
+        // IETF protocol assignments, 192.0.0.0/24
+        || (a == 192 && b == 0 && c == 0)
+        // Reserved, 240.0.0.0/4
+        || a >= 240)
+}
+
+/// The IPv4 address `ip` reaches: IPv4-mapped (::ffff:a.b.c.d),
+/// IPv4-compatible (::a.b.c.d), NAT64 (64:ff9b::a.b.c.d) or 6to4
+/// (2002:aabb:ccdd::)
+fn embedded_v4(ip: Ipv6Addr) -> Option<Ipv4Addr> {
+    let s = ip.segments();
+    let v4 = |high: u16, low: u16| Some(Ipv4Addr::from((u32::from(high) << 16) | u32::from(low)));
+    match s {
+        [0, 0, 0, 0, 0, 0xffff | 0, ..] | [0x64, 0xff9b, 0, 0, 0, 0, ..] => v4(s[6], s[7]),
+        [0x2002, ..] => v4(s[1], s[2]),
+        _ => None,
+    }
+}
+
+fn is_public_v6(ip: Ipv6Addr) -> bool {
+    if let Some(v4) = embedded_v4(ip) {
+        return is_public_v4(v4);
+    }
+    let first = ip.segments()[0];
+    !(ip.is_loopback()
+        || ip.is_unspecified()
+        || ip.is_multicast()
+        // Unique local, fc00::/7
+        || (first & 0xfe00) == 0xfc00
+        // Link-local, fe80::/10
+        || (first & 0xffc0) == 0xfe80)
+}
+
+/// Whether `ip` is a globally routable address
+pub fn is_public(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(v4) => is_public_v4(v4),
+        IpAddr::V6(v6) => is_public_v6(v6),
+    }
+}
+
+/// Check `url` against the policy and resolve the address to connect to
+async fn resolve_checked(url: &url::Url, policy: &UrlPolicy) -> Result<SocketAddr, FetchError> {
+    if !matches!(url.scheme(), "http" | "https") {
+        return Err(FetchError::Scheme(url.scheme().to_string()));
+    }
+    let host = url.host_str().ok_or_else(|| FetchError::Resolve(url.to_string()))?;
+    let bare_host = host.trim_start_matches('[').trim_end_matches(']');
+    if host_matches(bare_host, &policy.deny_hosts)
+        || (!policy.allow_hosts.is_empty() && !host_matches(bare_host, &policy.allow_hosts))
+    {
+        return Err(FetchError::Blocked(host.to_string()));
+    }
+
+    let port = url.port_or_known_default().unwrap_or(80);
+    let addrs: Vec<SocketAddr> = match url.host() {
+        Some(url::Host::Ipv4(ip)) => vec![SocketAddr::new(ip.into(), port)],
+        Some(url::Host::Ipv6(ip)) => vec![SocketAddr::new(ip.into(), port)],
+        _ => tokio::time::timeout(policy.timeout, tokio::net::lookup_host((host, port)))
+            .await
+            .map_err(|_| FetchError::Timeout)?
+            .map_err(|_| FetchError::Resolve(host.to_string()))?
+            .collect(),
+    };
+
+    // Every answer must be public: a client may pick any of them
+    if !policy.allow_private && addrs.iter().any(|a| !is_public(a.ip())) {
+        return Err(FetchError::Blocked(host.to_string()));
+    }
+    addrs.first().copied().ok_or_else(|| FetchError::Resolve(host.to_string()))
+}
+
+/// Fetch `url` under `policy` and return its text, with HTML stripped
+pub async fn fetch_text(url: &str, policy: &UrlPolicy) -> Result<FetchedPage, FetchError> {
+    let url = url::Url::parse(url)?;
+    let addr = resolve_checked(&url, policy).await?;
+
+    let mut client = reqwest::Client::builder()
+        .redirect(reqwest::redirect::Policy::none())
+        .timeout(policy.timeout)
+        .no_proxy();
+    if let Some(url::Host::Domain(domain)) = url.host() {
+        client = client.resolve(domain, addr);
+    }
+    let mut response = client.build()?.get(url.clone()).send().await.map_err(timeout_or_http)?;
+
+    if !response.status().is_success() {
+        return Err(FetchError::Status(response.status().as_u16()));
+    }
+    if response.content_length().is_some_and(|len| len > policy.max_bytes as u64) {
+        return Err(FetchError::TooLarge(policy.max_bytes));
+    }
+    let is_html = response.headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.contains("html"));
+
+    let mut body = Vec::new();
+    while let Some(chunk) = response.chunk().await.map_err(timeout_or_http)? {
+        if body.len() + chunk.len() > policy.max_bytes {
+            return Err(FetchError::TooLarge(policy.max_bytes));
+        }
+        body.extend_from_slice(&chunk);
+    }
+
+    let raw = String::from_utf8_lossy(&body);
+    let text = if is_html { strip_html(&raw) } else { raw.into_owned() };
+    Ok(FetchedPage { url: url.to_string(), content_length: body.len(), text })
+}
+
+fn timeout_or_http(err: reqwest::Error) -> FetchError {
+    if err.is_timeout() {
+        FetchError::Timeout
+    } else {
+        FetchError::Http(err)
+    }
+}
+
+lazy_static! {
+    // Elements whose content is never page text
+    static ref HIDDEN_ELEMENTS: Regex = compile_pattern(
+        r"(?is)<!--.*?-->|<(script|style|noscript|template|head)\b[^>]*>.*?</(?:script|style|noscript|template|head)\s*>"
+    ).unwrap();
+    // Tags that end a line of text
+    static ref BLOCK_TAGS: Regex = compile_pattern(
+        r"(?i)</?(?:p|div|br|li|ul|ol|tr|td|th|table|h[1-6]|section|article|header|footer|blockquote|pre)\b[^>]*>"
+    ).unwrap();
+    static ref OTHER_TAGS: Regex = compile_pattern(r"<[^>]*>").unwrap();
+    static ref HTML_ENTITY: Regex = compile_pattern(r"&(#[0-9]{1,7}|#[xX][0-9a-fA-F]{1,6}|[a-zA-Z]{2,6});").unwrap();
+    static ref BLANK_RUNS: Regex = compile_pattern(r"[ \t\r\f\v]*\n\s*").unwrap();
+    static ref SPACE_RUNS: Regex = compile_pattern(r"[ \t\r\f\v]+").unwrap();
+}
+
+fn decode_entity(entity: &str) -> Option<String> {
+    let named = match entity {
+        "amp" => '&',
+        "lt" => '<',
+        "gt" => '>',
+        "quot" => '"',
+        "apos" => '\'',
+        "nbsp" => ' ',
+        "ndash" => '–',
+        "mdash" => '—',
+        "hellip" => '…',
+        "copy" => '©',
+        "euro" => '€',
+        "pound" => '£',
+        _ => {
+            let code = match entity.strip_prefix("#x").or_else(|| entity.strip_prefix("#X")) {
+                Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                None => entity.strip_prefix('#')?.parse().ok()?,
+            };
+            char::from_u32(code)?
+        }
+    };
+    Some(named.to_string())
+}
+
+/// Reduce an HTML page to its visible text, one line per block element
+pub fn strip_html(html: &str) -> String {
+    let text = HIDDEN_ELEMENTS.replace_all(html, " ");
+    let text = BLOCK_TAGS.replace_all(&text, "\n");
+    let text = OTHER_TAGS.replace_all(&text, "");
+    let text = HTML_ENTITY.replace_all(&text, |cap: &regex::Captures| {
+        decode_entity(&cap[1]).unwrap_or_else(|| cap[0].to_string())
+    });
+    let text = SPACE_RUNS.replace_all(&text, " ");
+    BLANK_RUNS.replace_all(&text, "\n").trim().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strip_html() {
+        let html = "<html><head><title>x</title><style>p{}</style></head><body>\
+                    <h1>Report</h1><p>Paid&nbsp;<b>$5 million</b> to Acme &amp; Co.</p>\
+                    <script>var a = 'John Smith';</script><!-- Jane Doe --></body></html>";
+        assert_eq!(strip_html(html), "Report\nPaid $5 million to Acme & Co.");
+    }
+
+    #[tokio::test]
+    async fn test_blocks_internal_targets() {
+        let policy = UrlPolicy::default();
+        for url in [
+            "http://127.0.0.1/",
+            "http://10.0.0.8:8080/admin",
+            "http://169.254.169.254/latest/meta-data/",
+            "http://[::1]/",
+            "http://[::ffff:192.168.1.1]/",
+            "http://[::10.0.0.1]/",
+            "http://[64:ff9b::a00:1]/",
+            "http://[2002:a00:1::]/",
+            "http://192.0.0.8/",
+            "http://240.0.0.1/",
+            "http://localhost/",
+        ] {
+            assert!(matches!(fetch_text(url, &policy).await, Err(FetchError::Blocked(_))), "{}", url);
+        }
+        // An embedded public address stays public
+        for ip in ["64:ff9b::808:808", "2002:808:808::1", "2606:4700::1111"] {
+            assert!(is_public(ip.parse().unwrap()), "{}", ip);
+        }
+        assert!(matches!(fetch_text("file:///etc/passwd", &policy).await, Err(FetchError::Scheme(_))));
+        assert!(matches!(fetch_text("gopher://example.com/", &policy).await, Err(FetchError::Scheme(_))));
+
+        let deny = UrlPolicy { deny_hosts: vec!["example.com".to_string()], ..Default::default() };
+        assert!(matches!(fetch_text("https://docs.example.com/", &deny).await, Err(FetchError::Blocked(_))));
+        let allow = UrlPolicy { allow_hosts: vec!["example.org".to_string()], ..Default::default() };
+        assert!(matches!(fetch_text("https://example.com/", &allow).await, Err(FetchError::Blocked(_))));
+    }
+}
@@ -0,0 +1,180 @@
+//! Re-extracting an edited document
+//!
+//! A live editor that changes one paragraph of a long document shouldn't pay
+//! for a full extraction. `reextract_range` runs the extractors over the
+//! edited range plus a margin either side, the way a batch runs one
+//! `window`, and keeps the previous result's entities outside it, shifting
+//! those after the edit by the change in length. Matches are far shorter
+//! than the margin, so an entity outside it is the one a full run finds.
+//!
+//! The whole-document heuristics `window` warns about (acronym repeat
+//! counts, the person sentence-start check) only see the re-extracted range,
+//! so results near those thresholds can differ from a full run.
+
+use std::collections::HashSet;
+
+use crate::window::{self, Window, WINDOW_OVERLAP_BYTES};
+use crate::{
+    extract_unfinalized, extract_with_options, finish, Entity, EntityKind, ExtractOptions, ExtractionResult,
+    BUILTIN_EXTRACTORS,
+};
+
+/// Bytes either side of an edit whose entities are found afresh
+const MARGIN_BYTES: usize = WINDOW_OVERLAP_BYTES;
+
+/// Drop what the whole-document passes added (`coref` mentions,
+/// `person_offset` and `alias_of` links), so `finish` rebuilds it in the
+/// new offsets
+fn strip_links(result: &mut ExtractionResult) {
+    result.persons.retain(|e| !e.metadata.as_ref().is_some_and(|m| m.contains_key("coref")));
+    for entity in result.entities_mut() {
+        if let Some(metadata) = &mut entity.metadata {
+            metadata.remove("person_offset");
+            metadata.remove("alias_of");
+            if metadata.is_empty() {
+                entity.metadata = None;
+            }
+        }
+    }
+}
+
+/// Previous entities before `own_start`, the fresh ones, then previous
+/// entities from `old_own_end` on, shifted into the new text's offsets
+fn splice(prev: Vec<Entity>, fresh: Vec<Entity>, own_start: usize, old_own_end: usize, len: usize, prev_len: usize) -> Vec<Entity> {
+    let (before, rest): (Vec<Entity>, Vec<Entity>) = prev.into_iter().partition(|e| e.start < own_start);
+    let after = rest.into_iter()
+        .filter(|e| e.start >= old_own_end)
+        .map(|mut e| {
+            e.start = e.start + len - prev_len;
+            e.end = e.end + len - prev_len;
+            e
+        });
+    let mut entities: Vec<Entity> = before.into_iter().chain(fresh).chain(after).collect();
+    entities.sort_by(|a, b| a.order_key().cmp(&b.order_key()));
+    entities
+}
+
+/// Update `prev`, the result of `extract_with_options(old_text, options)`,
+/// for `text`, in which `changed` is the byte range holding the edit
+///
+/// `changed` is in `text`'s offsets: a deletion is an empty range where the
+/// removed text was, an insertion or replacement the range of the new text.
+/// The old text's length comes from `prev.text_len`. Entities within
+/// 4 KB of the edit are extracted again; the rest are reused, with
+/// paragraph tags, ids, links and UTF-16 offsets recomputed for the whole
+/// result. Pass the same `options` as for `prev`.
+///
+/// A deduplicated kind (persons, dates, ...) keeps one entity per value for
+/// the whole document, so an edit removing the first mention of a value may
+/// promote a later one that `prev` never held; that case, a `prev` that
+/// timed out or was a preview, and a `changed` inconsistent with the two
+/// lengths all fall back to a full extraction.
+pub fn reextract_range(
+    text: &str,
+    prev: &ExtractionResult,
+    changed: (usize, usize),
+    options: &ExtractOptions,
+) -> ExtractionResult {
+    let started = std::time::Instant::now();
+    let (start, end) = changed;
+    let (len, prev_len) = (text.len(), prev.text_len);
+    let consistent = start <= end
+        && end <= len
+        && text.is_char_boundary(start)
+        && text.is_char_boundary(end)
+        // The edit replaced prev's start..end + prev_len - len
+        && end + prev_len >= len + start;
+    if !consistent || prev.timed_out || prev.preview_truncated || !prev.errors.is_empty() {
+        return extract_with_options(text, options);
+    }
+
+    let own_start = window::cut_point(text, start.saturating_sub(MARGIN_BYTES));
+    let own_end = window::char_ceil(text, (end + MARGIN_BYTES).min(len));
+    let region = Window {
+        start: window::char_floor(text, own_start.saturating_sub(WINDOW_OVERLAP_BYTES)),
+        end: window::char_ceil(text, (own_end + WINDOW_OVERLAP_BYTES).min(len)),
+        own_start,
+        own_end,
+    };
+    let extracted = extract_unfinalized(&text[region.start..region.end], options, &BUILTIN_EXTRACTORS);
+    let timed_out = extracted.timed_out;
+    if !extracted.errors.is_empty() {
+        return extract_with_options(text, options);
+    }
+    let mut fresh = window::merge(vec![(region, extracted)]);
+    let old_own_end = own_end + prev_len - len;
+
+    let mut prev = prev.clone();
+    strip_links(&mut prev);
+    let mut result = ExtractionResult::default();
+    for &kind in EntityKind::ALL {
+        let old = std::mem::take(prev.field_mut(kind));
+        let dropped: Vec<String> = old.iter()
+            .filter(|e| (own_start..old_own_end).contains(&e.start))
+            .map(|e| e.value.to_lowercase())
+            .collect();
+        let mut entities = splice(old, std::mem::take(fresh.field_mut(kind)), own_start, old_own_end, len, prev_len);
+
+        if window::VALUE_DEDUPED.contains(&kind) {
+            let mut seen = HashSet::new();
+            entities.retain(|e| seen.insert(e.value.to_lowercase()));
+            if dropped.iter().any(|value| !seen.contains(value)) {
+                return extract_with_options(text, options);
+            }
+        }
+        *result.field_mut(kind) = entities;
+    }
+    result.custom = splice(std::mem::take(&mut prev.custom), fresh.custom, own_start, old_own_end, len, prev_len);
+
+    let mut result = finish(result, text, options, started.elapsed());
+    result.timed_out = timed_out;
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_edit_middle_paragraph() {
+        let filler = "The board met again and reviewed the minutes in detail. ".repeat(200);
+        let old_text = format!(
+            "Jeffrey Epstein wired $5 million on January 15, 2024.\n\n{filler}\n\n\
+             Call 555-123-4567 about the Acme Holdings transfer.\n\n{filler}\n\n\
+             Ghislaine Maxwell emailed jeff@example.com on March 3, 2005."
+        );
+        let options = ExtractOptions { tag_paragraphs: true, ..Default::default() };
+        let prev = extract_with_options(&old_text, &options);
+
+        let edit_start = old_text.find("Acme Holdings").unwrap();
+        let replacement = "Initech Corporation, which paid $20,000,";
+        let text = old_text.replacen("Acme Holdings", replacement, 1);
+        let delta = text.len() - old_text.len();
+        let result = reextract_range(&text, &prev, (edit_start, edit_start + replacement.len()), &options);
+
+        let before: Vec<&Entity> = prev.entities().filter(|e| e.end <= 60).collect();
+        assert!(before.iter().any(|e| e.value == "Jeffrey Epstein"));
+        for entity in &before {
+            assert!(result.entities().any(|e| e == *entity));
+        }
+
+        let tail = old_text.rfind("Ghislaine").unwrap();
+        let after: Vec<&Entity> = prev.entities().filter(|e| e.start >= tail).collect();
+        assert!(after.iter().any(|e| e.value == "jeff@example.com"));
+        for entity in after {
+            let shifted = result.entities()
+                .find(|e| e.value == entity.value && e.entity_type == entity.entity_type)
+                .unwrap();
+            assert_eq!((shifted.start, shifted.end), (entity.start + delta, entity.end + delta));
+            assert_eq!(&text[shifted.start..shifted.end], entity.value);
+        }
+
+        assert!(result.amounts.iter().any(|e| e.value == "$20,000"));
+        assert!(!result.organizations.iter().any(|e| e.value.contains("Acme")));
+        let full = extract_with_options(&text, &options);
+        assert_eq!(
+            ExtractionResult { processing_time_ms: 0, ..result },
+            ExtractionResult { processing_time_ms: 0, ..full },
+        );
+    }
+}
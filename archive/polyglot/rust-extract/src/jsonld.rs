@@ -0,0 +1,130 @@
+//! schema.org JSON-LD for knowledge graph ingestion
+//!
+//! `to_jsonld` turns a result into one `@graph`: a `schema:CreativeWork`
+//! node for the document, which `schema:mentions` one node per distinct
+//! entity. Node `@id`s are built from `Entity::stable_id`, so "Jeffrey
+//! Epstein" is the same node in every document and repeated mentions in one
+//! document collapse into it. Kinds schema.org has no type for become a
+//! `schema:Thing` whose `schema:additionalType` is the entity type.
+
+use serde_json::{json, Map, Value};
+use sha2::{Digest, Sha256};
+
+use crate::{Entity, ExtractionResult};
+
+/// Prefix of every `@id`; entity and document ids follow it
+const ID_PREFIX: &str = "urn:l-extract:";
+
+/// The node's `@type` and its properties besides `@id`
+fn node(entity: &Entity) -> (&'static str, Map<String, Value>) {
+    let normalized = entity.metadata.as_ref().and_then(|m| m.get("normalized"));
+    let mut properties = Map::new();
+    let mut set = |key: &str, value: Value| {
+        properties.insert(key.to_string(), value);
+    };
+    let schema_type = match entity.entity_type.as_str() {
+        "person" => "schema:Person",
+        "organization" => "schema:Organization",
+        "location" => "schema:Place",
+        "drug" => "schema:Drug",
+        "amount" => {
+            if let Some(value) = normalized.and_then(|n| n.parse::<f64>().ok()) {
+                set("schema:value", json!(value));
+            }
+            if let Some(code) = entity.metadata.as_ref().and_then(|m| m.get("currency")) {
+                set("schema:currency", json!(code));
+            }
+            "schema:MonetaryAmount"
+        }
+        "email" => {
+            set("schema:email", json!(normalized.unwrap_or(&entity.value)));
+            "schema:ContactPoint"
+        }
+        "phone" => {
+            set("schema:telephone", json!(entity.value));
+            "schema:ContactPoint"
+        }
+        "url" => {
+            set("schema:url", json!(entity.value));
+            "schema:WebPage"
+        }
+        "isbn" => {
+            set("schema:isbn", json!(normalized.unwrap_or(&entity.value)));
+            "schema:Book"
+        }
+        "icd_code" => {
+            set("schema:codeValue", json!(entity.value));
+            set("schema:codingSystem", json!("ICD-10"));
+            "schema:MedicalCode"
+        }
+        other => {
+            set("schema:additionalType", json!(other));
+            "schema:Thing"
+        }
+    };
+    properties.insert("schema:name".to_string(), json!(entity.value));
+    (schema_type, properties)
+}
+
+/// `result`, extracted from `text`, as a schema.org JSON-LD document
+///
+/// Every entity kind is included, custom ones too. A node takes its name
+/// from the first mention in document order.
+pub fn to_jsonld(text: &str, result: &ExtractionResult) -> Value {
+    let mut entities: Vec<&Entity> = result.entities().collect();
+    entities.sort_by(|a, b| a.order_key().cmp(&b.order_key()));
+
+    let mut nodes: Vec<Value> = Vec::new();
+    let mut mentions: Vec<Value> = Vec::new();
+    let mut seen = std::collections::HashSet::new();
+    for entity in entities {
+        let id = format!("{}entity:{}", ID_PREFIX, entity.stable_id());
+        if !seen.insert(id.clone()) {
+            continue;
+        }
+        let (schema_type, mut properties) = node(entity);
+        properties.insert("@id".to_string(), json!(id));
+        properties.insert("@type".to_string(), json!(schema_type));
+        nodes.push(Value::Object(properties));
+        mentions.push(json!({ "@id": id }));
+    }
+
+    let digest: String = Sha256::digest(text.as_bytes())[..8].iter().map(|b| format!("{:02x}", b)).collect();
+    let document = json!({
+        "@id": format!("{}document:{}", ID_PREFIX, digest),
+        "@type": "schema:CreativeWork",
+        "schema:mentions": mentions,
+    });
+    nodes.insert(0, document);
+    json!({
+        "@context": { "schema": "https://schema.org/" },
+        "@graph": nodes,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::extract_all;
+
+    #[test]
+    fn test_person_node() {
+        let text = "Jeffrey Epstein wired $5 million to Acme Holdings Inc. Later Jeffrey Epstein flew to Palm Beach.";
+        let result = extract_all(text);
+        let jsonld = to_jsonld(text, &result);
+        let graph = jsonld["@graph"].as_array().unwrap();
+
+        let person = result.persons.iter().find(|e| e.value == "Jeffrey Epstein").unwrap();
+        let id = format!("urn:l-extract:entity:{}", person.stable_id());
+        let nodes: Vec<&Value> = graph.iter().filter(|n| n["@id"] == id.as_str()).collect();
+        assert_eq!(nodes.len(), 1);
+        assert_eq!(nodes[0]["@type"], "schema:Person");
+        assert_eq!(nodes[0]["schema:name"], "Jeffrey Epstein");
+
+        assert_eq!(graph[0]["@type"], "schema:CreativeWork");
+        assert!(graph[0]["schema:mentions"].as_array().unwrap().contains(&json!({ "@id": id })));
+        let amount = graph.iter().find(|n| n["schema:name"] == "$5 million").unwrap();
+        assert_eq!((amount["@type"].as_str(), amount["schema:currency"].as_str()), (Some("schema:MonetaryAmount"), Some("USD")));
+        assert_eq!(amount["schema:value"], 5_000_000.0);
+    }
+}
@@ -3,7 +3,7 @@
 //! Parallel entity extraction 10x faster than Python regex
 //!
 //! Architecture:
-//! ```
+//! ```text
 //! [Doc batch]
 //!       ↓
 //! ┌─────────────────────────────────────┐
@@ -16,31 +16,303 @@
 //!       ↓ merge
 //! [Extraction Result JSON]
 //! ```
+//!
+//! Output is reproducible: the same text and options give the same result,
+//! byte for byte once serialized, apart from `processing_time_ms`. Parallel
+//! work is merged in a fixed order rather than completion order, sorts tie-break
+//! on `Entity::order_key`, and metadata serializes with its keys sorted.
 
 use lazy_static::lazy_static;
 use rayon::prelude::*;
 use regex::Regex;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+pub mod client;
+pub mod config;
+pub mod dedupe;
+pub mod diff;
+pub mod extractor;
+pub mod fetch;
+pub mod incremental;
+pub mod jsonld;
+pub mod minimal;
+pub mod msgpack;
+mod nfc;
+pub mod relationships;
+pub mod relevance;
+pub mod roles;
+pub mod sentences;
+pub mod structured;
+pub mod summary;
+pub mod table;
+pub mod transactions;
+mod window;
+
+pub use config::{ExtractorConfig, PatternConfig, PatternExtractor};
+pub use dedupe::dedupe_batch;
+pub use diff::{diff, extract_new, ExtractionDiff};
+pub use extractor::{ConfidenceFn, Extractor, ExtractorBuilder};
+pub use incremental::reextract_range;
+pub use jsonld::to_jsonld;
+pub use minimal::{from_minimal, to_minimal, MinimalEntity, MinimalResult};
+pub use msgpack::{from_bytes, to_bytes};
+pub use relationships::{extract_relationships, extract_relationships_with, Relationship};
+pub use relevance::{relevance, relevance_with};
+pub use roles::{extract_roles, PersonRole};
+pub use sentences::group_by_sentence;
+pub use structured::{extract_csv, extract_structured};
+pub use summary::{aggregate, BatchSummary};
+pub use table::extract_amount_table;
+pub use transactions::{extract_transactions, Transaction};
 
 // =============================================================================
 // DATA STRUCTURES
 // =============================================================================
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, thiserror::Error)]
+pub enum ExtractError {
+    #[error("failed to build thread pool: {0}")]
+    ThreadPool(#[from] rayon::ThreadPoolBuildError),
+    #[error("invalid CSV: {0}")]
+    Csv(#[from] csv::Error),
+    #[error("invalid MessagePack: {0}")]
+    Decode(#[from] rmp_serde::decode::Error),
+    #[error("{} entities don't match their offsets: {}", .0.len(), describe_mismatches(.0))]
+    OffsetMismatch(Vec<OffsetMismatch>),
+    #[error("can't read config: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("invalid config: {0}")]
+    Config(#[from] serde_json::Error),
+    #[error("invalid pattern {name:?}: {source}")]
+    Pattern { name: String, source: regex::Error },
+}
+
+/// An entity whose `value` differs from `text[start..end]`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct OffsetMismatch {
+    pub entity_type: String,
+    pub value: String,
+    pub start: usize,
+    pub end: usize,
+    /// What the offsets actually select, or `None` if they are out of
+    /// bounds or not on char boundaries
+    pub found: Option<String>,
+}
+
+fn describe_mismatches(mismatches: &[OffsetMismatch]) -> String {
+    mismatches.iter()
+        .map(|m| match &m.found {
+            Some(found) => format!("{} {:?} at {}..{} reads {:?}", m.entity_type, m.value, m.start, m.end, found),
+            None => format!("{} {:?} at {}..{} is not a valid range", m.entity_type, m.value, m.start, m.end),
+        })
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+/// Entity families, one per `ExtractionResult` vector
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EntityKind {
+    Date,
+    Person,
+    Organization,
+    Amount,
+    Location,
+    Email,
+    Phone,
+    Url,
+    PostalCode,
+    Citation,
+    Age,
+    Percent,
+    Ratio,
+    Redaction,
+    Quantity,
+    Ein,
+    IcdCode,
+    Drug,
+    Coordinate,
+    Reference,
+    Isbn,
+    Doi,
+    LicensePlate,
+    Duration,
+    WorkTitle,
+    RoutingNumber,
+}
+
+impl EntityKind {
+    pub const ALL: &'static [EntityKind] = &[
+        EntityKind::Date,
+        EntityKind::Person,
+        EntityKind::Organization,
+        EntityKind::Amount,
+        EntityKind::Location,
+        EntityKind::Email,
+        EntityKind::Phone,
+        EntityKind::Url,
+        EntityKind::PostalCode,
+        EntityKind::Citation,
+        EntityKind::Age,
+        EntityKind::Percent,
+        EntityKind::Ratio,
+        EntityKind::Redaction,
+        EntityKind::Quantity,
+        EntityKind::Ein,
+        EntityKind::IcdCode,
+        EntityKind::Drug,
+        EntityKind::Coordinate,
+        EntityKind::Reference,
+        EntityKind::Isbn,
+        EntityKind::Doi,
+        EntityKind::LicensePlate,
+        EntityKind::Duration,
+        EntityKind::WorkTitle,
+        EntityKind::RoutingNumber,
+    ];
+
+    /// The `entity_type` string on entities of this kind
+    pub fn as_str(self) -> &'static str {
+        match self {
+            EntityKind::Date => "date",
+            EntityKind::Person => "person",
+            EntityKind::Organization => "organization",
+            EntityKind::Amount => "amount",
+            EntityKind::Location => "location",
+            EntityKind::Email => "email",
+            EntityKind::Phone => "phone",
+            EntityKind::Url => "url",
+            EntityKind::PostalCode => "postal_code",
+            EntityKind::Citation => "citation",
+            EntityKind::Age => "age",
+            EntityKind::Percent => "percent",
+            EntityKind::Ratio => "ratio",
+            EntityKind::Redaction => "redaction",
+            EntityKind::Quantity => "quantity",
+            EntityKind::Ein => "ein",
+            EntityKind::IcdCode => "icd_code",
+            EntityKind::Drug => "drug",
+            EntityKind::Coordinate => "coordinate",
+            EntityKind::Reference => "reference",
+            EntityKind::Isbn => "isbn",
+            EntityKind::Doi => "doi",
+            EntityKind::LicensePlate => "license_plate",
+            EntityKind::Duration => "duration",
+            EntityKind::WorkTitle => "work_title",
+            EntityKind::RoutingNumber => "routing_number",
+        }
+    }
+
+    /// Selector strings accepted by `extract_types`, canonical name first
+    pub fn selectors(self) -> &'static [&'static str] {
+        match self {
+            EntityKind::Date => &["dates"],
+            EntityKind::Person => &["persons"],
+            EntityKind::Organization => &["organizations", "orgs"],
+            EntityKind::Amount => &["amounts"],
+            EntityKind::Location => &["locations"],
+            EntityKind::Email => &["emails"],
+            EntityKind::Phone => &["phones"],
+            EntityKind::Url => &["urls"],
+            EntityKind::PostalCode => &["postal_codes"],
+            EntityKind::Citation => &["citations"],
+            EntityKind::Age => &["ages"],
+            EntityKind::Percent => &["percentages", "percents"],
+            EntityKind::Ratio => &["ratios"],
+            EntityKind::Redaction => &["redactions"],
+            EntityKind::Quantity => &["quantities"],
+            EntityKind::Ein => &["eins"],
+            EntityKind::IcdCode => &["icd_codes", "medical"],
+            EntityKind::Drug => &["drugs", "medical"],
+            EntityKind::Coordinate => &["coordinates"],
+            EntityKind::Reference => &["references"],
+            EntityKind::Isbn => &["isbns", "identifiers"],
+            EntityKind::Doi => &["dois", "identifiers"],
+            EntityKind::LicensePlate => &["license_plates", "plates"],
+            EntityKind::Duration => &["durations"],
+            EntityKind::WorkTitle => &["work_titles", "titles"],
+            EntityKind::RoutingNumber => &["routing_numbers"],
+        }
+    }
+
+    /// The first kind `selector` names; see `selected` for group selectors
+    pub fn from_selector(selector: &str) -> Option<EntityKind> {
+        EntityKind::ALL.iter().copied().find(|k| k.selectors().contains(&selector))
+    }
+
+    /// Every kind `selector` names: one, or several for a group ("medical")
+    pub fn selected(selector: &str) -> impl Iterator<Item = EntityKind> + '_ {
+        EntityKind::ALL.iter().copied().filter(move |k| k.selectors().contains(&selector))
+    }
+
+    /// Confidence of this kind's typical match, before the per-pattern and
+    /// cue adjustments its extractor makes
+    pub fn default_confidence(self) -> f64 {
+        match self {
+            EntityKind::Location | EntityKind::WorkTitle | EntityKind::RoutingNumber => 0.70,
+            EntityKind::Person | EntityKind::LicensePlate => 0.75,
+            EntityKind::Organization | EntityKind::Phone | EntityKind::Duration => 0.80,
+            EntityKind::Date
+            | EntityKind::PostalCode
+            | EntityKind::Citation
+            | EntityKind::Age
+            | EntityKind::Ratio
+            | EntityKind::Quantity
+            | EntityKind::Drug
+            | EntityKind::Reference => 0.85,
+            EntityKind::Amount
+            | EntityKind::Percent
+            | EntityKind::Ein
+            | EntityKind::IcdCode
+            | EntityKind::Coordinate => 0.90,
+            EntityKind::Email | EntityKind::Url | EntityKind::Redaction | EntityKind::Isbn | EntityKind::Doi => 0.95,
+        }
+    }
+
+    /// Whether entities of this kind carry context unless `context_types`
+    /// says otherwise. Emails, phones and URLs are self-describing.
+    pub fn context_by_default(self) -> bool {
+        !matches!(self, EntityKind::Email | EntityKind::Phone | EntityKind::Url)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Entity {
+    /// Index within its result, in document order; see `ExtractionResult::renumber`
+    #[serde(default)]
+    pub id: usize,
     pub value: String,
     pub entity_type: String,
     pub start: usize,
     pub end: usize,
+    /// `start` and `end` in UTF-16 code units (JavaScript string indices),
+    /// with `ExtractOptions.utf16_offsets`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub utf16_start: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub utf16_end: Option<usize>,
     pub confidence: f64,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub context: Option<String>,
-    #[serde(skip_serializing_if = "Option::is_none")]
+    /// Serialized with keys sorted, so equal entities encode identically
+    #[serde(skip_serializing_if = "Option::is_none", serialize_with = "serialize_sorted")]
     pub metadata: Option<HashMap<String, String>>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+fn serialize_sorted<S: serde::Serializer>(
+    metadata: &Option<HashMap<String, String>>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    let sorted: Option<std::collections::BTreeMap<&String, &String>> =
+        metadata.as_ref().map(|m| m.iter().collect());
+    sorted.serialize(serializer)
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
 pub struct ExtractionResult {
     pub dates: Vec<Entity>,
     pub persons: Vec<Entity>,
@@ -50,379 +322,6132 @@ pub struct ExtractionResult {
     pub emails: Vec<Entity>,
     pub phones: Vec<Entity>,
     pub urls: Vec<Entity>,
+    #[serde(default)]
+    pub postal_codes: Vec<Entity>,
+    #[serde(default)]
+    pub citations: Vec<Entity>,
+    #[serde(default)]
+    pub ages: Vec<Entity>,
+    #[serde(default)]
+    pub percentages: Vec<Entity>,
+    #[serde(default)]
+    pub ratios: Vec<Entity>,
+    #[serde(default)]
+    pub redactions: Vec<Entity>,
+    #[serde(default)]
+    pub quantities: Vec<Entity>,
+    #[serde(default)]
+    pub eins: Vec<Entity>,
+    #[serde(default)]
+    pub icd_codes: Vec<Entity>,
+    #[serde(default)]
+    pub drugs: Vec<Entity>,
+    #[serde(default)]
+    pub coordinates: Vec<Entity>,
+    #[serde(default)]
+    pub references: Vec<Entity>,
+    #[serde(default)]
+    pub isbns: Vec<Entity>,
+    #[serde(default)]
+    pub dois: Vec<Entity>,
+    #[serde(default)]
+    pub license_plates: Vec<Entity>,
+    #[serde(default)]
+    pub durations: Vec<Entity>,
+    #[serde(default)]
+    pub work_titles: Vec<Entity>,
+    #[serde(default)]
+    pub routing_numbers: Vec<Entity>,
+    /// Entities from custom `EntityExtractor`s that don't target a kind
+    #[serde(default)]
+    pub custom: Vec<Entity>,
     pub total_count: usize,
     pub processing_time_ms: u64,
+    /// Set when `ExtractOptions.timeout` cut extraction short; the vectors
+    /// hold whatever was found before the deadline
+    #[serde(default)]
+    pub timed_out: bool,
+    /// Set by `extract_preview` when the text was longer than the preview
+    #[serde(default)]
+    pub preview_truncated: bool,
+    /// Byte length of the text a full extraction ran over; what
+    /// `reextract_range` measures an edit against
+    #[serde(default)]
+    pub text_len: usize,
+    /// One message per extractor that panicked ("custom: extraction
+    /// panicked: ..."); its vector holds nothing from it, the others are
+    /// complete
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<String>,
 }
 
-// =============================================================================
-// REGEX PATTERNS (compiled once)
-// =============================================================================
+impl Entity {
+    /// Total document order: start, end, type, value
+    pub fn order_key(&self) -> (usize, usize, &str, &str) {
+        (self.start, self.end, &self.entity_type, &self.value)
+    }
 
-lazy_static! {
-    // Dates - multiple formats
-    static ref DATE_PATTERNS: Vec<Regex> = vec![
-        // ISO: 2024-01-15
-        Regex::new(r"\b(\d{4})-(\d{2})-(\d{2})\b").unwrap(),
-        // US: 01/15/2024 or 1/15/24
-        Regex::new(r"\b(\d{1,2})/(\d{1,2})/(\d{2,4})\b").unwrap(),
-        // EU: 15-01-2024 or 15.01.2024
-        Regex::new(r"\b(\d{1,2})[-.](\d{1,2})[-.](\d{2,4})\b").unwrap(),
-        // Written: January 15, 2024 or Jan 15 2024
-        Regex::new(r"\b(Jan(?:uary)?|Feb(?:ruary)?|Mar(?:ch)?|Apr(?:il)?|May|June?|July?|Aug(?:ust)?|Sep(?:t(?:ember)?)?|Oct(?:ober)?|Nov(?:ember)?|Dec(?:ember)?)\s+(\d{1,2})(?:st|nd|rd|th)?,?\s*(\d{4})\b").unwrap(),
-        // Month Year: January 2024
-        Regex::new(r"\b(Jan(?:uary)?|Feb(?:ruary)?|Mar(?:ch)?|Apr(?:il)?|May|June?|July?|Aug(?:ust)?|Sep(?:t(?:ember)?)?|Oct(?:ober)?|Nov(?:ember)?|Dec(?:ember)?)\s+(\d{4})\b").unwrap(),
-    ];
+    /// Comparison key for matching the same entity across documents
+    ///
+    /// Uses `metadata["normalized"]` when an extractor provides one (amounts),
+    /// otherwise the lowercased value with whitespace collapsed.
+    pub fn normalized_value(&self) -> String {
+        let raw = self.metadata.as_ref()
+            .and_then(|m| m.get("normalized"))
+            .unwrap_or(&self.value);
+        raw.split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase()
+    }
 
-    // Person names - sophisticated patterns
-    static ref PERSON_PATTERNS: Vec<Regex> = vec![
-        // Full name: John Smith, John A. Smith, John Allen Smith
-        Regex::new(r"\b([A-Z][a-z]+(?:\s+[A-Z]\.?)?\s+[A-Z][a-z]+(?:\s+(?:Jr|Sr|III|IV|II)\.?)?)\b").unwrap(),
-        // Title + Name: Mr. John Smith, Dr. Jane Doe
-        Regex::new(r"\b((?:Mr|Mrs|Ms|Miss|Dr|Prof|Rev|Hon|Sir|Dame)\.?\s+[A-Z][a-z]+(?:\s+[A-Z][a-z]+)+)\b").unwrap(),
-        // Last, First: Smith, John
-        Regex::new(r"\b([A-Z][a-z]+,\s+[A-Z][a-z]+(?:\s+[A-Z]\.)?)\b").unwrap(),
-    ];
+    /// Stable cross-document identifier: the first 16 hex digits of
+    /// SHA-256 over `entity_type`, a NUL, and `normalized_value()`
+    ///
+    /// Offsets and context play no part, so the same name gets the same ID in
+    /// every document, run, and machine.
+    pub fn stable_id(&self) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(self.entity_type.as_bytes());
+        hasher.update([0]);
+        hasher.update(self.normalized_value().as_bytes());
+        hasher.finalize()[..8].iter().map(|b| format!("{:02x}", b)).collect()
+    }
+}
 
-    // Organizations
-    static ref ORG_PATTERNS: Vec<Regex> = vec![
-        // Company suffixes
-        Regex::new(r"\b([A-Z][A-Za-z]*(?:\s+[A-Z][A-Za-z]*)*\s+(?:Inc|Corp|LLC|Ltd|LLP|Co|Company|Corporation|Group|Holdings|Partners|Associates|Foundation|Institute|University|Bank|Trust|Fund|Capital|Ventures|International|Worldwide|Global)\.?)\b").unwrap(),
-        // The X Organization/Foundation
-        Regex::new(r"\b(The\s+[A-Z][A-Za-z]*(?:\s+[A-Z][A-Za-z]*)*\s+(?:Organization|Foundation|Institute|Association|Society|Council|Committee|Commission|Agency|Bureau|Department|Ministry))\b").unwrap(),
-        // Acronyms: FBI, CIA, NSA
-        Regex::new(r"\b([A-Z]{2,6})\b").unwrap(),
-    ];
+impl ExtractionResult {
+    /// Every entity across all types, in field order
+    pub fn entities(&self) -> impl Iterator<Item = &Entity> {
+        self.dates.iter()
+            .chain(&self.persons)
+            .chain(&self.organizations)
+            .chain(&self.amounts)
+            .chain(&self.locations)
+            .chain(&self.emails)
+            .chain(&self.phones)
+            .chain(&self.urls)
+            .chain(&self.postal_codes)
+            .chain(&self.citations)
+            .chain(&self.ages)
+            .chain(&self.percentages)
+            .chain(&self.ratios)
+            .chain(&self.redactions)
+            .chain(&self.quantities)
+            .chain(&self.eins)
+            .chain(&self.icd_codes)
+            .chain(&self.drugs)
+            .chain(&self.coordinates)
+            .chain(&self.references)
+            .chain(&self.isbns)
+            .chain(&self.dois)
+            .chain(&self.license_plates)
+            .chain(&self.durations)
+            .chain(&self.work_titles)
+            .chain(&self.routing_numbers)
+            .chain(&self.custom)
+    }
 
-    // Money amounts
-    static ref AMOUNT_PATTERNS: Vec<Regex> = vec![
-        // $1,234.56 or $1234.56
-        Regex::new(r"(\$|USD|EUR|€|£|GBP|¥|JPY)\s*(\d{1,3}(?:,\d{3})*(?:\.\d{2})?)").unwrap(),
-        // 1,234.56 USD
-        Regex::new(r"(\d{1,3}(?:,\d{3})*(?:\.\d{2})?)\s*(USD|EUR|GBP|JPY|dollars?|euros?|pounds?)").unwrap(),
-        // $X million/billion
-        Regex::new(r"(\$|USD|EUR|€|£)\s*(\d+(?:\.\d+)?)\s*(million|billion|thousand|M|B|K)").unwrap(),
-        // X million dollars
-        Regex::new(r"(\d+(?:\.\d+)?)\s*(million|billion|thousand)\s*(dollars?|USD|euros?|EUR|pounds?|GBP)").unwrap(),
-    ];
+    pub fn entities_mut(&mut self) -> impl Iterator<Item = &mut Entity> {
+        self.dates.iter_mut()
+            .chain(&mut self.persons)
+            .chain(&mut self.organizations)
+            .chain(&mut self.amounts)
+            .chain(&mut self.locations)
+            .chain(&mut self.emails)
+            .chain(&mut self.phones)
+            .chain(&mut self.urls)
+            .chain(&mut self.postal_codes)
+            .chain(&mut self.citations)
+            .chain(&mut self.ages)
+            .chain(&mut self.percentages)
+            .chain(&mut self.ratios)
+            .chain(&mut self.redactions)
+            .chain(&mut self.quantities)
+            .chain(&mut self.eins)
+            .chain(&mut self.icd_codes)
+            .chain(&mut self.drugs)
+            .chain(&mut self.coordinates)
+            .chain(&mut self.references)
+            .chain(&mut self.isbns)
+            .chain(&mut self.dois)
+            .chain(&mut self.license_plates)
+            .chain(&mut self.durations)
+            .chain(&mut self.work_titles)
+            .chain(&mut self.routing_numbers)
+            .chain(&mut self.custom)
+    }
 
-    // Locations
-    static ref LOCATION_PATTERNS: Vec<Regex> = vec![
-        // City, State/Country
-        Regex::new(r"\b([A-Z][a-z]+(?:\s+[A-Z][a-z]+)*),\s*([A-Z]{2}|[A-Z][a-z]+(?:\s+[A-Z][a-z]+)*)\b").unwrap(),
-        // Known places
-        Regex::new(r"\b(New York|Los Angeles|London|Paris|Tokyo|Hong Kong|Singapore|Dubai|Miami|Washington|Virgin Islands|Little St\.? James|Palm Beach|Manhattan|Florida|California|Texas)\b").unwrap(),
-        // Street addresses
-        Regex::new(r"\b(\d+\s+[A-Z][a-z]+(?:\s+[A-Z][a-z]+)*\s+(?:Street|St|Avenue|Ave|Road|Rd|Boulevard|Blvd|Drive|Dr|Lane|Ln|Court|Ct|Way|Place|Pl)\.?)\b").unwrap(),
-    ];
+    /// The entities of `kind`
+    pub fn field(&self, kind: EntityKind) -> &Vec<Entity> {
+        match kind {
+            EntityKind::Date => &self.dates,
+            EntityKind::Person => &self.persons,
+            EntityKind::Organization => &self.organizations,
+            EntityKind::Amount => &self.amounts,
+            EntityKind::Location => &self.locations,
+            EntityKind::Email => &self.emails,
+            EntityKind::Phone => &self.phones,
+            EntityKind::Url => &self.urls,
+            EntityKind::PostalCode => &self.postal_codes,
+            EntityKind::Citation => &self.citations,
+            EntityKind::Age => &self.ages,
+            EntityKind::Percent => &self.percentages,
+            EntityKind::Ratio => &self.ratios,
+            EntityKind::Redaction => &self.redactions,
+            EntityKind::Quantity => &self.quantities,
+            EntityKind::Ein => &self.eins,
+            EntityKind::IcdCode => &self.icd_codes,
+            EntityKind::Drug => &self.drugs,
+            EntityKind::Coordinate => &self.coordinates,
+            EntityKind::Reference => &self.references,
+            EntityKind::Isbn => &self.isbns,
+            EntityKind::Doi => &self.dois,
+            EntityKind::LicensePlate => &self.license_plates,
+            EntityKind::Duration => &self.durations,
+            EntityKind::WorkTitle => &self.work_titles,
+            EntityKind::RoutingNumber => &self.routing_numbers,
+        }
+    }
 
-    // Email addresses
-    static ref EMAIL_PATTERN: Regex = Regex::new(
-        r"\b([A-Za-z0-9._%+-]+@[A-Za-z0-9.-]+\.[A-Za-z]{2,})\b"
-    ).unwrap();
+    /// The vector holding entities of `kind`
+    pub fn field_mut(&mut self, kind: EntityKind) -> &mut Vec<Entity> {
+        match kind {
+            EntityKind::Date => &mut self.dates,
+            EntityKind::Person => &mut self.persons,
+            EntityKind::Organization => &mut self.organizations,
+            EntityKind::Amount => &mut self.amounts,
+            EntityKind::Location => &mut self.locations,
+            EntityKind::Email => &mut self.emails,
+            EntityKind::Phone => &mut self.phones,
+            EntityKind::Url => &mut self.urls,
+            EntityKind::PostalCode => &mut self.postal_codes,
+            EntityKind::Citation => &mut self.citations,
+            EntityKind::Age => &mut self.ages,
+            EntityKind::Percent => &mut self.percentages,
+            EntityKind::Ratio => &mut self.ratios,
+            EntityKind::Redaction => &mut self.redactions,
+            EntityKind::Quantity => &mut self.quantities,
+            EntityKind::Ein => &mut self.eins,
+            EntityKind::IcdCode => &mut self.icd_codes,
+            EntityKind::Drug => &mut self.drugs,
+            EntityKind::Coordinate => &mut self.coordinates,
+            EntityKind::Reference => &mut self.references,
+            EntityKind::Isbn => &mut self.isbns,
+            EntityKind::Doi => &mut self.dois,
+            EntityKind::LicensePlate => &mut self.license_plates,
+            EntityKind::Duration => &mut self.durations,
+            EntityKind::WorkTitle => &mut self.work_titles,
+            EntityKind::RoutingNumber => &mut self.routing_numbers,
+        }
+    }
 
-    // Phone numbers
-    static ref PHONE_PATTERN: Regex = Regex::new(
-        r"(?:\+?1[-.\s]?)?\(?([0-9]{3})\)?[-.\s]?([0-9]{3})[-.\s]?([0-9]{4})"
-    ).unwrap();
+    /// Set every entity's `id` to its position in document order
+    /// (`Entity::order_key`), 0 to `total_count - 1`. Extraction is
+    /// deterministic, so the same text always gets the same ids.
+    pub fn renumber(&mut self) {
+        let mut order: Vec<_> = self.entities()
+            .enumerate()
+            .map(|(i, e)| (e.order_key(), i))
+            .collect();
+        order.sort_unstable();
+        let mut ids = vec![0; order.len()];
+        for (id, &(.., i)) in order.iter().enumerate() {
+            ids[i] = id;
+        }
+        for (entity, id) in self.entities_mut().zip(ids) {
+            entity.id = id;
+        }
+    }
 
-    // URLs
-    static ref URL_PATTERN: Regex = Regex::new(
-        r"https?://[A-Za-z0-9][-A-Za-z0-9+&@#/%?=~_|!:,.;]*[-A-Za-z0-9+&@#/%=~_|]"
-    ).unwrap();
+    /// Keep only the entities `keep` accepts, across every vector
+    pub fn retain(&mut self, mut keep: impl FnMut(&Entity) -> bool) {
+        for &kind in EntityKind::ALL {
+            self.field_mut(kind).retain(&mut keep);
+        }
+        self.custom.retain(keep);
+        self.recount();
+    }
 
-    // Crypto addresses
-    static ref BTC_PATTERN: Regex = Regex::new(r"\b[13][a-km-zA-HJ-NP-Z1-9]{25,34}\b").unwrap();
-    static ref ETH_PATTERN: Regex = Regex::new(r"\b0x[a-fA-F0-9]{40}\b").unwrap();
-}
+    /// Recompute `total_count` from the entity vectors
+    pub fn recount(&mut self) {
+        self.total_count = self.entities().count();
+    }
 
-// =============================================================================
-// EXTRACTION FUNCTIONS
-// =============================================================================
+    /// Combine the results of two consecutive chunks of one document, `other`
+    /// extracted from the chunk starting at byte `offset_shift`
+    ///
+    /// `other`'s offsets, and the offsets in its `person_offset`, `coref`
+    /// and `alias_of` metadata, are shifted into document offsets. An entity
+    /// already in `self` at the same span is dropped, as is a later
+    /// occurrence of a value in a kind extraction keeps once (persons,
+    /// organizations, dates, amounts, locations). Ids are renumbered and
+    /// processing times summed. `other`'s UTF-16 offsets can't be shifted
+    /// without the text and are cleared; its `line`/`col` metadata stays
+    /// relative to its chunk.
+    pub fn merge(mut self, mut other: ExtractionResult, offset_shift: usize) -> ExtractionResult {
+        for &kind in EntityKind::ALL {
+            let dedup = window::VALUE_DEDUPED.contains(&kind);
+            let field = self.field_mut(kind);
+            let mut values: HashSet<String> = if dedup {
+                field.iter().map(|e| e.value.to_lowercase()).collect()
+            } else {
+                HashSet::new()
+            };
+            let mut spans: HashSet<(usize, usize, String)> = field.iter().map(|e| (e.start, e.end, e.value.clone())).collect();
+            for mut entity in std::mem::take(other.field_mut(kind)) {
+                shift_offsets(&mut entity, offset_shift);
+                if spans.insert((entity.start, entity.end, entity.value.clone())) && (!dedup || values.insert(entity.value.to_lowercase())) {
+                    field.push(entity);
+                }
+            }
+        }
+        let mut spans: HashSet<(usize, usize, String)> = self.custom.iter().map(|e| (e.start, e.end, e.value.clone())).collect();
+        for mut entity in std::mem::take(&mut other.custom) {
+            shift_offsets(&mut entity, offset_shift);
+            if spans.insert((entity.start, entity.end, entity.value.clone())) {
+                self.custom.push(entity);
+            }
+        }
 
-fn extract_with_patterns(text: &str, patterns: &[Regex], entity_type: &str, base_confidence: f64) -> Vec<Entity> {
-    let mut entities = Vec::new();
-    let mut seen = std::collections::HashSet::new();
+        self.processing_time_ms += other.processing_time_ms;
+        self.timed_out |= other.timed_out;
+        self.preview_truncated |= other.preview_truncated;
+        self.errors.append(&mut other.errors);
+        self.text_len = self.text_len.max(offset_shift + other.text_len);
+        self.recount();
+        self.renumber();
+        self
+    }
+}
 
-    for pattern in patterns {
-        for cap in pattern.captures_iter(text) {
-            if let Some(m) = cap.get(0) {
-                let value = m.as_str().to_string();
-                let normalized = value.to_lowercase();
+/// Metadata keys whose value is a byte offset into the text
+const OFFSET_METADATA: &[&str] = &["person_offset", "coref", "alias_of"];
 
-                if seen.contains(&normalized) {
-                    continue;
+fn shift_offsets(entity: &mut Entity, shift: usize) {
+    entity.start += shift;
+    entity.end += shift;
+    entity.utf16_start = None;
+    entity.utf16_end = None;
+    if let Some(metadata) = &mut entity.metadata {
+        for key in OFFSET_METADATA {
+            if let Some(offset) = metadata.get_mut(*key) {
+                if let Ok(value) = offset.parse::<usize>() {
+                    *offset = (value + shift).to_string();
                 }
-                seen.insert(normalized);
-
-                // Get surrounding context (50 chars each side)
-                let start = m.start().saturating_sub(50);
-                let end = (m.end() + 50).min(text.len());
-                let context = text[start..end].to_string();
-
-                entities.push(Entity {
-                    value,
-                    entity_type: entity_type.to_string(),
-                    start: m.start(),
-                    end: m.end(),
-                    confidence: base_confidence,
-                    context: Some(context),
-                    metadata: None,
-                });
             }
         }
     }
+}
 
-    entities
+/// How the per-type extractors are scheduled across threads.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Parallelism {
+    /// Sequential below `PARALLEL_THRESHOLD_BYTES`, parallel above it
+    #[default]
+    Auto,
+    /// Run every extractor and pattern on the calling thread
+    Sequential,
+    /// Flat rayon scope across extractors, plus per-pattern parallelism
+    Parallel,
 }
 
-fn extract_dates(text: &str) -> Vec<Entity> {
-    extract_with_patterns(text, &DATE_PATTERNS, "date", 0.85)
+/// How aggressively bare "First Last" candidates are filtered
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PersonStrictness {
+    /// Only the fixed blacklist
+    Off,
+    /// Drop pairs of common words; downrank unconfirmed sentence starts
+    #[default]
+    Normal,
+    /// Drop both
+    Strict,
 }
 
-fn extract_persons(text: &str) -> Vec<Entity> {
-    let mut entities = extract_with_patterns(text, &PERSON_PATTERNS, "person", 0.75);
+/// How amounts group thousands and mark decimals, beyond the "1,234.56"
+/// form that is always recognized
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NumberLocale {
+    /// Comma groups, point decimals only
+    #[default]
+    English,
+    /// Space or point groups, comma decimals: "1 234 567,89 EUR"
+    European,
+    /// Apostrophe groups, point decimals: "1'234'567.89 CHF"
+    Swiss,
+}
 
-    // Filter out common false positives
-    let blacklist = [
-        "the", "this", "that", "with", "from", "have", "will", "been",
-        "january", "february", "march", "april", "may", "june",
-        "july", "august", "september", "october", "november", "december",
-        "monday", "tuesday", "wednesday", "thursday", "friday", "saturday", "sunday"
-    ];
+/// Which entities to report by whether they sit inside a double-quoted
+/// span, straight ("...") or curly (“...”)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QuoteHandling {
+    /// All of them, untagged
+    #[default]
+    Include,
+    /// Those outside quotes, tagged `metadata["quoted"] = "false"`
+    ExcludeQuoted,
+    /// Those inside quotes, tagged `metadata["quoted"] = "true"`
+    OnlyQuoted,
+}
 
-    entities.retain(|e| {
-        let lower = e.value.to_lowercase();
-        !blacklist.iter().any(|b| lower.contains(b))
-    });
+/// How a result is written out; extraction is the same either way
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OutputProfile {
+    /// `ExtractionResult` as is
+    #[default]
+    Full,
+    /// `[value, start, end, confidence]` arrays under each type's key, no
+    /// context or metadata; see `minimal`
+    Minimal,
+}
 
-    entities
+/// What to report when an email sits inside a URL
+/// ("https://site.com/?email=a@b.com")
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EmailInUrl {
+    /// The URL only, unless the email follows a `mailto:` inside it
+    #[default]
+    PreferUrl,
+    /// Both the URL and the email
+    Both,
 }
 
-fn extract_organizations(text: &str) -> Vec<Entity> {
-    let mut entities = extract_with_patterns(text, &ORG_PATTERNS, "organization", 0.80);
+/// When a bare acronym ("SEC", "ACME") counts as an organization
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AcronymOptions {
+    /// Emit an acronym seen at least this many times in the document, even
+    /// without context
+    pub min_occurrences: usize,
+    /// Chars each side of an acronym searched for org-context words
+    pub context_chars: usize,
+    /// Extra non-org words on top of the built-in stoplist, any case
+    pub stoplist: Vec<String>,
+    /// Extra acronyms emitted wherever they appear, on top of the built-in
+    /// ones ("FBI", "SEC", ...), any case; a company's own "ACME"
+    pub known: Vec<String>,
+}
 
-    // Filter short acronyms that are likely false positives
-    entities.retain(|e| {
-        if e.value.len() <= 3 {
-            // Only keep well-known acronyms
-            let known = ["FBI", "CIA", "NSA", "SEC", "DOJ", "IRS", "EPA", "FDA", "FTC", "NYSE", "LLC", "CEO", "CFO"];
-            known.contains(&e.value.as_str())
-        } else {
-            true
+impl Default for AcronymOptions {
+    fn default() -> Self {
+        AcronymOptions { min_occurrences: 2, context_chars: 40, stoplist: Vec::new(), known: Vec::new() }
+    }
+}
+
+/// Words before an entity that deny it ("did not meet") or hedge it
+/// ("allegedly paid"), for `ExtractOptions.tag_assertions`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AssertionCues {
+    /// Words and phrases matched whole, any case; replace the built-in list
+    pub negation: Vec<String>,
+    pub hedging: Vec<String>,
+    /// Chars before an entity searched for a cue; the search also stops at
+    /// the end of the previous sentence or clause (". ", ";", "!", "?", a
+    /// line break)
+    pub window_chars: usize,
+}
+
+impl Default for AssertionCues {
+    fn default() -> Self {
+        let owned = |cues: &[&str]| cues.iter().map(|c| c.to_string()).collect();
+        AssertionCues {
+            negation: owned(&[
+                "no", "not", "never", "denied", "denies", "deny", "without", "neither", "nor",
+                "didn't", "doesn't", "don't", "wasn't", "weren't", "isn't", "hasn't", "hadn't",
+            ]),
+            hedging: owned(&[
+                "allegedly", "reportedly", "purportedly", "apparently", "possibly", "may have", "might have",
+                "could have", "is said to", "was said to", "rumored", "suspected", "unconfirmed",
+            ]),
+            window_chars: 40,
         }
-    });
+    }
+}
 
-    entities
+/// A unit `extract_quantities` recognizes after a number
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QuantityUnit {
+    /// Spellings, matched case-insensitively; the first is reported as the
+    /// original unit ("lb" for "200 lbs")
+    pub names: Vec<String>,
+    /// SI unit values convert to ("kg", "m", "L", "m2"); `None` keeps the
+    /// value as written
+    pub si_unit: Option<String>,
+    /// Multiplier from this unit to `si_unit`
+    pub factor: f64,
 }
 
-fn extract_amounts(text: &str) -> Vec<Entity> {
-    let mut entities = extract_with_patterns(text, &AMOUNT_PATTERNS, "amount", 0.90);
+impl QuantityUnit {
+    pub fn new(names: &[&str], si_unit: Option<&str>, factor: f64) -> Self {
+        QuantityUnit {
+            names: names.iter().map(|n| n.to_string()).collect(),
+            si_unit: si_unit.map(str::to_string),
+            factor,
+        }
+    }
 
-    // Normalize amounts to numeric value
-    for entity in &mut entities {
-        let mut metadata = HashMap::new();
-
-        // Parse and normalize
-        let value = entity.value.replace(",", "").replace("$", "").replace("€", "").replace("£", "");
-        let normalized: f64 = if value.to_lowercase().contains("million") || value.contains("M") {
-            value.split_whitespace().next()
-                .and_then(|s| s.parse::<f64>().ok())
-                .map(|n| n * 1_000_000.0)
-                .unwrap_or(0.0)
-        } else if value.to_lowercase().contains("billion") || value.contains("B") {
-            value.split_whitespace().next()
-                .and_then(|s| s.parse::<f64>().ok())
-                .map(|n| n * 1_000_000_000.0)
-                .unwrap_or(0.0)
-        } else {
-            value.split_whitespace().next()
-                .and_then(|s| s.parse::<f64>().ok())
-                .unwrap_or(0.0)
-        };
+    /// Common mass, length, volume and area units. Bare "in" and "t" are
+    /// left out: "5 in the" and "5 t" are rarely measurements.
+    pub fn defaults() -> Vec<QuantityUnit> {
+        let kg = Some("kg");
+        let m = Some("m");
+        let l = Some("L");
+        let m2 = Some("m2");
+        vec![
+            QuantityUnit::new(&["mg", "milligram", "milligrams"], kg, 1e-6),
+            QuantityUnit::new(&["g", "gram", "grams"], kg, 1e-3),
+            QuantityUnit::new(&["kg", "kgs", "kilogram", "kilograms", "kilo", "kilos"], kg, 1.0),
+            QuantityUnit::new(&["tonne", "tonnes", "metric ton", "metric tons"], kg, 1000.0),
+            QuantityUnit::new(&["ton", "tons"], kg, 907.18474),
+            QuantityUnit::new(&["lb", "lbs", "pound", "pounds"], kg, 0.45359237),
+            QuantityUnit::new(&["oz", "ounce", "ounces"], kg, 0.028349523125),
+            QuantityUnit::new(&["mm", "millimeter", "millimeters", "millimetre", "millimetres"], m, 1e-3),
+            QuantityUnit::new(&["cm", "centimeter", "centimeters", "centimetre", "centimetres"], m, 1e-2),
+            QuantityUnit::new(&["m", "meter", "meters", "metre", "metres"], m, 1.0),
+            QuantityUnit::new(&["km", "kilometer", "kilometers", "kilometre", "kilometres"], m, 1000.0),
+            QuantityUnit::new(&["inch", "inches"], m, 0.0254),
+            QuantityUnit::new(&["ft", "foot", "feet"], m, 0.3048),
+            QuantityUnit::new(&["yd", "yard", "yards"], m, 0.9144),
+            QuantityUnit::new(&["mi", "mile", "miles"], m, 1609.344),
+            QuantityUnit::new(&["mL", "ml", "milliliter", "milliliters", "millilitre", "millilitres"], l, 1e-3),
+            QuantityUnit::new(&["L", "liter", "liters", "litre", "litres"], l, 1.0),
+            QuantityUnit::new(&["gal", "gallon", "gallons"], l, 3.785411784),
+            QuantityUnit::new(&["qt", "quart", "quarts"], l, 0.946352946),
+            QuantityUnit::new(&["fl oz", "fluid ounce", "fluid ounces"], l, 0.0295735295625),
+            QuantityUnit::new(&["sq m", "m2", "m²", "square meter", "square meters", "square metre", "square metres"], m2, 1.0),
+            QuantityUnit::new(&["sq ft", "ft2", "ft²", "square foot", "square feet"], m2, 0.09290304),
+            QuantityUnit::new(&["sq mi", "square mile", "square miles"], m2, 2_589_988.110336),
+            QuantityUnit::new(&["acre", "acres"], m2, 4046.8564224),
+            QuantityUnit::new(&["ha", "hectare", "hectares"], m2, 10_000.0),
+        ]
+    }
+}
+
+/// A drug `extract_drugs` recognizes by name
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DrugName {
+    /// Generic name, reported as `metadata["generic"]` for every spelling
+    pub generic: String,
+    /// Spellings matched case-insensitively on word boundaries: the
+    /// generic name and any brand names
+    pub names: Vec<String>,
+    /// Therapeutic class ("opioid", "SSRI"), as `metadata["class"]`
+    pub class: Option<String>,
+}
 
-        metadata.insert("normalized".to_string(), normalized.to_string());
-        entity.metadata = Some(metadata);
+impl DrugName {
+    pub fn new(generic: &str, brands: &[&str], class: Option<&str>) -> Self {
+        DrugName {
+            generic: generic.to_string(),
+            names: std::iter::once(generic).chain(brands.iter().copied()).map(str::to_string).collect(),
+            class: class.map(str::to_string),
+        }
     }
 
-    entities
+    /// Drugs common in case files: controlled substances and the most
+    /// prescribed maintenance medications. Names that are also ordinary
+    /// words ("lithium", "Bayer") are left out.
+    pub fn defaults() -> Vec<DrugName> {
+        let drug = |generic, brands: &[&str], class| DrugName::new(generic, brands, Some(class));
+        vec![
+            drug("acetaminophen", &["paracetamol", "Tylenol"], "analgesic"),
+            drug("ibuprofen", &["Advil", "Motrin"], "NSAID"),
+            drug("naproxen", &["Aleve"], "NSAID"),
+            drug("aspirin", &[], "NSAID"),
+            drug("oxycodone", &["OxyContin", "Roxicodone", "Percocet"], "opioid"),
+            drug("hydrocodone", &["Vicodin", "Norco"], "opioid"),
+            drug("fentanyl", &["Duragesic", "Actiq"], "opioid"),
+            drug("morphine", &["MS Contin"], "opioid"),
+            drug("methadone", &["Dolophine"], "opioid"),
+            drug("buprenorphine", &["Suboxone", "Subutex"], "opioid"),
+            drug("tramadol", &["Ultram"], "opioid"),
+            drug("naloxone", &["Narcan"], "opioid antagonist"),
+            drug("alprazolam", &["Xanax"], "benzodiazepine"),
+            drug("diazepam", &["Valium"], "benzodiazepine"),
+            drug("lorazepam", &["Ativan"], "benzodiazepine"),
+            drug("clonazepam", &["Klonopin"], "benzodiazepine"),
+            drug("zolpidem", &["Ambien"], "sedative"),
+            drug("ketamine", &["Ketalar"], "anesthetic"),
+            drug("propofol", &["Diprivan"], "anesthetic"),
+            drug("amphetamine", &["Adderall"], "stimulant"),
+            drug("methylphenidate", &["Ritalin", "Concerta"], "stimulant"),
+            drug("sertraline", &["Zoloft"], "SSRI"),
+            drug("fluoxetine", &["Prozac"], "SSRI"),
+            drug("escitalopram", &["Lexapro"], "SSRI"),
+            drug("bupropion", &["Wellbutrin"], "antidepressant"),
+            drug("quetiapine", &["Seroquel"], "antipsychotic"),
+            drug("risperidone", &["Risperdal"], "antipsychotic"),
+            drug("gabapentin", &["Neurontin"], "anticonvulsant"),
+            drug("metformin", &["Glucophage"], "antidiabetic"),
+            drug("insulin", &["Lantus", "Humalog"], "antidiabetic"),
+            drug("atorvastatin", &["Lipitor"], "statin"),
+            drug("simvastatin", &["Zocor"], "statin"),
+            drug("lisinopril", &["Zestril", "Prinivil"], "ACE inhibitor"),
+            drug("amlodipine", &["Norvasc"], "calcium channel blocker"),
+            drug("metoprolol", &["Lopressor", "Toprol-XL"], "beta blocker"),
+            drug("levothyroxine", &["Synthroid"], "thyroid hormone"),
+            drug("omeprazole", &["Prilosec"], "proton pump inhibitor"),
+            drug("prednisone", &["Deltasone"], "corticosteroid"),
+            drug("warfarin", &["Coumadin"], "anticoagulant"),
+            drug("amoxicillin", &["Amoxil"], "antibiotic"),
+            drug("azithromycin", &["Zithromax"], "antibiotic"),
+            drug("ciprofloxacin", &["Cipro"], "antibiotic"),
+            drug("sildenafil", &["Viagra"], "PDE5 inhibitor"),
+        ]
+    }
 }
 
-fn extract_locations(text: &str) -> Vec<Entity> {
-    extract_with_patterns(text, &LOCATION_PATTERNS, "location", 0.70)
+/// Tuning knobs for a single extraction run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ExtractOptions {
+    pub parallelism: Parallelism,
+    /// Attach surrounding text to entities at all
+    pub include_context: bool,
+    /// Context width on each side of a match, in chars
+    pub context_chars: usize,
+    /// Only these kinds get context; `None` uses `EntityKind::context_by_default`
+    pub context_types: Option<Vec<EntityKind>>,
+    pub person_strictness: PersonStrictness,
+    pub acronyms: AcronymOptions,
+    /// NFC-normalize before matching so combining sequences ("Jose\u{301}")
+    /// match like precomposed text; offsets still point into the original
+    pub normalize_unicode: bool,
+    /// Per-type multipliers for `relevance_with`, overriding the built-in weights
+    pub type_weights: HashMap<String, f64>,
+    /// Add `metadata["entity_id"]` (see `Entity::stable_id`) to every entity
+    pub assign_ids: bool,
+    /// Wall-clock budget per document (milliseconds on the wire)
+    #[serde(with = "duration_ms")]
+    pub timeout: Option<Duration>,
+    /// Debugging aid: in debug builds, panic if any entity's `value` differs
+    /// from `text[start..end]`. Ignored in release builds; see `validate_offsets`
+    pub validate_offsets: bool,
+    /// Half-open byte ranges `[start, end)` of the original text to ignore
+    /// (boilerplate headers, signature blocks). Entities touching any of
+    /// them are dropped.
+    pub exclude_ranges: Vec<(usize, usize)>,
+    /// Shortest run of X's ("XXXXX") reported as a redaction
+    pub redaction_min_x: usize,
+    /// Units `extract_quantities` recognizes; replaces the built-in table
+    pub quantity_units: Vec<QuantityUnit>,
+    /// Drop emails with an unknown TLD or a malformed local part or domain
+    /// ("a..b@x.com", "foo@bar.invalidtld"), and downrank rare TLDs
+    pub strict_email: bool,
+    /// Extra thousands/decimal separators accepted in amounts
+    pub number_locale: NumberLocale,
+    /// Add `metadata["paragraph"]`, the 0-based index of the paragraph the
+    /// entity starts in
+    pub tag_paragraphs: bool,
+    /// Literal text between paragraphs for `tag_paragraphs`; `None` splits
+    /// on blank lines (LF or CRLF, any number of them)
+    pub paragraph_delimiter: Option<String>,
+    /// Built-in patterns to skip, by name (`builtin_pattern_names`)
+    pub disabled_patterns: HashSet<String>,
+    /// Gazetteer `extract_drugs` matches; replaces the built-in list
+    pub drugs: Vec<DrugName>,
+    /// Report later one-word mentions of an earlier full name ("Epstein"
+    /// after "Jeffrey Epstein") as persons, with `metadata["coref"]` (the
+    /// full mention's offset) and `metadata["resolved_name"]`
+    pub coref: bool,
+    /// Fill in each entity's `utf16_start` and `utf16_end`
+    pub utf16_offsets: bool,
+    /// Add the 1-based line and 0-based column, in chars, of each entity's
+    /// start and end: `metadata["line"]`, `["col"]`, `["end_line"]` and
+    /// `["end_col"]`. Lines end at `\n`, so CRLF counts once.
+    pub line_columns: bool,
+    /// Add `metadata["script"]`, the writing system most of the entity's
+    /// letters are in: "Latin", "Cyrillic", "Greek", "Arabic", "Hebrew",
+    /// "Devanagari", "Thai", "CJK" or "Other", and "Common" for values
+    /// without letters ("$5,000")
+    pub tag_scripts: bool,
+    /// Add `metadata["negated"]` and `metadata["hedged"]`, "true" or
+    /// "false", by whether `assertion_cues` has a cue shortly before the
+    /// entity: "did not meet Bill Clinton", "allegedly paid $5M"
+    pub tag_assertions: bool,
+    /// Cues and the window `tag_assertions` searches
+    pub assertion_cues: AssertionCues,
+    /// Read text as a transcript whose lines may start with a timestamp,
+    /// "[HH:MM:SS]" or "MM:SS" (bracketed or not): entities on such a line
+    /// get `metadata["timestamp"]`, its offset in whole seconds, and the
+    /// timestamp itself is no entity
+    pub transcript_timestamps: bool,
+    /// Keep or drop entities inside double quotes
+    pub quote_handling: QuoteHandling,
+    /// Report every pattern match as its own entity, with the pattern's
+    /// name in `metadata["pattern"]`: no first-occurrence dedup, no merging
+    /// of a span several patterns match, and none of the person and
+    /// organization false-positive filters. For studying the patterns, not
+    /// for analysis; later passes (denylist, exclusions) still apply.
+    pub raw: bool,
+    /// Values never reported, of any type ("Acme Corp", "Jane Doe");
+    /// matched case-insensitively with whitespace runs collapsed
+    pub denylist: HashSet<String>,
+    /// Whether an email inside a URL is reported as well
+    pub email_in_url: EmailInUrl,
+    /// Leave Markdown code (fenced and indented blocks) out of person and
+    /// organization extraction, where identifiers pass for names; the
+    /// other kinds still scan it
+    pub skip_code_blocks: bool,
+    /// Also report amounts written out in words ("five hundred thousand
+    /// dollars"), normalized like the numeric ones
+    pub parse_written_amounts: bool,
+    /// Extract only inside the matches of this regex ("(?s)Summary:.*?\n\n"),
+    /// each scanned on its own; offsets stay those of the whole text. A
+    /// pattern string in JSON.
+    #[serde(with = "optional_regex")]
+    pub scan_within: Option<Regex>,
+    /// The form the server writes `/extract` results in
+    pub output_profile: OutputProfile,
+    /// Lower repeated mentions: the Nth entity of one type and normalized
+    /// value (`Entity::normalized_value`), in document order, gets its
+    /// confidence times this factor (0 to 1) to the N-1, but no less than
+    /// `REPEAT_DECAY_FLOOR`. Only kinds that keep repeats have any, or all
+    /// of them under `raw`; persons, organizations, dates, amounts and
+    /// locations otherwise report each value once.
+    pub repeat_decay: Option<f64>,
 }
 
-fn extract_emails(text: &str) -> Vec<Entity> {
-    EMAIL_PATTERN.find_iter(text).map(|m| {
-        Entity {
-            value: m.as_str().to_string(),
-            entity_type: "email".to_string(),
-            start: m.start(),
-            end: m.end(),
-            confidence: 0.95,
-            context: None,
-            metadata: None,
+impl Default for ExtractOptions {
+    fn default() -> Self {
+        ExtractOptions {
+            parallelism: Parallelism::default(),
+            include_context: true,
+            context_chars: 50,
+            context_types: None,
+            person_strictness: PersonStrictness::default(),
+            acronyms: AcronymOptions::default(),
+            normalize_unicode: false,
+            type_weights: HashMap::new(),
+            assign_ids: false,
+            timeout: None,
+            validate_offsets: false,
+            exclude_ranges: Vec::new(),
+            redaction_min_x: 4,
+            quantity_units: QuantityUnit::defaults(),
+            strict_email: false,
+            number_locale: NumberLocale::default(),
+            tag_paragraphs: false,
+            quote_handling: QuoteHandling::default(),
+            raw: false,
+            paragraph_delimiter: None,
+            disabled_patterns: HashSet::new(),
+            drugs: DrugName::defaults(),
+            coref: false,
+            utf16_offsets: false,
+            line_columns: false,
+            tag_scripts: false,
+            tag_assertions: false,
+            assertion_cues: AssertionCues::default(),
+            transcript_timestamps: false,
+            denylist: HashSet::new(),
+            email_in_url: EmailInUrl::default(),
+            skip_code_blocks: false,
+            parse_written_amounts: false,
+            scan_within: None,
+            output_profile: OutputProfile::default(),
+            repeat_decay: None,
         }
-    }).collect()
+    }
 }
 
-fn extract_phones(text: &str) -> Vec<Entity> {
-    PHONE_PATTERN.find_iter(text).map(|m| {
-        Entity {
-            value: m.as_str().to_string(),
-            entity_type: "phone".to_string(),
-            start: m.start(),
-            end: m.end(),
-            confidence: 0.80,
-            context: None,
-            metadata: None,
+impl ExtractOptions {
+    /// Whether entities of `kind` should carry context under these options
+    pub fn wants_context(&self, kind: EntityKind) -> bool {
+        self.include_context && match &self.context_types {
+            Some(kinds) => kinds.contains(&kind),
+            None => kind.context_by_default(),
         }
-    }).collect()
+    }
 }
 
-fn extract_urls(text: &str) -> Vec<Entity> {
-    URL_PATTERN.find_iter(text).map(|m| {
-        Entity {
-            value: m.as_str().to_string(),
-            entity_type: "url".to_string(),
-            start: m.start(),
-            end: m.end(),
-            confidence: 0.95,
-            context: None,
-            metadata: None,
+mod duration_ms {
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::time::Duration;
+
+    pub fn serialize<S: Serializer>(value: &Option<Duration>, s: S) -> Result<S::Ok, S::Error> {
+        match value {
+            Some(d) => s.serialize_some(&(d.as_millis() as u64)),
+            None => s.serialize_none(),
         }
-    }).collect()
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Option<Duration>, D::Error> {
+        Ok(Option::<u64>::deserialize(d)?.map(Duration::from_millis))
+    }
+}
+
+mod optional_regex {
+    use regex::Regex;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(value: &Option<Regex>, s: S) -> Result<S::Ok, S::Error> {
+        match value {
+            Some(regex) => s.serialize_some(regex.as_str()),
+            None => s.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Option<Regex>, D::Error> {
+        Option::<String>::deserialize(d)?
+            .map(|pattern| super::compile_pattern(&pattern).map_err(serde::de::Error::custom))
+            .transpose()
+    }
 }
 
+/// Documents smaller than this are extracted sequentially under `Parallelism::Auto`.
+///
+/// Measured with a release build over repeated copies of a mixed-entity
+/// paragraph: the parallel path carries a fixed ~10-15µs of scheduling cost
+/// (1 KB: 38µs sequential vs 46µs parallel), which is a quarter of the work
+/// for small documents but under 3% at 16 KB (~400µs sequential), where a
+/// second core already more than pays for it.
+pub const PARALLEL_THRESHOLD_BYTES: usize = 16 * 1024;
+
 // =============================================================================
-// PARALLEL EXTRACTION
+// REGEX PATTERNS (compiled once)
 // =============================================================================
 
-/// Extract all entities from text using parallel processing
-pub fn extract_all(text: &str) -> ExtractionResult {
-    let start = std::time::Instant::now();
-
-    // Run all extractions in parallel using rayon
-    let ((dates, persons), rest) = rayon::join(
-        || rayon::join(
-            || extract_dates(text),
-            || extract_persons(text)
-        ),
-        || rayon::join(
-            || rayon::join(
-                || extract_organizations(text),
-                || extract_amounts(text)
-            ),
-            || rayon::join(
-                || rayon::join(
-                    || extract_locations(text),
-                    || extract_emails(text)
-                ),
-                || rayon::join(
-                    || extract_phones(text),
-                    || extract_urls(text)
-                )
-            )
-        )
-    );
+/// Compiled program size cap per pattern. The regex crate never backtracks,
+/// but nested quantifiers over Unicode classes can still blow up the
+/// automaton, so oversized patterns fail to compile instead.
+const PATTERN_SIZE_LIMIT: usize = 4 * (1 << 20);
+/// Lazy DFA cache cap per pattern; past it the engine falls back to the
+/// slower NFA simulation rather than growing memory
+const PATTERN_DFA_SIZE_LIMIT: usize = 2 * (1 << 20);
 
-    let ((orgs, amounts), ((locations, emails), (phones, urls))) = rest;
+pub(crate) fn compile_pattern(pattern: &str) -> Result<Regex, regex::Error> {
+    regex::RegexBuilder::new(pattern)
+        .size_limit(PATTERN_SIZE_LIMIT)
+        .dfa_size_limit(PATTERN_DFA_SIZE_LIMIT)
+        .build()
+}
 
-    let total_count = dates.len() + persons.len() + orgs.len() + amounts.len() +
-                      locations.len() + emails.len() + phones.len() + urls.len();
+/// A built-in extraction pattern and the stable name
+/// `ExtractOptions.disabled_patterns` refers to it by ("org.acronym")
+pub struct NamedPattern {
+    pub name: &'static str,
+    pub regex: Regex,
+    /// Overrides the kind's base confidence in `extract_with_patterns`
+    pub confidence: Option<f64>,
+}
 
-    ExtractionResult {
-        dates,
-        persons,
-        organizations: orgs,
-        amounts,
-        locations,
-        emails,
-        phones,
-        urls,
-        total_count,
-        processing_time_ms: start.elapsed().as_millis() as u64,
+impl NamedPattern {
+    fn with_confidence(mut self, confidence: f64) -> Self {
+        self.confidence = Some(confidence);
+        self
     }
 }
 
-/// Extract specific entity types only
-pub fn extract_types(text: &str, types: &[&str]) -> ExtractionResult {
-    let start = std::time::Instant::now();
+impl std::ops::Deref for NamedPattern {
+    type Target = Regex;
 
-    let dates = if types.contains(&"dates") { extract_dates(text) } else { vec![] };
-    let persons = if types.contains(&"persons") { extract_persons(text) } else { vec![] };
-    let orgs = if types.contains(&"organizations") || types.contains(&"orgs") {
-        extract_organizations(text)
-    } else { vec![] };
-    let amounts = if types.contains(&"amounts") { extract_amounts(text) } else { vec![] };
-    let locations = if types.contains(&"locations") { extract_locations(text) } else { vec![] };
-    let emails = if types.contains(&"emails") { extract_emails(text) } else { vec![] };
-    let phones = if types.contains(&"phones") { extract_phones(text) } else { vec![] };
-    let urls = if types.contains(&"urls") { extract_urls(text) } else { vec![] };
-
-    let total_count = dates.len() + persons.len() + orgs.len() + amounts.len() +
-                      locations.len() + emails.len() + phones.len() + urls.len();
-
-    ExtractionResult {
-        dates,
-        persons,
-        organizations: orgs,
-        amounts,
-        locations,
-        emails,
-        phones,
-        urls,
-        total_count,
-        processing_time_ms: start.elapsed().as_millis() as u64,
+    fn deref(&self) -> &Regex {
+        &self.regex
     }
 }
 
-// =============================================================================
-// BATCH PROCESSING
-// =============================================================================
+/// Currency symbols and their ISO 4217 codes; "$" is read as USD and "¥"
+/// as JPY
+const CURRENCY_SYMBOLS: &[(&str, &str)] = &[
+    ("$", "USD"), ("€", "EUR"), ("£", "GBP"), ("¥", "JPY"), ("₹", "INR"), ("₩", "KRW"), ("₽", "RUB"), ("฿", "THB"),
+    ("₺", "TRY"), ("₪", "ILS"), ("₫", "VND"), ("₱", "PHP"), ("₴", "UAH"), ("₦", "NGN"),
+];
 
-/// Process multiple documents in parallel
-pub fn extract_batch(documents: &[&str]) -> Vec<ExtractionResult> {
-    documents.par_iter()
-        .map(|doc| extract_all(doc))
-        .collect()
+/// ISO 4217 codes amounts are written with, as a regex alternation. CHF
+/// amounts are apostrophe-grouped, so they are left to `NumberLocale::Swiss`
+/// rather than read as "567.89 CHF" out of "1'234'567.89 CHF".
+const CURRENCY_CODES: &str = "USD|EUR|GBP|JPY|CNY|INR|KRW|RUB|THB|TRY|ILS|VND|PHP|UAH|NGN|AUD|CAD|HKD|SGD|MXN|BRL|ZAR|AED|SAR";
+
+/// What may precede an amount, as a regex alternation: a symbol of
+/// `CURRENCY_SYMBOLS` or a code
+fn currency_prefix() -> String {
+    let symbols: Vec<String> = CURRENCY_SYMBOLS.iter().map(|(symbol, _)| regex::escape(symbol)).collect();
+    format!(r"{}|\b(?:{})\b", symbols.join("|"), CURRENCY_CODES)
 }
 
-// =============================================================================
-// TESTS
-// =============================================================================
+/// Words between a name and its alias, as a regex alternation
+const ALIAS_CONNECTORS: &str = r"a/k/a|f/k/a|d/b/a|aka|fka|dba|(?:also|formerly)\s+known\s+as|doing\s+business\s+as";
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+/// Currencies without a minor unit, whose amounts are whole numbers
+const ZERO_DECIMAL_CURRENCIES: &[&str] = &["JPY", "KRW", "VND"];
 
-    #[test]
-    fn test_date_extraction() {
-        let text = "Meeting scheduled for January 15, 2024 and 2024-01-20";
-        let result = extract_dates(text);
-        assert!(result.len() >= 2);
+/// ISO 4217 code of the currency an amount is written in, if it names one
+pub(crate) fn currency_code(value: &str) -> Option<&'static str> {
+    let upper = value.to_uppercase();
+    if let Some(code) = CURRENCY_CODES.split('|').find(|code| upper.contains(code)) {
+        return Some(code);
     }
-
-    #[test]
-    fn test_person_extraction() {
-        let text = "Jeffrey Epstein met with Bill Clinton at the estate.";
-        let result = extract_persons(text);
-        assert!(result.iter().any(|e| e.value.contains("Epstein")));
+    if let Some(&(_, code)) = CURRENCY_SYMBOLS.iter().find(|(symbol, _)| value.contains(symbol)) {
+        return Some(code);
     }
+    let lower = value.to_lowercase();
+    [("dollar", "USD"), ("euro", "EUR"), ("pound", "GBP"), ("chf", "CHF"), ("fr.", "CHF")]
+        .iter()
+        .find(|(word, _)| lower.contains(word))
+        .map(|&(_, code)| code)
+}
 
-    #[test]
-    fn test_amount_extraction() {
-        let text = "The transaction was $5.5 million USD and another $100,000";
-        let result = extract_amounts(text);
-        assert!(result.len() >= 2);
-    }
+fn named(name: &'static str, pattern: &str) -> NamedPattern {
+    NamedPattern { name, regex: compile_pattern(pattern).unwrap(), confidence: None }
+}
 
-    #[test]
-    fn test_parallel_extraction() {
-        let text = "On January 15, 2024, Jeffrey Epstein transferred $5 million to Clinton Foundation in New York. Contact: jeff@example.com";
-        let result = extract_all(text);
-        assert!(result.dates.len() > 0);
-        assert!(result.persons.len() > 0);
-        assert!(result.amounts.len() > 0);
-        assert!(result.emails.len() > 0);
+lazy_static! {
+    // Dates - multiple formats
+    static ref DATE_PATTERNS: Vec<NamedPattern> = vec![
+        // ISO: 2024-01-15
+        named("date.iso", r"\b([0-9]{4})-([0-9]{2})-([0-9]{2})\b").with_confidence(0.95),
+        // US: 01/15/2024 or 1/15/24
+        named("date.us", r"\b([0-9]{1,2})/([0-9]{1,2})/([0-9]{2,4})\b"),
+        // EU: 15-01-2024 or 15.01.2024
+        named("date.eu", r"\b([0-9]{1,2})[-.]([0-9]{1,2})[-.]([0-9]{2,4})\b"),
+        // Written: January 15, 2024 or Jan 15 2024
+        named("date.written", r"\b(Jan(?:uary)?|Feb(?:ruary)?|Mar(?:ch)?|Apr(?:il)?|May|June?|July?|Aug(?:ust)?|Sep(?:t(?:ember)?)?|Oct(?:ober)?|Nov(?:ember)?|Dec(?:ember)?)\s+([0-9]{1,2})(?:st|nd|rd|th)?,?\s*([0-9]{4})\b"),
+        // Month Year: January 2024
+        named("date.month_year", r"\b(Jan(?:uary)?|Feb(?:ruary)?|Mar(?:ch)?|Apr(?:il)?|May|June?|July?|Aug(?:ust)?|Sep(?:t(?:ember)?)?|Oct(?:ober)?|Nov(?:ember)?|Dec(?:ember)?)\s+([0-9]{4})\b").with_confidence(0.75),
+    ];
+
+    // Person names - sophisticated patterns
+    static ref PERSON_PATTERNS: Vec<NamedPattern> = vec![
+        // Full name: John Smith, John A. Smith, John Allen Smith, José García
+        named("person.full_name", r"\b(\p{Lu}\p{Ll}+(?:\s+\p{Lu}\.?)?\s+\p{Lu}\p{Ll}+(?:\s+(?:Jr|Sr|III|IV|II)\.?)?)\b"),
+        // Title + Name: Mr. John Smith, Dr. Jane Doe
+        named("person.titled", r"\b((?:Mr|Mrs|Ms|Miss|Dr|Prof|Rev|Hon|Sir|Dame)\.?\s+\p{Lu}\p{Ll}+(?:\s+\p{Lu}\p{Ll}+)+)\b"),
+        // Last, First: Smith, John
+        named("person.last_first", r"\b(\p{Lu}\p{Ll}+,\s+\p{Lu}\p{Ll}+(?:\s+\p{Lu}\.)?)\b"),
+    ];
+
+    // Organizations
+    static ref ORG_PATTERNS: Vec<NamedPattern> = vec![
+        // Company suffixes. The legal abbreviations match in any case ("acme
+        // inc", "Globex llc"); whole words like "limited" stay capitalized,
+        // lowercase they are ordinary prose
+        named("org.suffix", r"\b([A-Z][A-Za-z]*(?:\s+[A-Z][A-Za-z]*)*,?\s+(?:Incorporated|(?i:Inc|Corp|LLC|Ltd|LLP)|Limited|Co|Company|Corporation|Group|Holdings|Partners|Associates|Foundation|Institute|University|Bank|Trust|Fund|Capital|Ventures|International|Worldwide|Global)\.?)\b"),
+        // The X Organization/Foundation
+        named("org.the", r"\b(The\s+[A-Z][A-Za-z]*(?:\s+[A-Z][A-Za-z]*)*\s+(?:Organization|Foundation|Institute|Association|Society|Council|Committee|Commission|Agency|Bureau|Department|Ministry))\b"),
+        // ALL CAPS legal style: ACME HOLDINGS INC
+        named("org.caps", r"\b((?:[A-Z][A-Z0-9&'-]*,?\s+)+(?:INC|CORP|LLC|LTD|LLP|CO|COMPANY|CORPORATION|GROUP|HOLDINGS|PARTNERS|ASSOCIATES|FOUNDATION|INSTITUTE|UNIVERSITY|BANK|TRUST|FUND|CAPITAL|VENTURES|INTERNATIONAL|WORLDWIDE|GLOBAL)\b\.?)"),
+        // Acronyms: FBI, CIA, NSA
+        named("org.acronym", r"\b([A-Z]{2,6})\b"),
+    ];
+
+    // Money amounts
+    static ref AMOUNT_PATTERNS: Vec<NamedPattern> = {
+        let prefix = currency_prefix();
+        vec![
+            // $1,234.56 or $1234.56, and Indian grouping: ₹5,00,000
+            named("amount.symbol", &format!(r"({prefix})\s*(\d{{1,2}}(?:,\d{{2}})+,\d{{3}}(?:\.\d{{2}})?|\d{{1,3}}(?:,\d{{3}})*(?:\.\d{{2}})?)")),
+            // 1,234.56 USD
+            named("amount.code", &format!(r"(\d{{1,3}}(?:,\d{{3}})*(?:\.\d{{2}})?)\s*(\b(?:{CURRENCY_CODES})\b|dollars?|euros?|pounds?)")),
+            // $X million/billion
+            named("amount.magnitude", &format!(r"({prefix})\s*(\d+(?:\.\d+)?)\s*(million|billion|thousand|M|B|K)")),
+            // X million dollars
+            named("amount.magnitude_words", &format!(r"(\d+(?:\.\d+)?)\s*(million|billion|thousand)\s*(dollars?|\b(?:{CURRENCY_CODES})\b|euros?|pounds?)")),
+        ]
+    };
+    // Amounts in words: five hundred thousand dollars, twenty-five euros,
+    // one million two hundred thousand dollars; `parse_written_amounts` only
+    static ref WRITTEN_AMOUNT_PATTERN: NamedPattern = named(
+        "amount.written",
+        concat!(
+            r"(?i)\b(?:(?:twenty|thirty|forty|fifty|sixty|seventy|eighty|ninety)(?:-(?:one|two|three|four|five|six|seven|eight|nine))?",
+            r"|ten|eleven|twelve|thirteen|fourteen|fifteen|sixteen|seventeen|eighteen|nineteen",
+            r"|one|two|three|four|five|six|seven|eight|nine)\b",
+            r"(?:(?:\s+and\s+|\s+|-)(?:twenty|thirty|forty|fifty|sixty|seventy|eighty|ninety|ten|eleven|twelve|thirteen",
+            r"|fourteen|fifteen|sixteen|seventeen|eighteen|nineteen|one|two|three|four|five|six|seven|eight|nine",
+            r"|hundred|thousand|million|billion)\b)*",
+            r"\s+(?:U\.?S\.?\s+)?(?:dollars?|euros?|pounds?(?:\s+sterling)?)\b",
+        ),
+    );
+
+    // Amounts with locale separators, currency before or after. A group is
+    // exactly three digits after a single separator, so a neighbouring
+    // number ("in 2019 1 234 EUR", "EUR 1 234 and 5") stays out
+    static ref EUROPEAN_AMOUNT_PATTERNS: Vec<NamedPattern> = locale_amount_patterns(
+        ["amount.european_prefix", "amount.european_suffix"],
+        r"\d{1,3}(?:[ \u{a0}\u{202f}.]\d{3}\b)+(?:,\d{1,2})?|\d+,\d{1,2}"
+    );
+    static ref SWISS_AMOUNT_PATTERNS: Vec<NamedPattern> = locale_amount_patterns(
+        ["amount.swiss_prefix", "amount.swiss_suffix"],
+        r"\d{1,3}(?:['’]\d{3}\b)+(?:\.\d{1,2})?"
+    );
+
+    // Locations
+    static ref LOCATION_PATTERNS: Vec<NamedPattern> = vec![
+        // City, State/Country
+        named("location.city_region", r"\b([A-Z][a-z]+(?:\s+[A-Z][a-z]+)*),\s*([A-Z]{2}|[A-Z][a-z]+(?:\s+[A-Z][a-z]+)*)\b"),
+        // Known places
+        named("location.known", r"\b(New York|Los Angeles|London|Paris|Tokyo|Hong Kong|Singapore|Dubai|Miami|Washington|Virgin Islands|Little St\.? James|Palm Beach|Manhattan|Florida|California|Texas)\b"),
+        // Street addresses
+        named("location.street", r"\b(\d+\s+[A-Z][a-z]+(?:\s+[A-Z][a-z]+)*\s+(?:Street|St|Avenue|Ave|Road|Rd|Boulevard|Blvd|Drive|Dr|Lane|Ln|Court|Ct|Way|Place|Pl)\.?)\b"),
+    ];
+
+    // Email addresses
+    static ref EMAIL_PATTERN: NamedPattern = named(
+        "email",
+        r"\b([A-Za-z0-9._%+-]+@[A-Za-z0-9.-]+\.[A-Za-z]{2,})\b",
+    );
+    // Emails with "@" and "." spelled out: jeff [at] example [dot] com,
+    // a (at) b.com, jeff at example dot com
+    static ref OBFUSCATED_EMAIL_PATTERN: NamedPattern = named(
+        "email.obfuscated",
+        r"(?i)\b[A-Za-z0-9._%+-]+(?:\s*[\[({]at[\])}]\s*|\s+at\s+)[A-Za-z0-9-]+(?:(?:\s*[\[({]dot[\])}]\s*|\s+dot\s+|\.)[A-Za-z0-9-]+)+\b",
+    );
+    static ref SPELLED_AT: Regex = compile_pattern(r"(?i)\s*[\[({]at[\])}]\s*|\s+at\s+").unwrap();
+    static ref SPELLED_DOT: Regex = compile_pattern(r"(?i)\s*[\[({]dot[\])}]\s*|\s+dot\s+").unwrap();
+
+    // Phone numbers
+    static ref PHONE_PATTERN: NamedPattern = named(
+        "phone",
+        &format!(r"(?:\+?1[-.\s]?)?\(?([0-9]{{3}})\)?[-.\s]?([0-9]{{3}})[-.\s]?([0-9]{{4}}){PHONE_EXTENSION}"),
+    );
+    // +CC and 7 to 15 digits, single separators between them: +44 20 7946
+    // 0958, +33 1.23.45.67.89, +44 (0)20 7946 0958
+    static ref INTERNATIONAL_PHONE_PATTERN: NamedPattern = named(
+        "phone.international",
+        &format!(r"\+[0-9](?:[ .()-]{{0,2}}[0-9]){{6,14}}{PHONE_EXTENSION}"),
+    );
+
+    // URLs
+    static ref URL_PATTERN: NamedPattern = named(
+        "url",
+        r"https?://[A-Za-z0-9][-A-Za-z0-9+&@#/%?=~_|!:,.;]*[-A-Za-z0-9+&@#/%=~_|]",
+    );
+    // Defanged URLs and domains, as threat reports write them:
+    // hxxp://evil[.]com/path, evil(dot)com. Candidates without a defanged
+    // scheme or dot are plain text ("v1.2", "https://example.com").
+    static ref DEFANGED_URL_PATTERN: NamedPattern = named(
+        "url.defanged",
+        concat!(
+            r"(?i-u)(?u:\b)(?:(?:hxxps?|https?|fxps?|ftp)(?:\[:\]|:)//)?",
+            r"[a-z0-9](?:[a-z0-9-]*[a-z0-9])?(?:(?:\.|\[\.\]|\(\.\)|\{\.\}|\[dot\]|\(dot\)|\{dot\})[a-z0-9](?:[a-z0-9-]*[a-z0-9])?)+",
+            r"(?::\d{1,5})?(?:/[-a-z0-9+&@#/%?=~_|!:,.;\[\]]*[-a-z0-9+&@#/%=~_|\]])?",
+        ),
+    );
+    static ref DEFANGED_DOT: Regex = compile_pattern(r"(?i)\[\.\]|\(\.\)|\{\.\}|\[dot\]|\(dot\)|\{dot\}").unwrap();
+    // `OBFUSCATION_MARKERS` and `DEFANG_MARKERS` in one, so `Sniff` reads
+    // the text once for both; the bracketed dots are in each
+    static ref SNIFF_MARKERS: aho_corasick::AhoCorasick = aho_corasick::AhoCorasick::builder()
+        .ascii_case_insensitive(true)
+        .build(OBFUSCATION_MARKERS.iter().chain(DEFANG_MARKERS))
+        .unwrap();
+
+    // Postal codes
+    static ref US_ZIP_PATTERN: NamedPattern = named("postal.us", r"\b\d{5}(?:-\d{4})?\b");
+    static ref CA_POSTAL_PATTERN: NamedPattern = named(
+        "postal.ca",
+        r"\b[ABCEGHJ-NPRSTVXY]\d[ABCEGHJ-NPRSTV-Z] ?\d[ABCEGHJ-NPRSTV-Z]\d\b",
+    );
+    static ref UK_POSTCODE_PATTERN: NamedPattern = named(
+        "postal.uk",
+        r"\b(?:[A-Z]{1,2}\d[A-Z\d]?|GIR) ?\d[ABD-HJLNP-UW-Z]{2}\b",
+    );
+    // What may precede a bare 5-digit number for it to count as a ZIP:
+    // a state abbreviation ("Miami, FL 33101") or an explicit label
+    static ref US_ZIP_CONTEXT: Regex = compile_pattern(
+        r"(?i:\b(?:zip(?:\s*code)?|postcode|postal\s+code)\b[\s:#.]*$)|(?:\b(?:AL|AK|AZ|AR|CA|CO|CT|DE|DC|FL|GA|HI|ID|IL|IN|IA|KS|KY|LA|ME|MD|MA|MI|MN|MS|MO|MT|NE|NV|NH|NJ|NM|NY|NC|ND|OH|OK|OR|PA|RI|SC|SD|TN|TX|UT|VT|VA|WA|WV|WI|WY|PR)\.?,?\s+$)"
+    ).unwrap();
+
+    // Legal citations
+    // Case reporters: 123 F.3d 456, 410 U.S. 113, 999 F. Supp. 2d 12
+    static ref CASE_CITATION_PATTERN: NamedPattern = named(
+        "citation.case",
+        r"\b(\d{1,4})\s+(U\.\s?S\.|S\.\s?Ct\.|L\.\s?Ed\.(?:\s?2d)?|F\.(?:\s?(?:2d|3d|4th))?|F\.\s?Supp\.(?:\s?(?:2d|3d))?|F\.\s?App'x|B\.R\.|So\.(?:\s?(?:2d|3d))?|N\.[EW]\.(?:\s?(?:2d|3d))?|[NS]\.E\.(?:\s?(?:2d|3d))?|[AP]\.(?:\s?(?:2d|3d))?|S\.W\.(?:\s?(?:2d|3d))?)\s+(\d{1,5})\b",
+    );
+    // Statutes and regulations: 42 U.S.C. § 1983, 17 C.F.R. §§ 240.10b-5
+    static ref STATUTE_CITATION_PATTERN: NamedPattern = named(
+        "citation.statute",
+        r"\b(\d{1,3})\s+(U\.\s?S\.\s?C\.(?:\s?A\.)?|C\.\s?F\.\s?R\.)\s*(?:§§?|[Ss]ec(?:tion)?\.?)\s*(\d+[A-Za-z0-9.-]*(?:\([A-Za-z0-9]+\))*)",
+    );
+
+    // Cross-references within a filing: Exhibit 12, Exhibit A-1, Item 4(b),
+    // Section 3.2, Paragraph 37
+    static ref REFERENCE_PATTERN: NamedPattern = named(
+        "reference",
+        r"\b(Exhibits?|Items?|Sections?|Paragraphs?|Para\.|Sec\.)\s+(\d+(?:\.\d+)*[A-Za-z]?(?:-\d+)?(?:\([A-Za-z0-9]+\))*|[A-Z]{1,2}(?:-\d+)?\b)",
+    );
+    // ¶ 37, ¶¶ 12-14
+    static ref PARAGRAPH_SIGN_PATTERN: NamedPattern = named(
+        "reference.paragraph",
+        r"¶¶?\s*(\d+(?:\.\d+)*(?:\([A-Za-z0-9]+\))*(?:\s*[-–]\s*\d+)?)",
+    );
+
+    // Percentages: 12.5%, 40 percent, 3 per cent
+    static ref PERCENT_PATTERN: NamedPattern = named(
+        "percent",
+        r"(?i)\b(\d+(?:\.\d+)?)(?:\s?%|\s+per\s?cent\b)",
+    );
+    // Written fractions: one-third, two thirds, three-quarters
+    static ref FRACTION_PATTERN: NamedPattern = named(
+        "percent.fraction",
+        r"(?i)\b(one|two|three|four|five|six|seven|eight|nine)[- ](halves|half|thirds?|quarters?|fourths?|fifths?|sixths?|eighths?|tenths?)\b",
+    );
+    // Ratios: 2:1, 3.5:1
+    static ref RATIO_PATTERN: NamedPattern = named(
+        "ratio",
+        r"\b(\d{1,6}(?:\.\d+)?):(\d{1,6}(?:\.\d+)?)\b",
+    );
+
+    // Employer Identification Numbers: 12-3456789
+    static ref EIN_PATTERN: NamedPattern = named("ein", r"\b(\d{2})-\d{7}\b");
+    // ABA routing numbers: 021000021
+    static ref ROUTING_NUMBER_PATTERN: NamedPattern = named("routing_number", r"\b[0-9]{9}\b");
+    // ICD-10 codes: E11, E11.9, S72.001A
+    static ref ICD10_PATTERN: NamedPattern = named("icd10", r"\b([A-Z]\d{2})(?:\.[0-9A-Z]{1,4})?\b");
+    static ref ICD10_CUE_PATTERN: Regex = compile_pattern(
+        r"(?i)\b(?:ICD(?:-?1[01])?(?:-CM)?|diagnos(?:is|es|ed)|dx|codes?)\b"
+    ).unwrap();
+
+    // Between a name and its alias: ", aka", " f/k/a", "(formerly known as"
+    static ref ALIAS_CONNECTOR_PATTERN: Regex = compile_pattern(
+        &format!(r#"^,?\s*\(?(?i:{ALIAS_CONNECTORS})\.?\s+["“]?"#)
+    ).unwrap();
+    // A person alias the person patterns miss: "Jack", "Big Jim"
+    static ref ALIAS_NAME_PATTERN: Regex = compile_pattern(r"^\p{Lu}\p{Ll}+(?: \p{Lu}\p{Ll}+)*\b").unwrap();
+
+    // ISBN-13 (978/979 prefix) or ISBN-10, digits optionally grouped by
+    // single hyphens or spaces: 978-0-306-40615-7, 0 306 40615 2
+    static ref ISBN_PATTERN: NamedPattern = named(
+        "isbn",
+        r"\b(?:97[89](?:[- ]?\d){10}|\d(?:[- ]?\d){8}[- ]?[\dX])\b",
+    );
+    static ref ISBN_CUE_PATTERN: Regex = compile_pattern(r"(?i)\bISBN(?:-1[03])?:?\s*$").unwrap();
+    // DOIs: 10.1000/xyz123, 10.1038/nphys1170; trailing punctuation is
+    // trimmed after the match
+    static ref DOI_PATTERN: NamedPattern = named("doi", r#"\b10\.\d{4,9}/[^\s"<>]+"#);
+
+    // One capitalized word, a candidate first or last name
+    static ref NAME_TOKEN_PATTERN: Regex = compile_pattern(r"\b\p{Lu}\p{Ll}+\b").unwrap();
+
+    // Decimal degree pairs: 40.7128, -74.0060 or 40.7128° N, 74.0060° W
+    static ref DECIMAL_COORDINATE_PATTERN: NamedPattern = named(
+        "coordinate.decimal",
+        r"([-+]?\d{1,2}\.\d{3,})°?(?:\s*([NS]))?\s*,\s*([-+]?\d{1,3}\.\d{3,})°?(?:\s*([EW])\b)?"
+    );
+    // Degrees, minutes, seconds: 40°42'46"N 74°00'22"W
+    static ref DMS_COORDINATE_PATTERN: NamedPattern = named(
+        "coordinate.dms",
+        r#"\b(\d{1,2})°\s*(\d{1,2})['′]\s*(?:(\d{1,2}(?:\.\d+)?)["″]\s*)?([NS])[,\s]*(\d{1,3})°\s*(\d{1,2})['′]\s*(?:(\d{1,2}(?:\.\d+)?)["″]\s*)?([EW])\b"#
+    );
+
+    // A plate after its cue: "plate ABC-1234", "tag # 7XYZ123",
+    // "registration no. AB 12"; the state before the cue is looked up after
+    static ref LICENSE_PLATE_PATTERN: NamedPattern = named(
+        "license_plate",
+        r"\b(?i:(?:licen[cs]e|vehicle)\s+)?(?i:plates?|tags?|registration)(?i:\s+(?:no\.?|number))?\s*[:#]?\s*([A-Z0-9]{1,4}(?:[- ]?[A-Z0-9]{1,4})?)\b",
+    );
+
+    static ref ROUTING_CUE_PATTERN: Regex = compile_pattern(
+        r"(?i)\b(?:routing|ABA|RTN|transit)\b"
+    ).unwrap();
+
+    static ref EIN_CUE_PATTERN: Regex = compile_pattern(
+        r"(?i)\b(?:EIN|FEIN|TIN|tax\s+id(?:entification)?|federal\s+(?:tax\s+)?id(?:entification)?|employer\s+identification)\b"
+    ).unwrap();
+
+    // One or more blank lines between paragraphs
+    static ref PARAGRAPH_BREAK_PATTERN: Regex = compile_pattern(r"\r?\n(?:[ \t]*\r?\n)+").unwrap();
+    // A transcript line's leading timestamp: "[00:12:34]", "[12:34]", "12:34"
+    static ref LINE_TIMESTAMP_PATTERN: Regex = compile_pattern(
+        r"(?m)^[ \t]*(?:\[((?:[0-9]{1,2}:)?[0-9]{1,3}:[0-9]{2})\]|((?:[0-9]{1,2}:)?[0-9]{1,3}:[0-9]{2})\b)"
+    ).unwrap();
+
+    // Numbers that may start a quantity; the unit is looked up after the match
+    static ref QUANTITY_NUMBER_PATTERN: NamedPattern = named(
+        "quantity",
+        r"\b(\d{1,3}(?:,\d{3})+|\d+)(\.\d+)?",
+    );
+
+    // Redaction placeholders: [REDACTED], (WITHHELD), <REDACTED>, with an
+    // optional label inside: [REDACTED - (b)(6)]
+    static ref BRACKETED_REDACTION_PATTERN: NamedPattern = named(
+        "redaction.bracketed",
+        r"(?i)\[\s*(?:REDACTED|REDACTION|WITHHELD|DELETED|REMOVED)\b[^\]\n]{0,40}\]|\(\s*(?:REDACTED|WITHHELD)\b[^)\n]{0,40}\)|<\s*(?:REDACTED|WITHHELD)\b[^>\n]{0,40}>",
+    );
+    // Runs of block characters: █████, ▇▇▇, ■■■
+    static ref BLOCK_REDACTION_PATTERN: NamedPattern = named("redaction.block", r"[█▇▆▓▒■▮]{2,}(?:[ \t]+[█▇▆▓▒■▮]{2,})*");
+    // Runs of X; length is checked against `redaction_min_x`
+    static ref X_REDACTION_PATTERN: NamedPattern = named("redaction.x_run", r"\b(?:X{2,}|x{2,})\b");
+
+    // Ages: "age 42", "aged 42", "42-year-old", "42 years old"
+    static ref AGE_PATTERNS: Vec<NamedPattern> = vec![
+        named("age.age_n", r"(?i)\bage[ds]?\s+([0-9]{1,3})\b"),
+        named("age.years_old", r"(?i)\b([0-9]{1,3})[- ]years?[- ]old\b"),
+    ];
+    // Date ranges: from January 2020 to March 2021, 2020–2021, 2019-21,
+    // 1/5/2020 through 2/7/2020. Each side is any one date form or a year.
+    static ref DATE_RANGE_PATTERN: NamedPattern = {
+        let month = r"(?:Jan(?:uary)?|Feb(?:ruary)?|Mar(?:ch)?|Apr(?:il)?|May|June?|July?|Aug(?:ust)?|Sep(?:t(?:ember)?)?|Oct(?:ober)?|Nov(?:ember)?|Dec(?:ember)?)";
+        let side = format!(
+            r"[0-9]{{4}}-[0-9]{{2}}-[0-9]{{2}}|[0-9]{{1,2}}/[0-9]{{1,2}}/[0-9]{{2,4}}|{month}\s+[0-9]{{1,2}}(?:st|nd|rd|th)?,?\s*[0-9]{{4}}|{month}\s+[0-9]{{4}}|(?:19|20)[0-9]{{2}}"
+        );
+        named(
+            "date.range",
+            &format!(r"\b({side})(?:\s*[–—-]\s*|\s+(?:to|through|thru|until)\s+)({side}|[0-9]{{2}})\b"),
+        )
+    };
+    // Durations: three years, 18 months, a week, 2.5 years, six-month
+    static ref DURATION_PATTERN: NamedPattern = named(
+        "duration",
+        concat!(
+            r"(?i)\b((?:\d+(?:\.\d+)?|an?|(?:twenty|thirty|forty|fifty|sixty|seventy|eighty|ninety)(?:-(?:one|two|three|four|five|six|seven|eight|nine))?",
+            r"|ten|eleven|twelve|thirteen|fourteen|fifteen|sixteen|seventeen|eighteen|nineteen",
+            r"|one|two|three|four|five|six|seven|eight|nine))[\s-]+(years?|months?|weeks?|days?)\b",
+        ),
+    );
+    // Titles of works in quotes or Markdown italics: "The Panama Papers",
+    // *Filthy Rich*; the title is the first group that took part
+    static ref QUOTED_TITLE_PATTERN: NamedPattern = named(
+        "work_title.quoted",
+        r#""([^"\n]{3,120})"|“([^”\n]{3,120})”|\*([^*\s][^*\n]{1,118}[^*\s])\*|\b_([^_\s][^_\n]{1,118}[^_\s])_\b"#,
+    );
+    // Case names by their fixed openings: In re Acme, United States v.
+    // Smith, People v. Jones
+    static ref CASE_NAME_PATTERN: NamedPattern = named(
+        "work_title.case",
+        r"\b(?:In re|In the Matter of|Ex parte|(?:United States|State|People|Commonwealth)(?: of [A-Z][a-z]+)? v\.)\s+[A-Z][\w'&-]*(?:\s+(?:[A-Z][\w'&-]*|of|and|&))*",
+    );
+    // Quarters: Q3 2024, Q3'24, 3Q24, Q3 FY2024, 2024-Q3
+    static ref QUARTER_PATTERN: NamedPattern = named(
+        "date.quarter",
+        r"\b(?:Q([1-4])|([1-4])Q)(?:\s*|['’]|-)(FY\s?)?([0-9]{4}|[0-9]{2})\b|\b([0-9]{4})[\s-]?Q([1-4])\b",
+    );
+    // Written quarters: the third quarter of 2024, 2nd quarter fiscal 2023
+    static ref WRITTEN_QUARTER_PATTERN: NamedPattern = named(
+        "date.quarter_written",
+        r"(?i)\b(first|second|third|fourth|1st|2nd|3rd|4th)\s+quarter\s+(?:of\s+)?(fiscal\s+(?:year\s+)?|FY\s?)?([0-9]{4})\b",
+    );
+    // Fiscal years: FY2023, FY 23, fiscal 2023, fiscal year 2023
+    static ref FISCAL_YEAR_PATTERN: NamedPattern = named(
+        "date.fiscal_year",
+        r"\b(?:FY\s?|[Ff]iscal\s+(?:[Yy]ear\s+)?)([0-9]{4}|[0-9]{2})\b",
+    );
+
+    // Bare birth years: "born 1970", "born in 1970", "b. 1970"
+    static ref BIRTH_YEAR_PATTERN: NamedPattern = named(
+        "date.birth_year",
+        r"(?i)(?:\bborn(?:\s+in)?|\bb\.)\s+(\d{4})\b",
+    );
+    // Birth cue right before a date, matched against a short prefix
+    static ref BIRTH_CUE_PATTERN: Regex = compile_pattern(
+        r"(?i)(?:\bborn(?:\s+(?:on|in))?|\bb\.)\s*$"
+    ).unwrap();
+    // Header name opening an RFC 822 line, ahead of the addresses on it
+    static ref EMAIL_HEADER_PATTERN: Regex = compile_pattern(r"(?i)^[ \t]*(from|to|cc|bcc)[ \t]*:").unwrap();
+
+    // Crypto addresses
+    static ref BTC_PATTERN: Regex = compile_pattern(r"\b[13][a-km-zA-HJ-NP-Z1-9]{25,34}\b").unwrap();
+    static ref ETH_PATTERN: Regex = compile_pattern(r"\b0x[a-fA-F0-9]{40}\b").unwrap();
+}
+
+// =============================================================================
+// EXTRACTION FUNCTIONS
+// =============================================================================
+
+/// Byte ranges of Markdown code: fenced blocks (``` or ~~~, through the
+/// closing fence or to the end of the text) and indented blocks (lines
+/// indented four spaces or a tab, starting after a blank line)
+fn code_blocks(text: &str) -> Vec<(usize, usize)> {
+    let mut blocks = Vec::new();
+    let mut fence: Option<(usize, &str)> = None;
+    let mut indented: Option<usize> = None;
+    let mut prev_blank = true;
+    let mut offset = 0;
+    for raw in text.split_inclusive('\n') {
+        let line = raw.trim_end_matches(['\n', '\r']);
+        let trimmed = line.trim_start();
+        let line_start = offset;
+        offset += raw.len();
+
+        if let Some((start, marker)) = fence {
+            if trimmed.starts_with(marker) && trimmed.trim_start_matches(&marker[..1]).trim().is_empty() {
+                blocks.push((start, offset));
+                fence = None;
+            }
+            prev_blank = false;
+            continue;
+        }
+        let marker = ["```", "~~~"].into_iter().find(|m| trimmed.starts_with(m));
+        if let Some(marker) = marker.filter(|_| line.len() - trimmed.len() <= 3) {
+            blocks.extend(indented.take().map(|start| (start, line_start)));
+            fence = Some((line_start, marker));
+        } else if (line.starts_with("    ") || line.starts_with('\t')) && !trimmed.is_empty() {
+            if prev_blank || indented.is_some() {
+                indented.get_or_insert(line_start);
+            }
+        } else if !trimmed.is_empty() {
+            blocks.extend(indented.take().map(|start| (start, line_start)));
+        }
+        prev_blank = trimmed.is_empty();
+    }
+    blocks.extend(fence.map(|(start, _)| (start, text.len())));
+    blocks.extend(indented.map(|start| (start, text.len())));
+    blocks
+}
+
+/// Whether `start..end` overlaps one of the sorted `blocks`
+fn in_code(blocks: &[(usize, usize)], start: usize, end: usize) -> bool {
+    let i = blocks.partition_point(|&(_, block_end)| block_end <= start);
+    blocks.get(i).is_some_and(|&(block_start, _)| block_start < end)
+}
+
+/// Every obfuscated email has one of these: a spelled-out "at" in
+/// brackets, or a bare " at " with a spelled-out dot
+const OBFUSCATION_MARKERS: &[&str] = &["[at]", "(at)", "{at}", " dot ", "[dot]", "(dot)", "{dot}"];
+
+/// Every defanged URL has one of these
+const DEFANG_MARKERS: &[&str] = &["hxxp", "fxp", "[:]", "[.]", "(.)", "{.}", "[dot]", "(dot)", "{dot}"];
+
+/// What a text could hold, from a few byte scans ahead of the extractors
+///
+/// A kind is skipped only when every one of its patterns needs something
+/// the text lacks, so results are the same as running it. On 2,000 prose
+/// documents (4.5 MB, no digits, addresses or links; release build, one
+/// core) the 13 gated kinds went from 145 ms to 33 ms, the fixed cost of
+/// `extract_types`; the rest of `extract_all` is the name-based kinds,
+/// which no sniff can rule out.
+#[derive(Debug, Clone, Copy)]
+struct Sniff {
+    /// Any char `\d` can match; `is_numeric` is a superset of it
+    digit: bool,
+    at: bool,
+    /// An "[at]", " dot " or "[dot]" of an obfuscated email
+    spelled_at: bool,
+    http: bool,
+    /// An "hxxp" or "[.]" of a defanged URL
+    defanged: bool,
+    /// `parse_written_amounts` is on, so amounts need no digit
+    written_amounts: bool,
+}
+
+impl Sniff {
+    fn new(text: &str) -> Self {
+        let bytes = text.as_bytes();
+        let (mut spelled_at, mut defanged) = (false, false);
+        for marker in SNIFF_MARKERS.find_iter(text) {
+            let marker = text[marker.start()..marker.end()].to_ascii_lowercase();
+            spelled_at |= OBFUSCATION_MARKERS.contains(&marker.as_str());
+            defanged |= DEFANG_MARKERS.contains(&marker.as_str());
+            if spelled_at && defanged {
+                break;
+            }
+        }
+        Sniff {
+            digit: bytes.iter().any(u8::is_ascii_digit) || (!text.is_ascii() && text.chars().any(char::is_numeric)),
+            at: bytes.contains(&b'@'),
+            spelled_at,
+            http: text.contains("http"),
+            defanged,
+            written_amounts: false,
+        }
+    }
+
+    /// Whether `kind`'s extractor could find anything
+    fn admits(self, kind: EntityKind) -> bool {
+        match kind {
+            EntityKind::Amount => self.digit || self.written_amounts,
+            EntityKind::Date | EntityKind::Phone | EntityKind::PostalCode
+            | EntityKind::Citation | EntityKind::Age | EntityKind::Ratio | EntityKind::Quantity
+            | EntityKind::Ein | EntityKind::IcdCode | EntityKind::Coordinate | EntityKind::Isbn
+            | EntityKind::Doi | EntityKind::LicensePlate | EntityKind::RoutingNumber => self.digit,
+            EntityKind::Email => self.at || self.spelled_at,
+            EntityKind::Url => self.http || self.defanged,
+            _ => true,
+        }
+    }
+}
+
+/// Per-run state shared by every extractor: options, deadline, timeout flag
+pub struct ExtractCtx<'a> {
+    opts: &'a ExtractOptions,
+    parallel: bool,
+    sniff: Sniff,
+    /// `code_blocks`, with `skip_code_blocks`
+    code: Vec<(usize, usize)>,
+    deadline: Option<Instant>,
+    timed_out: AtomicBool,
+}
+
+impl<'a> ExtractCtx<'a> {
+    fn new(opts: &'a ExtractOptions, text: &str) -> Self {
+        let parallel = match opts.parallelism {
+            Parallelism::Auto => text.len() >= PARALLEL_THRESHOLD_BYTES,
+            Parallelism::Sequential => false,
+            Parallelism::Parallel => true,
+        };
+        ExtractCtx {
+            opts,
+            parallel,
+            sniff: Sniff { written_amounts: opts.parse_written_amounts, ..Sniff::new(text) },
+            code: if opts.skip_code_blocks { code_blocks(text) } else { Vec::new() },
+            deadline: opts.timeout.map(|t| Instant::now() + t),
+            timed_out: AtomicBool::new(false),
+        }
+    }
+
+    pub fn options(&self) -> &ExtractOptions {
+        self.opts
+    }
+
+    /// Whether `start..end` overlaps code `kind` shouldn't be looked for in
+    fn skips_code(&self, kind: EntityKind, start: usize, end: usize) -> bool {
+        matches!(kind, EntityKind::Person | EntityKind::Organization) && in_code(&self.code, start, end)
+    }
+
+    /// True once the deadline has passed; latches so later checks are cheap.
+    /// Long-running custom extractors should poll this and stop early.
+    pub fn expired(&self) -> bool {
+        if self.timed_out.load(Ordering::Relaxed) {
+            return true;
+        }
+        match self.deadline {
+            Some(deadline) if Instant::now() >= deadline => {
+                self.timed_out.store(true, Ordering::Relaxed);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn disabled(&self, pattern: &NamedPattern) -> bool {
+        self.opts.disabled_patterns.contains(pattern.name)
+    }
+
+    /// Captures of `pattern`, stopping early at the deadline
+    fn captures_all<'t>(&self, pattern: &NamedPattern, text: &'t str) -> Vec<regex::Captures<'t>> {
+        if self.expired() || self.disabled(pattern) {
+            return Vec::new();
+        }
+        pattern.captures_iter(text).take_while(|_| !self.expired()).collect()
+    }
+
+    /// Context for a match of `kind`, if the options ask for it
+    fn context(&self, kind: EntityKind, text: &str, start: usize, end: usize) -> Option<String> {
+        self.opts.wants_context(kind)
+            .then(|| context_window(text, start, end, self.opts.context_chars))
+    }
+
+    /// Matches of `pattern`, stopping early at the deadline
+    fn find_all<'t>(&self, pattern: &NamedPattern, text: &'t str) -> Vec<regex::Match<'t>> {
+        // Checked before the first search too: a pattern with no match scans
+        // the whole document in one call, which the deadline can't interrupt
+        if self.expired() || self.disabled(pattern) {
+            return Vec::new();
+        }
+        pattern.find_iter(text).take_while(|_| !self.expired()).collect()
+    }
+}
+
+/// A source of entities that runs alongside the others in one pipeline
+///
+/// Built-in extractors are the `EntityKind` values themselves. Custom ones
+/// (a dictionary matcher, a model) are registered with
+/// `ExtractorBuilder::extractor`; their entities land in the vector for
+/// `kind()`, or `ExtractionResult::custom` when it returns `None`.
+pub trait EntityExtractor: Send {
+    fn extract(&self, text: &str) -> Vec<Entity>;
+
+    /// Run within a shared pipeline run. The default ignores `ctx`;
+    /// override to honor options or the deadline.
+    fn extract_in(&self, text: &str, ctx: &ExtractCtx) -> Vec<Entity> {
+        let _ = ctx;
+        self.extract(text)
+    }
+
+    /// The result vector this extractor fills
+    fn kind(&self) -> Option<EntityKind> {
+        None
+    }
+
+    /// `Entity::entity_type` of a custom extractor's entities, by which
+    /// `types` selects it; one without is left out of typed runs
+    fn entity_type(&self) -> Option<&str> {
+        None
+    }
+}
+
+impl EntityExtractor for EntityKind {
+    fn extract(&self, text: &str) -> Vec<Entity> {
+        let options = ExtractOptions::default();
+        self.extract_in(text, &ExtractCtx::new(&options, text))
+    }
+
+    fn extract_in(&self, text: &str, ctx: &ExtractCtx) -> Vec<Entity> {
+        extract_kind(*self, text, ctx)
+    }
+
+    fn kind(&self) -> Option<EntityKind> {
+        Some(*self)
+    }
+}
+
+/// Canonical form of an entity's value, stored as `metadata["normalized"]`
+///
+/// The built-in amount and org extractors normalize through
+/// `AmountNormalizer` and `OrgNormalizer`. Register others per kind with
+/// `ExtractorBuilder::normalizer`; they run after extraction and replace the
+/// built-in form. `None` leaves the entity's metadata as it was.
+pub trait Normalizer: Send + Sync {
+    fn normalize(&self, entity: &Entity) -> Option<String>;
+}
+
+impl<F: Fn(&Entity) -> Option<String> + Send + Sync> Normalizer for F {
+    fn normalize(&self, entity: &Entity) -> Option<String> {
+        self(entity)
+    }
+}
+
+/// Registered extractors, run in order and merged in that order
+pub type Registry = Vec<Box<dyn EntityExtractor + Sync>>;
+
+/// One registry entry per `EntityKind`
+pub fn builtin_extractors() -> Registry {
+    EntityKind::ALL.iter().map(|&kind| Box::new(kind) as Box<dyn EntityExtractor + Sync>).collect()
+}
+
+lazy_static! {
+    pub(crate) static ref BUILTIN_EXTRACTORS: Registry = builtin_extractors();
+}
+
+/// Surrounding context, `chars` chars each side of the match
+fn context_window(text: &str, start: usize, end: usize, chars: usize) -> String {
+    let from = match chars {
+        0 => start,
+        n => text[..start].char_indices().rev().nth(n - 1).map_or(0, |(i, _)| i),
+    };
+    let to = text[end..].char_indices().nth(chars).map_or(text.len(), |(i, _)| end + i);
+    text[from..to].to_string()
+}
+
+/// Matches of `patterns`, first occurrence of each value only. A span
+/// several patterns match is one entity with the highest of their
+/// confidences, and their names in `metadata["patterns"]`. Under `raw`,
+/// every match instead.
+fn extract_with_patterns(text: &str, patterns: &[NamedPattern], kind: EntityKind, base_confidence: f64, ctx: &ExtractCtx) -> Vec<Entity> {
+    let mut entities: Vec<Entity> = Vec::new();
+    let mut seen = std::collections::HashSet::new();
+    // Span -> (index in `entities`, names of the patterns that matched it)
+    let mut spans: HashMap<(usize, usize), (usize, Vec<&str>)> = HashMap::new();
+
+    // Matching can run per pattern in parallel; dedup stays in pattern order
+    // so the output is identical either way
+    let per_pattern: Vec<Vec<regex::Match>> = if ctx.parallel {
+        patterns.par_iter().map(|p| ctx.find_all(p, text)).collect()
+    } else {
+        patterns.iter().map(|p| ctx.find_all(p, text)).collect()
+    };
+
+    for (pattern, matches) in patterns.iter().zip(per_pattern) {
+        let confidence = pattern.confidence.unwrap_or(base_confidence);
+        for m in matches {
+            if ctx.skips_code(kind, m.start(), m.end()) {
+                continue;
+            }
+            if ctx.opts.raw {
+                entities.push(Entity {
+                    id: 0,
+                    utf16_start: None,
+                    utf16_end: None,
+                    value: m.as_str().to_string(),
+                    entity_type: kind.as_str().to_string(),
+                    start: m.start(),
+                    end: m.end(),
+                    confidence,
+                    context: ctx.context(kind, text, m.start(), m.end()),
+                    metadata: Some(HashMap::from([("pattern".to_string(), pattern.name.to_string())])),
+                });
+                continue;
+            }
+            if let Some((i, names)) = spans.get_mut(&(m.start(), m.end())) {
+                if !names.contains(&pattern.name) {
+                    names.push(pattern.name);
+                    entities[*i].confidence = entities[*i].confidence.max(confidence);
+                }
+                continue;
+            }
+
+            let value = m.as_str().to_string();
+            let normalized = value.to_lowercase();
+
+            if seen.contains(&normalized) {
+                continue;
+            }
+            seen.insert(normalized);
+            spans.insert((m.start(), m.end()), (entities.len(), vec![pattern.name]));
+
+            entities.push(Entity {
+                id: 0,
+                utf16_start: None,
+                utf16_end: None,
+                value,
+                entity_type: kind.as_str().to_string(),
+                start: m.start(),
+                end: m.end(),
+                confidence,
+                context: ctx.context(kind, text, m.start(), m.end()),
+                metadata: None,
+            });
+        }
+    }
+
+    for (i, names) in spans.into_values() {
+        if names.len() > 1 {
+            entities[i].metadata = Some(HashMap::from([("patterns".to_string(), names.join(","))]));
+        }
+    }
+    entities
+}
+
+/// A quarter or fiscal year: "2024-Q3", "FY2024-Q3", "FY2023"
+struct Period {
+    year: u32,
+    quarter: Option<u32>,
+    fiscal: bool,
+}
+
+impl Period {
+    /// Four digits as written, two as 20xx; None for anything else
+    fn year(digits: &str) -> Option<u32> {
+        let year: u32 = digits.parse().ok()?;
+        Some(if digits.len() == 2 { 2000 + year } else { year })
+    }
+
+    fn metadata(&self) -> HashMap<String, String> {
+        let fy = if self.fiscal { "FY" } else { "" };
+        let (kind, period) = match self.quarter {
+            Some(q) => ("quarter", format!("{}{}-Q{}", fy, self.year, q)),
+            None => ("fiscal_year", format!("FY{}", self.year)),
+        };
+        let mut metadata = HashMap::from([
+            ("kind".to_string(), kind.to_string()),
+            ("period".to_string(), period),
+        ]);
+        // Fiscal years start in different months per organization, so only
+        // calendar quarters get a date range
+        if let (Some(q), false) = (self.quarter, self.fiscal) {
+            let (first, last, last_day) = [(1, 3, 31), (4, 6, 30), (7, 9, 30), (10, 12, 31)][q as usize - 1];
+            metadata.insert("range_start".to_string(), format!("{:04}-{:02}-01", self.year, first));
+            metadata.insert("range_end".to_string(), format!("{:04}-{:02}-{}", self.year, last, last_day));
+        }
+        metadata
+    }
+}
+
+/// Quarters and fiscal years, outside the spans already in `entities`
+fn extract_periods(text: &str, ctx: &ExtractCtx, entities: &mut Vec<Entity>) {
+    let mut found: Vec<(regex::Match, Period, f64)> = Vec::new();
+    for cap in ctx.captures_all(&QUARTER_PATTERN, text) {
+        let (quarter, year) = match (cap.get(1).or(cap.get(2)), cap.get(4)) {
+            (Some(q), Some(y)) => (q, y),
+            _ => (cap.get(6).unwrap(), cap.get(5).unwrap()),
+        };
+        let Some(year) = Period::year(year.as_str()) else { continue };
+        let period = Period { year, quarter: quarter.as_str().parse().ok(), fiscal: cap.get(3).is_some() };
+        found.push((cap.get(0).unwrap(), period, 0.85));
+    }
+    for cap in ctx.captures_all(&WRITTEN_QUARTER_PATTERN, text) {
+        let quarter = match cap[1].to_lowercase().as_str() {
+            "first" | "1st" => 1,
+            "second" | "2nd" => 2,
+            "third" | "3rd" => 3,
+            _ => 4,
+        };
+        let Some(year) = Period::year(&cap[3]) else { continue };
+        let period = Period { year, quarter: Some(quarter), fiscal: cap.get(2).is_some() };
+        found.push((cap.get(0).unwrap(), period, 0.85));
+    }
+    for cap in ctx.captures_all(&FISCAL_YEAR_PATTERN, text) {
+        let Some(year) = Period::year(&cap[1]) else { continue };
+        let period = Period { year, quarter: None, fiscal: true };
+        found.push((cap.get(0).unwrap(), period, 0.80));
+    }
+
+    // Earlier patterns win: "Q3 FY2024" is one quarter, not also a year
+    for (m, period, confidence) in found {
+        if entities.iter().any(|e| e.start < m.end() && m.start() < e.end) {
+            continue;
+        }
+        entities.push(Entity {
+            id: 0,
+            utf16_start: None,
+            utf16_end: None,
+            value: m.as_str().to_string(),
+            entity_type: EntityKind::Date.as_str().to_string(),
+            start: m.start(),
+            end: m.end(),
+            confidence,
+            context: ctx.context(EntityKind::Date, text, m.start(), m.end()),
+            metadata: Some(period.metadata()),
+        });
+    }
+}
+
+fn extract_dates(text: &str, ctx: &ExtractCtx) -> Vec<Entity> {
+    let mut entities = extract_with_patterns(text, &DATE_PATTERNS, EntityKind::Date, EntityKind::Date.default_confidence(), ctx);
+
+    for entity in &mut entities {
+        let from = text[..entity.start].char_indices().rev().nth(15).map_or(0, |(i, _)| i);
+        if BIRTH_CUE_PATTERN.is_match(&text[from..entity.start]) {
+            entity.metadata.get_or_insert_with(HashMap::new)
+                .insert("kind".to_string(), "birthdate".to_string());
+        }
+    }
+
+    // Year-only birthdates, which the date patterns don't cover on their own
+    for cap in ctx.captures_all(&BIRTH_YEAR_PATTERN, text) {
+        let m = cap.get(1).unwrap();
+        if entities.iter().any(|e| e.start < m.end() && m.start() < e.end) {
+            continue;
+        }
+        entities.push(Entity {
+            id: 0,
+            utf16_start: None,
+            utf16_end: None,
+            value: m.as_str().to_string(),
+            entity_type: EntityKind::Date.as_str().to_string(),
+            start: m.start(),
+            end: m.end(),
+            confidence: 0.80,
+            context: ctx.context(EntityKind::Date, text, m.start(), m.end()),
+            metadata: Some(HashMap::from([
+                ("kind".to_string(), "birthdate".to_string()),
+                ("year".to_string(), m.as_str().to_string()),
+            ])),
+        });
+    }
+
+    extract_date_ranges(text, ctx, &mut entities);
+    extract_periods(text, ctx, &mut entities);
+    entities
+}
+
+/// First and last day of a date as the date patterns write it, a month
+/// ("March 2021") or a bare year covering all its days
+fn date_bounds(value: &str) -> Option<(chrono::NaiveDate, chrono::NaiveDate)> {
+    use chrono::NaiveDate;
+    const MONTHS: [&str; 12] = ["jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec"];
+    let month = |name: &str| MONTHS.iter().position(|&m| name.to_lowercase().starts_with(m)).map(|i| i as u32 + 1);
+    let number = |digits: &str| digits.parse::<u32>().ok();
+    let last_of_month = |year: i32, month: u32| {
+        let (next_year, next_month) = if month == 12 { (year + 1, 1) } else { (year, month + 1) };
+        NaiveDate::from_ymd_opt(next_year, next_month, 1)?.pred_opt()
+    };
+
+    if value.len() == 4 && value.bytes().all(|b| b.is_ascii_digit()) {
+        let year = value.parse().ok()?;
+        return Some((NaiveDate::from_ymd_opt(year, 1, 1)?, NaiveDate::from_ymd_opt(year, 12, 31)?));
+    }
+    let (pattern, cap) = DATE_PATTERNS.iter()
+        .find_map(|p| p.captures(value).filter(|c| c.get(0).unwrap().as_str() == value).map(|c| (p.name, c)))?;
+    let (year, month, day) = match pattern {
+        "date.iso" => (cap[1].parse().ok()?, number(&cap[2])?, number(&cap[3])?),
+        "date.us" => (Period::year(&cap[3])? as i32, number(&cap[1])?, number(&cap[2])?),
+        "date.eu" => (Period::year(&cap[3])? as i32, number(&cap[2])?, number(&cap[1])?),
+        "date.written" => (cap[3].parse().ok()?, month(&cap[1])?, number(&cap[2])?),
+        _ => {
+            let (year, month) = (cap[2].parse().ok()?, month(&cap[1])?);
+            return Some((NaiveDate::from_ymd_opt(year, month, 1)?, last_of_month(year, month)?));
+        }
+    };
+    let date = NaiveDate::from_ymd_opt(year, month, day)?;
+    Some((date, date))
+}
+
+/// Date ranges as one date with `metadata["kind"] = "range"` and ISO
+/// `range_start`/`range_end` (first and last day covered), replacing the
+/// dates at either end. A two-digit end year ("2019-21") follows a year.
+fn extract_date_ranges(text: &str, ctx: &ExtractCtx, entities: &mut Vec<Entity>) {
+    for cap in ctx.captures_all(&DATE_RANGE_PATTERN, text) {
+        let m = cap.get(0).unwrap();
+        let (first, last) = (&cap[1], &cap[2]);
+        let Some((range_start, first_end)) = date_bounds(first) else { continue };
+        let range_end = match last.len() {
+            2 if first.len() == 4 => date_bounds(&format!("{}{}", &first[..2], last)).map(|(_, end)| end),
+            2 => None,
+            _ => date_bounds(last).map(|(_, end)| end),
+        };
+        let Some(range_end) = range_end.filter(|&end| end > first_end) else { continue };
+
+        entities.retain(|e| !(e.start < m.end() && m.start() < e.end));
+        entities.push(Entity {
+            id: 0,
+            utf16_start: None,
+            utf16_end: None,
+            value: m.as_str().to_string(),
+            entity_type: EntityKind::Date.as_str().to_string(),
+            start: m.start(),
+            end: m.end(),
+            confidence: 0.85,
+            context: ctx.context(EntityKind::Date, text, m.start(), m.end()),
+            metadata: Some(HashMap::from([
+                ("kind".to_string(), "range".to_string()),
+                ("range_start".to_string(), range_start.to_string()),
+                ("range_end".to_string(), range_end.to_string()),
+            ])),
+        });
+    }
+}
+
+/// Lengths of time, with the ISO 8601 duration as `metadata["normalized"]`
+/// ("P3Y"), the `unit` and `count` as written, and `days` (365-day years,
+/// 30-day months), plus `months` for years and months. Ages ("15 years
+/// old"), points in time ("3 years ago") and frequencies ("twice a week")
+/// are left out.
+fn extract_durations(text: &str, ctx: &ExtractCtx) -> Vec<Entity> {
+    ctx.captures_all(&DURATION_PATTERN, text).iter()
+        .filter_map(|cap| {
+            let m = cap.get(0).unwrap();
+            let after = text[m.end()..].trim_start();
+            let after = after[..after.ceil_char_boundary(after.len().min(8))].to_lowercase();
+            if ["old", "ago", "of age"].iter().any(|w| after.starts_with(w)) {
+                return None;
+            }
+            let count: f64 = match cap[1].to_lowercase().as_str() {
+                "a" | "an" => {
+                    let previous = text[..m.start()].trim_end().rsplit(char::is_whitespace).next().unwrap_or("");
+                    if ["once", "twice", "thrice", "times"].contains(&previous.to_lowercase().as_str()) {
+                        return None;
+                    }
+                    1.0
+                }
+                count => count.parse().ok().or_else(|| written_amount_value(count))?,
+            };
+            let unit = cap[2].to_lowercase();
+            let unit = unit.trim_end_matches('s');
+            let (designator, days, months) = match unit {
+                "year" => ('Y', 365.0, Some(12.0)),
+                "month" => ('M', 30.0, Some(1.0)),
+                "week" => ('W', 7.0, None),
+                _ => ('D', 1.0, None),
+            };
+            let mut metadata = HashMap::from([
+                ("normalized".to_string(), format!("P{}{}", count, designator)),
+                ("unit".to_string(), unit.to_string()),
+                ("count".to_string(), count.to_string()),
+                ("days".to_string(), (count * days).to_string()),
+            ]);
+            if let Some(months) = months {
+                metadata.insert("months".to_string(), (count * months).to_string());
+            }
+            Some(Entity {
+                id: 0,
+                utf16_start: None,
+                utf16_end: None,
+                value: m.as_str().to_string(),
+                entity_type: EntityKind::Duration.as_str().to_string(),
+                start: m.start(),
+                end: m.end(),
+                confidence: EntityKind::Duration.default_confidence(),
+                context: ctx.context(EntityKind::Duration, text, m.start(), m.end()),
+                metadata: Some(metadata),
+            })
+        })
+        .collect()
+}
+
+/// Words a title-cased title may leave lowercase
+const TITLE_MINOR_WORDS: &[&str] = &[
+    "a", "an", "the", "and", "or", "nor", "but", "of", "in", "on", "at", "to", "for", "by", "with", "from", "as", "v.", "vs.",
+];
+
+/// Whether quoted or italic text reads as a title: two or more words, the
+/// first capitalized and every other one too unless it is a minor word
+fn title_cased(text: &str) -> bool {
+    let words: Vec<&str> = text.split_whitespace().collect();
+    let capitalized = |word: &str| word.chars().find(|c| c.is_alphanumeric()).is_some_and(|c| c.is_uppercase() || c.is_ascii_digit());
+    words.len() >= 2
+        && capitalized(words[0])
+        && words[1..].iter().all(|w| capitalized(w) || TITLE_MINOR_WORDS.contains(&w.to_lowercase().as_str()))
+}
+
+/// Titles of works: case names by their opening (`metadata["kind"]`
+/// "case"), and title-cased text in quotes ("quoted") or Markdown italics
+/// ("italic"). A quoted case name is reported once, as a case.
+fn extract_titles(text: &str, ctx: &ExtractCtx) -> Vec<Entity> {
+    let title = |start: usize, end: usize, kind: &str, confidence: f64| Entity {
+        id: 0,
+        utf16_start: None,
+        utf16_end: None,
+        value: text[start..end].to_string(),
+        entity_type: EntityKind::WorkTitle.as_str().to_string(),
+        start,
+        end,
+        confidence,
+        context: ctx.context(EntityKind::WorkTitle, text, start, end),
+        metadata: Some(HashMap::from([("kind".to_string(), kind.to_string())])),
+    };
+
+    let mut titles: Vec<Entity> = ctx.find_all(&CASE_NAME_PATTERN, text).into_iter()
+        .map(|m| {
+            // A trailing "of"/"and" belongs to the sentence
+            let value = m.as_str().trim_end_matches(" of").trim_end_matches(" and").trim_end_matches(" &");
+            title(m.start(), m.start() + value.len(), "case", 0.85)
+        })
+        .collect();
+    let quoted: Vec<Entity> = ctx.captures_all(&QUOTED_TITLE_PATTERN, text).iter()
+        .filter_map(|cap| {
+            let (group, inner) = (1..=4).find_map(|i| cap.get(i).map(|m| (i, m)))?;
+            // American punctuation goes inside the quotes: "The Panama Papers,"
+            let value = inner.as_str().trim().trim_end_matches([',', '.']);
+            let start = inner.start() + (inner.as_str().len() - inner.as_str().trim_start().len());
+            let end = start + value.len();
+            if !title_cased(value) || titles.iter().any(|t| t.start < end && start < t.end) {
+                return None;
+            }
+            Some(title(start, end, if group <= 2 { "quoted" } else { "italic" }, EntityKind::WorkTitle.default_confidence()))
+        })
+        .collect();
+    titles.extend(quoted);
+    titles.sort_by(|a, b| a.order_key().cmp(&b.order_key()));
+    titles
+}
+
+fn extract_ages(text: &str, ctx: &ExtractCtx) -> Vec<Entity> {
+    let mut entities = Vec::new();
+    for pattern in AGE_PATTERNS.iter() {
+        for cap in ctx.captures_all(pattern, text) {
+            let m = cap.get(0).unwrap();
+            let age: u32 = cap[1].parse().unwrap();
+            if age > 130 || entities.iter().any(|e: &Entity| e.start < m.end() && m.start() < e.end) {
+                continue;
+            }
+            entities.push(Entity {
+                id: 0,
+                utf16_start: None,
+                utf16_end: None,
+                value: m.as_str().to_string(),
+                entity_type: EntityKind::Age.as_str().to_string(),
+                start: m.start(),
+                end: m.end(),
+                confidence: 0.85,
+                context: ctx.context(EntityKind::Age, text, m.start(), m.end()),
+                metadata: Some(HashMap::from([("age".to_string(), age.to_string())])),
+            });
+        }
+    }
+    entities.sort_by(|a, b| a.order_key().cmp(&b.order_key()));
+    entities
+}
+
+/// Longest gap between a person and an age or birthdate that still
+/// counts as adjacent ("John Smith, age 42", "John Smith was born ...")
+const PERSON_LINK_MAX_GAP: usize = 24;
+
+/// Set `metadata["person_offset"]` on ages and birthdates sitting next to a
+/// person, to the nearest person's start offset
+fn link_persons(result: &mut ExtractionResult, text: &str) {
+    let ExtractionResult { persons, ages, dates, .. } = result;
+    if persons.is_empty() {
+        return;
+    }
+
+    let birthdates = dates.iter_mut().filter(|e| {
+        e.metadata.as_ref().and_then(|m| m.get("kind")).is_some_and(|k| k == "birthdate")
+    });
+    for entity in ages.iter_mut().chain(birthdates) {
+        let nearest = persons.iter()
+            .filter_map(|p| {
+                let (from, to) = if p.end <= entity.start {
+                    (p.end, entity.start)
+                } else if entity.end <= p.start {
+                    (entity.end, p.start)
+                } else {
+                    return None;
+                };
+                let gap = &text[from..to];
+                if gap.len() > PERSON_LINK_MAX_GAP {
+                    return None;
+                }
+                // No sentence break in between; "b." is a cue, not a full stop
+                let crosses_sentence = gap.replace("b.", "").contains(['.', '!', '?', ';', '\n']);
+                (!crosses_sentence).then_some((gap.len(), p.start))
+            })
+            .min();
+        if let Some((_, person_start)) = nearest {
+            entity.metadata.get_or_insert_with(HashMap::new)
+                .insert("person_offset".to_string(), person_start.to_string());
+        }
+    }
+}
+
+/// One-word mentions of an earlier multi-word person, resolved to the
+/// nearest full mention before them whose first or last name they are.
+/// Words inside any entity, or inside a repeat of a full name, are skipped.
+fn person_coreferences(result: &ExtractionResult, text: &str, options: &ExtractOptions) -> Vec<Entity> {
+    let full: Vec<&Entity> = result.persons.iter()
+        .filter(|p| p.value.split_whitespace().nth(1).is_some())
+        .collect();
+    let mut by_part: HashMap<&str, Vec<&Entity>> = HashMap::new();
+    for &person in &full {
+        let words: Vec<&str> = person.value.split_whitespace().map(|w| w.trim_matches(',')).collect();
+        for part in [words[0], words[words.len() - 1]] {
+            // Titles and initials ("Mr.", "J.") don't identify anyone
+            if part.chars().count() > 1 && !part.ends_with('.') {
+                by_part.entry(part).or_default().push(person);
+            }
+        }
+    }
+    if by_part.is_empty() {
+        return Vec::new();
+    }
+
+    let mut covered: Vec<(usize, usize)> = result.entities().map(|e| (e.start, e.end)).collect();
+    if options.skip_code_blocks {
+        covered.extend(code_blocks(text));
+    }
+    for person in &full {
+        covered.extend(text.match_indices(person.value.as_str()).map(|(i, v)| (i, i + v.len())));
+    }
+
+    NAME_TOKEN_PATTERN.find_iter(text)
+        .filter_map(|m| {
+            let resolved = by_part.get(m.as_str())?.iter()
+                .filter(|p| p.end <= m.start())
+                .max_by_key(|p| p.start)?;
+            if covered.iter().any(|&(start, end)| start < m.end() && m.start() < end) {
+                return None;
+            }
+            Some(Entity {
+                id: 0,
+                utf16_start: None,
+                utf16_end: None,
+                value: m.as_str().to_string(),
+                entity_type: EntityKind::Person.as_str().to_string(),
+                start: m.start(),
+                end: m.end(),
+                confidence: resolved.confidence * 0.9,
+                context: options.wants_context(EntityKind::Person)
+                    .then(|| context_window(text, m.start(), m.end(), options.context_chars)),
+                metadata: Some(HashMap::from([
+                    ("coref".to_string(), resolved.start.to_string()),
+                    ("resolved_name".to_string(), resolved.value.clone()),
+                ])),
+            })
+        })
+        .collect()
+}
+
+/// Set `metadata["alias_of"]`, the primary's start offset, on an entity
+/// that follows one of its own kind across an alias connector ("John
+/// Smith, aka Jack", "Acme Corp, f/k/a Widgets Inc"). A person alias the
+/// person patterns missed, such as a lone first name, is added as a person.
+fn link_aliases(result: &mut ExtractionResult, text: &str, options: &ExtractOptions, regions: &Regions) {
+    let mut covered: Vec<(usize, usize)> = result.entities().map(|e| (e.start, e.end)).collect();
+    if options.skip_code_blocks {
+        covered.extend(code_blocks(text));
+    }
+    for &kind in EntityKind::ALL {
+        let entities = result.field_mut(kind);
+        let mut by_start: HashMap<usize, usize> = HashMap::new();
+        for (i, entity) in entities.iter().enumerate() {
+            by_start.entry(entity.start).or_insert(i);
+        }
+
+        let mut links = Vec::new();
+        let mut added = Vec::new();
+        for primary in entities.iter() {
+            let Some(connector) = ALIAS_CONNECTOR_PATTERN.find(&text[primary.end..]) else { continue };
+            let at = primary.end + connector.end();
+            if let Some(&alias) = by_start.get(&at) {
+                links.push((alias, primary.start));
+                continue;
+            }
+            let Some(name) = ALIAS_NAME_PATTERN.find(&text[at..]).filter(|_| kind == EntityKind::Person) else { continue };
+            let (start, end) = (at, at + name.end());
+            if covered.iter().any(|&(from, to)| from < end && start < to) {
+                continue;
+            }
+            added.push(Entity {
+                id: 0,
+                utf16_start: None,
+                utf16_end: None,
+                value: name.as_str().to_string(),
+                entity_type: kind.as_str().to_string(),
+                start,
+                end,
+                confidence: primary.confidence * 0.9,
+                context: options.wants_context(kind).then(|| context_window(text, start, end, options.context_chars)),
+                metadata: Some(HashMap::from([("alias_of".to_string(), primary.start.to_string())])),
+            });
+        }
+
+        for (alias, primary_start) in links {
+            entities[alias].metadata.get_or_insert_with(HashMap::new)
+                .insert("alias_of".to_string(), primary_start.to_string());
+        }
+        if !added.is_empty() {
+            finalize_entities(&mut added, options, regions);
+            entities.extend(added);
+            entities.sort_by(|a, b| a.order_key().cmp(&b.order_key()));
+        }
+    }
+}
+
+/// Add `person_coreferences` to the persons, in document order
+fn add_person_coreferences(result: &mut ExtractionResult, text: &str, options: &ExtractOptions, regions: &Regions) {
+    let mut mentions = person_coreferences(result, text, options);
+    finalize_entities(&mut mentions, options, regions);
+    result.persons.extend(mentions);
+    result.persons.sort_by(|a, b| a.order_key().cmp(&b.order_key()));
+}
+
+fn extract_persons(text: &str, ctx: &ExtractCtx) -> Vec<Entity> {
+    let mut entities = extract_with_patterns(text, &PERSON_PATTERNS, EntityKind::Person, EntityKind::Person.default_confidence(), ctx);
+    if ctx.opts.raw {
+        return entities;
+    }
+
+    // Filter out common false positives
+    let blacklist = [
+        "the", "this", "that", "with", "from", "have", "will", "been",
+        "january", "february", "march", "april", "may", "june",
+        "july", "august", "september", "october", "november", "december",
+        "monday", "tuesday", "wednesday", "thursday", "friday", "saturday", "sunday"
+    ];
+
+    entities.retain(|e| {
+        let lower = e.value.to_lowercase();
+        !blacklist.iter().any(|b| lower.contains(b))
+    });
+
+    // The heuristic scans the whole text once; skip it on a blown budget
+    let strictness = ctx.opts.person_strictness;
+    if strictness == PersonStrictness::Off || ctx.expired() {
+        return entities;
+    }
+
+    let mid_sentence = capitalized_mid_sentence(text);
+    entities.retain_mut(|e| {
+        // Titled ("Dr. Jane Doe") and inverted ("Smith, John") forms are
+        // deliberate; only the bare full-name pattern is ambiguous
+        let mut tokens = e.value.split_whitespace();
+        let (Some(first), Some(last)) = (tokens.next(), tokens.last()) else {
+            return true;
+        };
+        if e.value.contains(',') || PERSON_TITLES.contains(&first.trim_end_matches('.')) {
+            return true;
+        }
+
+        if is_common_word(first) && is_common_word(last) {
+            return false;
+        }
+        if is_sentence_start(text, e.start) && !mid_sentence.contains(first) {
+            if strictness == PersonStrictness::Strict {
+                return false;
+            }
+            e.confidence *= 0.6;
+        }
+        true
+    });
+
+    entities
+}
+
+const PERSON_TITLES: &[&str] = &["Mr", "Mrs", "Ms", "Miss", "Dr", "Prof", "Rev", "Hon", "Sir", "Dame"];
+
+/// Capitalized words that are rarely a first or last name on their own
+const COMMON_CAPITALIZED_WORDS: &[&str] = &[
+    "united", "states", "kingdom", "nations", "new", "north", "south", "east", "west",
+    "board", "members", "member", "committee", "council", "department", "office",
+    "federal", "national", "general", "district", "county", "city", "state", "court",
+    "supreme", "grand", "jury", "chief", "executive", "officer", "vice", "senior",
+    "annual", "report", "first", "last", "all", "our", "dear", "best", "regards",
+    "please", "thank", "thanks", "see", "attached", "press", "release", "page",
+    "section", "exhibit", "table", "figure", "total", "net", "year", "end",
+];
+
+fn is_common_word(word: &str) -> bool {
+    let lower = word.trim_end_matches('.').to_lowercase();
+    COMMON_CAPITALIZED_WORDS.contains(&lower.as_str())
+}
+
+/// Whether `pos` begins a sentence: start of text, after terminal
+/// punctuation, or at the start of a line
+fn is_sentence_start(text: &str, pos: usize) -> bool {
+    let before = &text[..pos];
+    let trimmed = before.trim_end();
+    trimmed.is_empty()
+        || trimmed.ends_with(['.', '!', '?', ':', '"'])
+        || before[trimmed.len()..].contains('\n')
+}
+
+/// Capitalized words seen somewhere other than a sentence start
+fn capitalized_mid_sentence(text: &str) -> HashSet<&str> {
+    let mut words = HashSet::new();
+    let mut word_start = None;
+    for (i, c) in text.char_indices().chain(std::iter::once((text.len(), ' '))) {
+        match (word_start, c.is_alphabetic()) {
+            (None, true) => word_start = Some(i),
+            (Some(start), false) => {
+                let word = &text[start..i];
+                if word.starts_with(char::is_uppercase) && !is_sentence_start(text, start) {
+                    words.insert(word);
+                }
+                word_start = None;
+            }
+            _ => {}
+        }
+    }
+    words
+}
+
+fn extract_organizations(text: &str, ctx: &ExtractCtx) -> Vec<Entity> {
+    let mut entities = extract_with_patterns(text, &ORG_PATTERNS, EntityKind::Organization, EntityKind::Organization.default_confidence(), ctx);
+
+    // Bare acronyms need evidence: ALL CAPS text, Roman numerals and
+    // headers otherwise drown out the real organizations
+    let acronym_pattern = ORG_PATTERNS.last().unwrap();
+    if !ctx.opts.raw && entities.iter().any(|e| is_acronym(&e.value)) {
+        let options = &ctx.opts.acronyms;
+        let mut occurrences: HashMap<&str, usize> = HashMap::new();
+        for m in ctx.find_all(acronym_pattern, text) {
+            if ctx.skips_code(EntityKind::Organization, m.start(), m.end()) {
+                continue;
+            }
+            *occurrences.entry(m.as_str()).or_default() += 1;
+        }
+        entities.retain(|e| {
+            if !is_acronym(&e.value)
+                || KNOWN_ACRONYMS.contains(&e.value.as_str())
+                || options.known.iter().any(|k| k.eq_ignore_ascii_case(&e.value))
+            {
+                return true;
+            }
+            if is_acronym_stopword(&e.value, options) {
+                return false;
+            }
+            occurrences.get(e.value.as_str()).copied().unwrap_or(0) >= options.min_occurrences
+                || has_org_context(text, e.start, e.end, options.context_chars)
+        });
+    }
+
+    // Acronym hits inside a longer name ("ACME" in "ACME HOLDINGS INC")
+    if !ctx.opts.raw {
+        drop_contained_spans(&mut entities);
+    }
+
+    // Informal lowercase suffixes ("Acme inc") are a weaker signal
+    for entity in &mut entities {
+        let suffix = entity.value.rsplit(char::is_whitespace).next().unwrap_or("");
+        if entity.value.contains(char::is_whitespace) && suffix.starts_with(char::is_lowercase) {
+            entity.confidence *= 0.9;
+        }
+    }
+
+    // Canonical form for matching; ALL CAPS names also get a title-cased
+    // form for display
+    for entity in &mut entities {
+        let normalized = OrgNormalizer.normalize(entity);
+        let metadata = entity.metadata.get_or_insert_with(HashMap::new);
+        if let Some(normalized) = normalized {
+            metadata.insert("normalized".to_string(), normalized);
+        }
+        let is_all_caps = !entity.value.chars().any(|c| c.is_lowercase());
+        if is_all_caps && entity.value.contains(char::is_whitespace) {
+            metadata.insert("display".to_string(), title_case(&entity.value));
+        }
+    }
+
+    entities
+}
+
+/// Acronyms that are organizations wherever they appear
+const KNOWN_ACRONYMS: &[&str] = &[
+    "FBI", "CIA", "NSA", "SEC", "DOJ", "IRS", "EPA", "FDA", "FTC", "NYSE",
+    "DEA", "ATF", "DHS", "NASA", "NATO", "UN", "WHO", "IMF", "OECD", "FINRA",
+];
+
+/// ALL CAPS words that are never organizations on their own: English plus
+/// the common Spanish, French and German function words, and document
+/// furniture (headers, legal boilerplate, units)
+const ACRONYM_STOPWORDS: &[&str] = &[
+    // English
+    "A", "AN", "THE", "AND", "OR", "NOT", "NO", "YES", "OF", "TO", "IN", "ON", "AT", "BY",
+    "FOR", "WITH", "FROM", "INTO", "AS", "IS", "IT", "BE", "ARE", "WAS", "WERE", "HAS",
+    "HAVE", "HAD", "THIS", "THAT", "THESE", "THOSE", "ALL", "ANY", "BUT", "IF", "WE",
+    "YOU", "HE", "SHE", "THEY", "OUR", "YOUR", "HIS", "HER", "ITS", "MY", "ME", "US",
+    "SO", "DO", "UP", "OK", "AM", "PM", "ID", "TV", "FAQ", "ASAP", "FYI", "RE", "FW", "FWD",
+    "CC", "BCC", "PS", "CEO", "CFO", "COO", "CTO", "VP", "LLC", "LLP", "INC", "LTD", "CORP",
+    // Spanish
+    "EL", "LA", "LOS", "LAS", "DE", "DEL", "Y", "EN", "CON", "POR", "PARA", "QUE", "SE", "SU",
+    // French
+    "LE", "LES", "DES", "DU", "ET", "AU", "AUX", "UNE", "POUR", "SUR", "PAR", "EST",
+    // German
+    "DER", "DIE", "DAS", "UND", "MIT", "VON", "ZU", "DEN", "DEM", "EIN", "EINE", "IST",
+    // Headers and boilerplate
+    "NOTE", "NOTES", "PAGE", "DATE", "TIME", "SUBJECT", "PART", "TITLE",
+    "TABLE", "FIGURE", "INDEX", "TOTAL", "SUM", "NAME", "TYPE", "FILE", "COPY", "DRAFT",
+    "FINAL", "URGENT", "CONFIDENTIAL", "PRIVATE", "SECRET", "WHEREAS", "HEREBY",
+];
+
+/// Words near an acronym that suggest it names an organization
+const ORG_CONTEXT_WORDS: &[&str] = &[
+    "agency", "agencies", "department", "bureau", "office", "commission", "committee",
+    "board", "authority", "administration", "ministry", "council", "company", "companies",
+    "corporation", "firm", "bank", "fund", "group", "subsidiary", "inc", "corp", "llc", "ltd",
+    "agents", "officials", "spokesperson", "spokesman", "spokeswoman", "director", "chairman",
+    "filed", "investigation", "investigators", "subpoena", "regulators", "according",
+];
+
+/// A candidate from the bare acronym pattern
+fn is_acronym(value: &str) -> bool {
+    (2..=6).contains(&value.len()) && value.bytes().all(|b| b.is_ascii_uppercase())
+}
+
+fn is_roman_numeral(value: &str) -> bool {
+    value.bytes().all(|b| b"IVXLCDM".contains(&b))
+}
+
+fn is_acronym_stopword(value: &str, options: &AcronymOptions) -> bool {
+    ACRONYM_STOPWORDS.contains(&value)
+        || is_roman_numeral(value)
+        || options.stoplist.iter().any(|s| s.eq_ignore_ascii_case(value))
+}
+
+/// An org-context word within `chars` chars, or a parenthesized
+/// definition like "Securities and Exchange Commission (SEC)"
+fn has_org_context(text: &str, start: usize, end: usize, chars: usize) -> bool {
+    if text[..start].ends_with('(') && text[end..].starts_with(')') {
+        return true;
+    }
+    context_window(text, start, end, chars)
+        .split(|c: char| !c.is_alphabetic())
+        .any(|word| ORG_CONTEXT_WORDS.iter().any(|w| w.eq_ignore_ascii_case(word)))
+}
+
+/// Legal suffix spellings and their canonical forms, matched on the last
+/// word after punctuation is stripped ("L.L.C." is "LLC" by then)
+const ORG_SUFFIXES: &[(&str, &str)] = &[
+    ("INCORPORATED", "INC"),
+    ("CORPORATION", "CORP"),
+    ("COMPANY", "CO"),
+    ("LIMITED", "LTD"),
+];
+
+/// The org extractor's normalizer, `canonical_org`
+pub struct OrgNormalizer;
+
+impl Normalizer for OrgNormalizer {
+    fn normalize(&self, entity: &Entity) -> Option<String> {
+        Some(canonical_org(&entity.value))
+    }
+}
+
+/// Uppercased, punctuation-free name with a standard legal suffix:
+/// "ACME, Inc." and "Acme Incorporated" are both "ACME INC"
+fn canonical_org(value: &str) -> String {
+    let cleaned: String = value.chars()
+        .filter(|c| !matches!(c, '.' | '\''))
+        .map(|c| if c.is_alphanumeric() || c == '&' { c } else { ' ' })
+        .collect::<String>()
+        .to_uppercase();
+    let mut words: Vec<&str> = cleaned.split_whitespace().collect();
+    if let Some(last) = words.last_mut() {
+        if let Some((_, canonical)) = ORG_SUFFIXES.iter().find(|(spelling, _)| spelling == last) {
+            *last = canonical;
+        }
+    }
+    words.join(" ")
+}
+
+fn title_case(value: &str) -> String {
+    value.split_whitespace()
+        .map(|word| {
+            let mut chars = word.chars();
+            match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars.flat_map(char::to_lowercase)).collect(),
+                None => String::new(),
+            }
+        })
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+/// Drop entities whose span lies strictly inside another entity's span,
+/// keeping the survivors in their original order
+fn drop_contained_spans(entities: &mut Vec<Entity>) {
+    let mut order: Vec<usize> = (0..entities.len()).collect();
+    order.sort_by_key(|&i| (entities[i].start, std::cmp::Reverse(entities[i].end)));
+
+    let mut contained = vec![false; entities.len()];
+    let mut max_end = 0;
+    let mut prev: Option<(usize, usize)> = None;
+    for i in order {
+        let span = (entities[i].start, entities[i].end);
+        if prev.is_some_and(|p| p != span) && span.1 <= max_end {
+            contained[i] = true;
+        }
+        max_end = max_end.max(span.1);
+        prev = Some(span);
+    }
+
+    let mut flags = contained.into_iter();
+    entities.retain(|_| !flags.next().unwrap());
+}
+
+fn locale_amount_patterns([prefix, suffix]: [&'static str; 2], number: &str) -> Vec<NamedPattern> {
+    let currency = r"EUR|€|CHF|Fr\.|SFr\.|GBP|£|USD|\$";
+    vec![
+        named(prefix, &format!(r"(?:{currency})\s?\b(?:{number})\b")),
+        named(suffix, &format!(r"\b(?:{number})\s?(?:{currency})")),
+    ]
+}
+
+/// Numeric value of a locale-formatted amount ("1 234 567,89 EUR")
+fn locale_amount_value(value: &str, locale: NumberLocale) -> Option<f64> {
+    let digits: String = value.chars()
+        .skip_while(|c| !c.is_ascii_digit())
+        .take_while(|c| c.is_ascii_digit() || matches!(c, ' ' | '\u{a0}' | '\u{202f}' | '.' | ',' | '\'' | '’'))
+        .collect();
+    let digits = digits.trim_end();
+    let number: String = match locale {
+        NumberLocale::European => digits.chars()
+            .filter(|c| !matches!(c, ' ' | '\u{a0}' | '\u{202f}' | '.'))
+            .map(|c| if c == ',' { '.' } else { c })
+            .collect(),
+        NumberLocale::Swiss => digits.chars().filter(|c| !matches!(c, '\'' | '’')).collect(),
+        NumberLocale::English => digits.replace(',', ""),
+    };
+    number.parse().ok()
+}
+
+/// The amount extractor's normalizer: the numeric value, "$5 million" is
+/// 5000000, grouped and decimal-separated per `locale`. Amounts in a
+/// currency without a minor unit ("¥1,000") are rounded to whole numbers.
+pub struct AmountNormalizer {
+    pub locale: NumberLocale,
+}
+
+impl Normalizer for AmountNormalizer {
+    fn normalize(&self, entity: &Entity) -> Option<String> {
+        if let Some(value) = written_amount_value(&entity.value) {
+            return Some(value.to_string());
+        }
+        let value = match self.locale {
+            NumberLocale::English => english_amount_value(&entity.value)?,
+            locale => locale_amount_value(&entity.value, locale)?,
+        };
+        let whole = currency_code(&entity.value).is_some_and(|code| ZERO_DECIMAL_CURRENCIES.contains(&code));
+        Some(if whole { value.round() } else { value }.to_string())
+    }
+}
+
+/// Numeric value of a comma-grouped amount and its magnitude word,
+/// whatever the currency marker: "USD 1,234.56", "₹5,00,000", "$5M"
+fn english_amount_value(value: &str) -> Option<f64> {
+    let rest = &value[value.find(|c: char| c.is_ascii_digit())?..];
+    let end = rest.find(|c: char| !c.is_ascii_digit() && c != ',' && c != '.').unwrap_or(rest.len());
+    let number: f64 = rest[..end].replace(',', "").parse().ok()?;
+    let scale = match rest[end..].split_whitespace().next() {
+        Some("M") => 1e6,
+        Some("B") => 1e9,
+        Some("K") => 1e3,
+        Some(word) => match word.to_lowercase().as_str() {
+            "million" => 1e6,
+            "billion" => 1e9,
+            "thousand" => 1e3,
+            _ => 1.0,
+        },
+        None => 1.0,
+    };
+    Some(number * scale)
+}
+
+/// `metadata` of an amount: its `normalized` value and, when it names one,
+/// its ISO 4217 `currency`
+fn amount_metadata(entity: &Entity, normalizer: &AmountNormalizer) -> Option<HashMap<String, String>> {
+    let normalized = normalizer.normalize(entity)?;
+    let mut metadata = HashMap::from([("normalized".to_string(), normalized)]);
+    if let Some(code) = currency_code(&entity.value) {
+        metadata.insert("currency".to_string(), code.to_string());
+    }
+    Some(metadata)
+}
+
+/// Value of a number written in English words before its currency word:
+/// "one million two hundred thousand dollars" is 1200000. `None` unless
+/// the first word is a number.
+fn written_amount_value(value: &str) -> Option<f64> {
+    let lowercase = value.to_lowercase();
+    let mut words = lowercase.split(|c: char| c.is_whitespace() || c == '-').filter(|w| !w.is_empty()).peekable();
+    words.peek().and_then(|w| small_number_word(w))?;
+    let (mut total, mut group) = (0.0, 0.0_f64);
+    for word in words {
+        match word {
+            "and" => {}
+            "hundred" => group = group.max(1.0) * 100.0,
+            "thousand" | "million" | "billion" => {
+                let scale = match word {
+                    "thousand" => 1e3,
+                    "million" => 1e6,
+                    _ => 1e9,
+                };
+                total += group.max(1.0) * scale;
+                group = 0.0;
+            }
+            _ => match small_number_word(word) {
+                Some(n) => group += n,
+                // The currency word
+                None => break,
+            },
+        }
+    }
+    Some(total + group)
+}
+
+/// 1 to 90, the words a written number is built from below "hundred"
+fn small_number_word(word: &str) -> Option<f64> {
+    const WORDS: [&str; 19] = [
+        "one", "two", "three", "four", "five", "six", "seven", "eight", "nine", "ten",
+        "eleven", "twelve", "thirteen", "fourteen", "fifteen", "sixteen", "seventeen", "eighteen", "nineteen",
+    ];
+    const TENS: [&str; 8] = ["twenty", "thirty", "forty", "fifty", "sixty", "seventy", "eighty", "ninety"];
+    if let Some(i) = WORDS.iter().position(|&w| w == word) {
+        return Some(i as f64 + 1.0);
+    }
+    TENS.iter().position(|&w| w == word).map(|i| (i as f64 + 2.0) * 10.0)
+}
+
+fn extract_amounts(text: &str, ctx: &ExtractCtx) -> Vec<Entity> {
+    let mut entities = extract_with_patterns(text, &AMOUNT_PATTERNS, EntityKind::Amount, EntityKind::Amount.default_confidence(), ctx);
+
+    // Normalize amounts to numeric value
+    let english = AmountNormalizer { locale: NumberLocale::English };
+    for entity in &mut entities {
+        entity.metadata = amount_metadata(entity, &english);
+    }
+
+    if ctx.opts.parse_written_amounts {
+        let mut written = extract_with_patterns(text, std::slice::from_ref(&*WRITTEN_AMOUNT_PATTERN), EntityKind::Amount, 0.85, ctx);
+        for entity in &mut written {
+            entity.metadata = amount_metadata(entity, &english);
+        }
+        entities.extend(written);
+    }
+
+    let locale_patterns: &[NamedPattern] = match ctx.opts.number_locale {
+        NumberLocale::English => return entities,
+        NumberLocale::European => &EUROPEAN_AMOUNT_PATTERNS,
+        NumberLocale::Swiss => &SWISS_AMOUNT_PATTERNS,
+    };
+    let mut local = extract_with_patterns(text, locale_patterns, EntityKind::Amount, EntityKind::Amount.default_confidence(), ctx);
+    let normalizer = AmountNormalizer { locale: ctx.opts.number_locale };
+    local.retain_mut(|entity| {
+        entity.metadata = amount_metadata(entity, &normalizer);
+        entity.metadata.is_some()
+    });
+    local.sort_by(|a, b| a.order_key().cmp(&b.order_key()));
+    // Drops "89 EUR" out of "1 234 567,89 EUR"
+    entities.retain(|e| !local.iter().any(|l| l.start <= e.start && e.end <= l.end));
+    entities.extend(local);
+    entities
+}
+
+fn number_word(word: &str) -> Option<f64> {
+    let n = match word {
+        "one" => 1, "two" => 2, "three" => 3, "four" => 4, "five" => 5,
+        "six" => 6, "seven" => 7, "eight" => 8, "nine" => 9,
+        _ => return None,
+    };
+    Some(n as f64)
+}
+
+fn denominator_word(word: &str) -> Option<f64> {
+    let d = match word.trim_end_matches('s') {
+        "half" | "halve" => 2,
+        "third" => 3,
+        "quarter" | "fourth" => 4,
+        "fifth" => 5,
+        "sixth" => 6,
+        "eighth" => 8,
+        "tenth" => 10,
+        _ => return None,
+    };
+    Some(d as f64)
+}
+
+fn numeric_entity(kind: EntityKind, text: &str, m: regex::Match, confidence: f64, value: f64, ctx: &ExtractCtx) -> Entity {
+    Entity {
+        id: 0,
+        utf16_start: None,
+        utf16_end: None,
+        value: m.as_str().to_string(),
+        entity_type: kind.as_str().to_string(),
+        start: m.start(),
+        end: m.end(),
+        confidence,
+        context: ctx.context(kind, text, m.start(), m.end()),
+        metadata: Some(HashMap::from([("normalized".to_string(), value.to_string())])),
+    }
+}
+
+/// Percentages and written fractions, normalized to a decimal fraction
+/// (12.5% and "one-eighth" are both 0.125)
+fn extract_percentages(text: &str, ctx: &ExtractCtx) -> Vec<Entity> {
+    let mut entities: Vec<Entity> = ctx.captures_all(&PERCENT_PATTERN, text).iter()
+        .filter_map(|cap| {
+            let percent: f64 = cap[1].parse().ok()?;
+            Some(numeric_entity(EntityKind::Percent, text, cap.get(0).unwrap(), 0.90, percent / 100.0, ctx))
+        })
+        .collect();
+
+    entities.extend(ctx.captures_all(&FRACTION_PATTERN, text).iter()
+        .filter_map(|cap| {
+            let numerator = number_word(&cap[1].to_lowercase())?;
+            let denominator = denominator_word(&cap[2].to_lowercase())?;
+            Some(numeric_entity(EntityKind::Percent, text, cap.get(0).unwrap(), 0.80, numerator / denominator, ctx))
+        }));
+
+    entities.sort_by(|a, b| a.order_key().cmp(&b.order_key()));
+    entities
+}
+
+/// `N:M` ratios, normalized to N / M. Times ("10:30") are skipped unless
+/// the word "ratio" is right there.
+fn extract_ratios(text: &str, ctx: &ExtractCtx) -> Vec<Entity> {
+    ctx.captures_all(&RATIO_PATTERN, text).iter()
+        .filter_map(|cap| {
+            let m = cap.get(0).unwrap();
+            // Part of a longer run like 10:30:45 or 1:2:3
+            if text[m.end()..].starts_with(':') || text[..m.start()].ends_with(':') {
+                return None;
+            }
+            let denominator: f64 = cap[2].parse().ok()?;
+            if denominator == 0.0 {
+                return None;
+            }
+            // The cue is a word or two away, so only that much is lowercased
+            let before = text[..m.start()].trim_end();
+            let before = before[before.floor_char_boundary(before.len().saturating_sub(16))..].to_lowercase();
+            let after = text[m.end()..].trim_start();
+            let after = after[..after.ceil_char_boundary(after.len().min(16))].to_lowercase();
+            let has_cue = before.ends_with("ratio") || before.ends_with("ratio of") || after.starts_with("ratio");
+            let looks_like_time = cap[2].len() == 2 && !cap[1].contains('.') && !cap[2].contains('.');
+            if looks_like_time && !has_cue {
+                return None;
+            }
+            let value = cap[1].parse::<f64>().ok()? / denominator;
+            Some(numeric_entity(EntityKind::Ratio, text, m, if has_cue { 0.85 } else { 0.60 }, value, ctx))
+        })
+        .collect()
+}
+
+/// A number followed by a unit from `ExtractOptions.quantity_units`,
+/// normalized to its SI unit where the table gives one
+fn extract_quantities(text: &str, ctx: &ExtractCtx) -> Vec<Entity> {
+    // Longest spelling first, so "fl oz" wins over "oz" and "sq ft" over "ft"
+    let mut names: Vec<(&str, &QuantityUnit)> = ctx.options().quantity_units.iter()
+        .flat_map(|unit| unit.names.iter().map(move |name| (name.as_str(), unit)))
+        .collect();
+    names.sort_by_key(|(name, _)| std::cmp::Reverse(name.len()));
+
+    ctx.captures_all(&QUANTITY_NUMBER_PATTERN, text).iter()
+        .filter_map(|cap| {
+            let number = cap.get(0).unwrap();
+            // Part of a decimal or a longer token ("1.2.3", "v2")
+            if text[..number.start()].ends_with(['.', ',']) {
+                return None;
+            }
+            let rest = &text[number.end()..];
+            let gap = match rest.chars().next() {
+                Some(c @ (' ' | '\u{a0}' | '-')) => c.len_utf8(),
+                _ => 0,
+            };
+            let rest = &rest[gap..];
+            let (name, unit) = names.iter().find(|(name, _)| {
+                rest.get(..name.len()).is_some_and(|head| head.eq_ignore_ascii_case(name))
+                    && !rest[name.len()..].starts_with(|c: char| c.is_alphanumeric())
+            })?;
+
+            let value: f64 = format!("{}{}", cap[1].replace(',', ""), cap.get(2).map_or("", |m| m.as_str())).parse().ok()?;
+            let end = number.end() + gap + name.len();
+            let mut metadata = HashMap::from([
+                ("quantity".to_string(), value.to_string()),
+                ("unit".to_string(), unit.names[0].clone()),
+            ]);
+            if let Some(si_unit) = &unit.si_unit {
+                metadata.insert("normalized".to_string(), (value * unit.factor).to_string());
+                metadata.insert("si_unit".to_string(), si_unit.clone());
+            }
+
+            Some(Entity {
+                id: 0,
+                utf16_start: None,
+                utf16_end: None,
+                value: text[number.start()..end].to_string(),
+                entity_type: EntityKind::Quantity.as_str().to_string(),
+                start: number.start(),
+                end,
+                confidence: 0.85,
+                context: ctx.context(EntityKind::Quantity, text, number.start(), end),
+                metadata: Some(metadata),
+            })
+        })
+        .collect()
+}
+
+/// EIN prefixes the IRS has assigned to its campuses and online
+/// application; 00, 07-09, 17-19, 28, 29, 49, 69, 70, 78, 79, 89, 96 and
+/// 97 are not in use
+const EIN_PREFIXES: &[&str] = &[
+    "01", "02", "03", "04", "05", "06", "10", "11", "12", "13", "14", "15", "16",
+    "20", "21", "22", "23", "24", "25", "26", "27", "30", "31", "32", "33", "34",
+    "35", "36", "37", "38", "39", "40", "41", "42", "43", "44", "45", "46", "47",
+    "48", "50", "51", "52", "53", "54", "55", "56", "57", "58", "59", "60", "61",
+    "62", "63", "64", "65", "66", "67", "68", "71", "72", "73", "74", "75", "76",
+    "77", "80", "81", "82", "83", "84", "85", "86", "87", "88", "90", "91", "92",
+    "93", "94", "95", "98", "99",
+];
+
+/// Chars each side of an EIN searched for "EIN", "Tax ID" and the like
+const EIN_CUE_CHARS: usize = 40;
+
+/// Plate-like tokens after a cue word: 3 to 8 letters and digits, at least
+/// one of each. Bare tokens aren't matched; without the cue they look like
+/// any part number. `metadata["jurisdiction"]` is the state code the
+/// context names, if any, and raises confidence.
+fn extract_license_plates(text: &str, ctx: &ExtractCtx) -> Vec<Entity> {
+    ctx.captures_all(&LICENSE_PLATE_PATTERN, text).iter()
+        .filter_map(|cap| {
+            let plate = cap.get(1).unwrap();
+            let normalized: String = plate.as_str().chars().filter(char::is_ascii_alphanumeric).collect();
+            let letters = normalized.chars().filter(char::is_ascii_alphabetic).count();
+            if !(3..=8).contains(&normalized.len()) || letters == 0 || letters == normalized.len() {
+                return None;
+            }
+            let cue = cap.get(0).unwrap();
+            let mut metadata = HashMap::from([("normalized".to_string(), normalized)]);
+            let jurisdiction = plate_jurisdiction(&text[..cue.start()], &text[plate.end()..]);
+            if let Some(code) = jurisdiction {
+                metadata.insert("jurisdiction".to_string(), code.to_string());
+            }
+            Some(Entity {
+                id: 0,
+                utf16_start: None,
+                utf16_end: None,
+                value: plate.as_str().to_string(),
+                entity_type: EntityKind::LicensePlate.as_str().to_string(),
+                start: plate.start(),
+                end: plate.end(),
+                confidence: if jurisdiction.is_some() { 0.85 } else { 0.75 },
+                context: ctx.context(EntityKind::LicensePlate, text, plate.start(), plate.end()),
+                metadata: Some(metadata),
+            })
+        })
+        .collect()
+}
+
+/// `NN-NNNNNNN` with an assigned prefix and a tax-ID cue nearby. Bare
+/// numbers of that shape are mostly case or invoice numbers, so they are
+/// dropped. Runs of dashed digit groups (SSN-like 123-45-6789, or
+/// 12-3456789-0) are not EINs either.
+fn extract_eins(text: &str, ctx: &ExtractCtx) -> Vec<Entity> {
+    ctx.captures_all(&EIN_PATTERN, text).iter()
+        .filter_map(|cap| {
+            let m = cap.get(0).unwrap();
+            let before = text[..m.start()].strip_suffix('-');
+            let after = text[m.end()..].strip_prefix('-');
+            if before.is_some_and(|s| s.ends_with(|c: char| c.is_ascii_digit()))
+                || after.is_some_and(|s| s.starts_with(|c: char| c.is_ascii_digit()))
+            {
+                return None;
+            }
+            let prefix = &cap[1];
+            if !EIN_PREFIXES.contains(&prefix)
+                || !EIN_CUE_PATTERN.is_match(&context_window(text, m.start(), m.end(), EIN_CUE_CHARS))
+            {
+                return None;
+            }
+            Some(Entity {
+                id: 0,
+                utf16_start: None,
+                utf16_end: None,
+                value: m.as_str().to_string(),
+                entity_type: EntityKind::Ein.as_str().to_string(),
+                start: m.start(),
+                end: m.end(),
+                confidence: 0.90,
+                context: ctx.context(EntityKind::Ein, text, m.start(), m.end()),
+                metadata: Some(HashMap::from([("prefix".to_string(), prefix.to_string())])),
+            })
+        })
+        .collect()
+}
+
+/// Federal Reserve Banks by district, 1 to 12
+const RESERVE_BANKS: [&str; 12] = [
+    "Boston", "New York", "Philadelphia", "Cleveland", "Richmond", "Atlanta",
+    "Chicago", "St. Louis", "Minneapolis", "Kansas City", "Dallas", "San Francisco",
+];
+
+/// Chars each side of a routing number searched for "routing", "ABA", "RTN"
+const ROUTING_CUE_CHARS: usize = 40;
+
+/// The Federal Reserve district of a routing number's first two digits:
+/// 01-12 for banks, 21-32 for thrifts and 61-72 for electronic transfers
+/// in the same twelve districts; 00 (government) and 80 (traveler's
+/// checks) have none. Other prefixes are unassigned.
+fn routing_district(prefix: u32) -> Option<Option<u32>> {
+    match prefix {
+        0 | 80 => Some(None),
+        1..=12 => Some(Some(prefix)),
+        21..=32 => Some(Some(prefix - 20)),
+        61..=72 => Some(Some(prefix - 60)),
+        _ => None,
+    }
+}
+
+/// Nine-digit runs with an assigned prefix that pass the ABA check,
+/// 3(d1+d4+d7) + 7(d2+d5+d8) + (d3+d6+d9) = 0 mod 10. One number in about
+/// 26 passes by chance, so a "routing", "ABA" or "RTN" nearby raises the
+/// confidence. EINs (12-3456789) and SSNs (123-45-6789) are dashed, and a
+/// run inside dashed digit groups is skipped, so no digits are both.
+fn extract_routing_numbers(text: &str, ctx: &ExtractCtx) -> Vec<Entity> {
+    ctx.find_all(&ROUTING_NUMBER_PATTERN, text).into_iter()
+        .filter_map(|m| {
+            let before = text[..m.start()].strip_suffix('-');
+            let after = text[m.end()..].strip_prefix('-');
+            if before.is_some_and(|s| s.ends_with(|c: char| c.is_ascii_digit()))
+                || after.is_some_and(|s| s.starts_with(|c: char| c.is_ascii_digit()))
+            {
+                return None;
+            }
+            let d: Vec<u32> = m.as_str().bytes().map(|b| u32::from(b - b'0')).collect();
+            let weighted = 3 * (d[0] + d[3] + d[6]) + 7 * (d[1] + d[4] + d[7]) + d[2] + d[5] + d[8];
+            let district = routing_district(d[0] * 10 + d[1])?;
+            if !weighted.is_multiple_of(10) {
+                return None;
+            }
+            let cued = ROUTING_CUE_PATTERN.is_match(&context_window(text, m.start(), m.end(), ROUTING_CUE_CHARS));
+            let mut metadata = HashMap::from([("prefix".to_string(), m.as_str()[..2].to_string())]);
+            if let Some(district) = district {
+                metadata.insert("district".to_string(), format!("{district:02}"));
+                metadata.insert("reserve_bank".to_string(), RESERVE_BANKS[district as usize - 1].to_string());
+            }
+            Some(Entity {
+                id: 0,
+                utf16_start: None,
+                utf16_end: None,
+                value: m.as_str().to_string(),
+                entity_type: EntityKind::RoutingNumber.as_str().to_string(),
+                start: m.start(),
+                end: m.end(),
+                confidence: if cued { 0.95 } else { 0.70 },
+                context: ctx.context(EntityKind::RoutingNumber, text, m.start(), m.end()),
+                metadata: Some(metadata),
+            })
+        })
+        .collect()
+}
+
+/// Whether `digits` (no separators, "X" as 10) pass the ISBN-10 mod-11
+/// or ISBN-13 mod-10 check
+fn isbn_checksum_ok(digits: &[u32]) -> bool {
+    match digits.len() {
+        10 => digits.iter().enumerate().map(|(i, d)| (10 - i as u32) * d).sum::<u32>() % 11 == 0,
+        13 => digits.iter().enumerate().map(|(i, d)| if i % 2 == 0 { *d } else { 3 * d }).sum::<u32>() % 10 == 0,
+        _ => false,
+    }
+}
+
+/// ISBNs with a valid check digit. An ISBN-13 stands on its own; a bare
+/// ten-digit number passes the mod-11 check one time in eleven, so an
+/// ISBN-10 needs an "ISBN" label right before it.
+fn extract_isbns(text: &str, ctx: &ExtractCtx) -> Vec<Entity> {
+    ctx.find_all(&ISBN_PATTERN, text).into_iter()
+        .filter_map(|m| {
+            let normalized: String = m.as_str().chars().filter(|c| c.is_ascii_alphanumeric()).collect();
+            // The pattern only allows "X" as an ISBN-10 check digit
+            let digits: Vec<u32> = normalized.chars().map(|c| c.to_digit(10).unwrap_or(10)).collect();
+            if !isbn_checksum_ok(&digits) {
+                return None;
+            }
+            let from = text[..m.start()].char_indices().rev().nth(11).map_or(0, |(i, _)| i);
+            let cued = ISBN_CUE_PATTERN.is_match(&text[from..m.start()]);
+            let confidence = match (cued, digits.len()) {
+                (true, _) => 0.95,
+                (false, 13) => 0.85,
+                (false, _) => return None,
+            };
+            Some(Entity {
+                id: 0,
+                utf16_start: None,
+                utf16_end: None,
+                value: m.as_str().to_string(),
+                entity_type: EntityKind::Isbn.as_str().to_string(),
+                start: m.start(),
+                end: m.end(),
+                confidence,
+                context: ctx.context(EntityKind::Isbn, text, m.start(), m.end()),
+                metadata: Some(HashMap::from([
+                    ("normalized".to_string(), normalized.clone()),
+                    ("format".to_string(), format!("isbn{}", normalized.len())),
+                ])),
+            })
+        })
+        .collect()
+}
+
+/// DOIs, less trailing sentence punctuation and unbalanced closing
+/// parentheses; `metadata["normalized"]` is lowercased, as DOIs are
+/// case-insensitive
+fn extract_dois(text: &str, ctx: &ExtractCtx) -> Vec<Entity> {
+    ctx.find_all(&DOI_PATTERN, text).into_iter()
+        .map(|m| {
+            let mut value = m.as_str();
+            loop {
+                let trimmed = value.trim_end_matches(['.', ',', ';', ':', '\'', ']']);
+                let trimmed = match trimmed.strip_suffix(')') {
+                    Some(rest) if trimmed.matches('(').count() < trimmed.matches(')').count() => rest,
+                    _ => trimmed,
+                };
+                if trimmed.len() == value.len() {
+                    break;
+                }
+                value = trimmed;
+            }
+            let (start, end) = (m.start(), m.start() + value.len());
+            let prefix = &value[..value.find('/').unwrap()];
+            Entity {
+                id: 0,
+                utf16_start: None,
+                utf16_end: None,
+                value: value.to_string(),
+                entity_type: EntityKind::Doi.as_str().to_string(),
+                start,
+                end,
+                confidence: 0.95,
+                context: ctx.context(EntityKind::Doi, text, start, end),
+                metadata: Some(HashMap::from([
+                    ("normalized".to_string(), value.to_lowercase()),
+                    ("prefix".to_string(), prefix.to_string()),
+                ])),
+            }
+        })
+        .filter(|e| !e.value.ends_with('/'))
+        .collect()
+}
+
+/// ICD-10 chapters by first and last three-character category
+const ICD10_CHAPTERS: &[(&str, &str, &str)] = &[
+    ("A00", "B99", "Certain infectious and parasitic diseases"),
+    ("C00", "D49", "Neoplasms"),
+    ("D50", "D89", "Diseases of the blood and immune mechanism"),
+    ("E00", "E89", "Endocrine, nutritional and metabolic diseases"),
+    ("F01", "F99", "Mental and behavioural disorders"),
+    ("G00", "G99", "Diseases of the nervous system"),
+    ("H00", "H59", "Diseases of the eye and adnexa"),
+    ("H60", "H95", "Diseases of the ear and mastoid process"),
+    ("I00", "I99", "Diseases of the circulatory system"),
+    ("J00", "J99", "Diseases of the respiratory system"),
+    ("K00", "K95", "Diseases of the digestive system"),
+    ("L00", "L99", "Diseases of the skin and subcutaneous tissue"),
+    ("M00", "M99", "Diseases of the musculoskeletal system and connective tissue"),
+    ("N00", "N99", "Diseases of the genitourinary system"),
+    ("O00", "O99", "Pregnancy, childbirth and the puerperium"),
+    ("P00", "P96", "Certain conditions originating in the perinatal period"),
+    ("Q00", "Q99", "Congenital malformations and chromosomal abnormalities"),
+    ("R00", "R99", "Symptoms, signs and abnormal findings"),
+    ("S00", "T88", "Injury, poisoning and other consequences of external causes"),
+    ("U00", "U85", "Codes for special purposes"),
+    ("V00", "Y99", "External causes of morbidity"),
+    ("Z00", "Z99", "Factors influencing health status and contact with health services"),
+];
+
+/// Chars either side of a code searched for `ICD10_CUE_PATTERN`
+const ICD10_CUE_CHARS: usize = 40;
+
+/// ICD-10 codes whose category falls inside a chapter, with
+/// `metadata["category"]` ("E11") and `metadata["chapter"]`. A bare
+/// category reads like a model or grid reference ("B52", "F16"), so it
+/// needs a diagnosis cue nearby; a dotted subcode doesn't.
+fn extract_icd_codes(text: &str, ctx: &ExtractCtx) -> Vec<Entity> {
+    ctx.captures_all(&ICD10_PATTERN, text).iter()
+        .filter_map(|cap| {
+            let m = cap.get(0).unwrap();
+            let category = &cap[1];
+            let (_, _, chapter) = ICD10_CHAPTERS.iter()
+                .find(|(first, last, _)| (*first..=*last).contains(&category))?;
+            let has_cue = ICD10_CUE_PATTERN.is_match(&context_window(text, m.start(), m.end(), ICD10_CUE_CHARS));
+            let confidence = match (m.len() > category.len(), has_cue) {
+                (true, true) => 0.90,
+                (true, false) => 0.80,
+                (false, true) => 0.75,
+                (false, false) => return None,
+            };
+            Some(Entity {
+                id: 0,
+                utf16_start: None,
+                utf16_end: None,
+                value: m.as_str().to_string(),
+                entity_type: EntityKind::IcdCode.as_str().to_string(),
+                start: m.start(),
+                end: m.end(),
+                confidence,
+                context: ctx.context(EntityKind::IcdCode, text, m.start(), m.end()),
+                metadata: Some(HashMap::from([
+                    ("category".to_string(), category.to_string()),
+                    ("chapter".to_string(), chapter.to_string()),
+                ])),
+            })
+        })
+        .collect()
+}
+
+fn drug_automaton(drugs: &[DrugName]) -> Option<aho_corasick::AhoCorasick> {
+    aho_corasick::AhoCorasick::builder()
+        .ascii_case_insensitive(true)
+        .match_kind(aho_corasick::MatchKind::LeftmostLongest)
+        .build(drugs.iter().flat_map(|drug| &drug.names))
+        .ok()
+}
+
+lazy_static! {
+    // Building the automaton costs far more than scanning a document with
+    // it, so the default gazetteer's is built once
+    static ref DEFAULT_DRUGS: (Vec<DrugName>, Option<aho_corasick::AhoCorasick>) = {
+        let drugs = DrugName::defaults();
+        let automaton = drug_automaton(&drugs);
+        (drugs, automaton)
+    };
+}
+
+/// Names from `ExtractOptions.drugs`, longest first where they overlap,
+/// with the generic name and class in `metadata`
+///
+/// The default list's automaton is shared; any other list gets one built
+/// per call. On 2,000 prose documents (release build, one core) sharing it
+/// took drug extraction from 1.6 s to 40 ms, and `extract_all` from 1.55 s
+/// to 133 ms.
+fn extract_drugs(text: &str, ctx: &ExtractCtx) -> Vec<Entity> {
+    let drugs = &ctx.options().drugs;
+    let names: Vec<(&str, &DrugName)> = drugs.iter()
+        .flat_map(|drug| drug.names.iter().map(move |name| (name.as_str(), drug)))
+        .collect();
+    let built;
+    let automaton = if *drugs == DEFAULT_DRUGS.0 {
+        DEFAULT_DRUGS.1.as_ref()
+    } else {
+        built = drug_automaton(drugs);
+        built.as_ref()
+    };
+    let Some(automaton) = automaton else {
+        return Vec::new();
+    };
+
+    automaton.find_iter(text)
+        .filter(|m| {
+            !text[..m.start()].ends_with(|c: char| c.is_alphanumeric())
+                && !text[m.end()..].starts_with(|c: char| c.is_alphanumeric())
+        })
+        .map(|m| {
+            let drug = names[m.pattern().as_usize()].1;
+            let mut metadata = HashMap::from([("generic".to_string(), drug.generic.clone())]);
+            if let Some(class) = &drug.class {
+                metadata.insert("class".to_string(), class.clone());
+            }
+            Entity {
+                id: 0,
+                utf16_start: None,
+                utf16_end: None,
+                value: text[m.start()..m.end()].to_string(),
+                entity_type: EntityKind::Drug.as_str().to_string(),
+                start: m.start(),
+                end: m.end(),
+                confidence: 0.85,
+                context: ctx.context(EntityKind::Drug, text, m.start(), m.end()),
+                metadata: Some(metadata),
+            }
+        })
+        .collect()
+}
+
+/// Signed decimal degrees from a matched number and optional hemisphere
+/// letter; a letter and a sign together are contradictory
+fn signed_degrees(number: &str, hemisphere: Option<&str>) -> Option<f64> {
+    let value: f64 = number.parse().ok()?;
+    match hemisphere {
+        Some(_) if number.starts_with(['-', '+']) => None,
+        Some("S" | "W") => Some(-value),
+        _ => Some(value),
+    }
+}
+
+/// Degrees, minutes and optional seconds as decimal degrees; `None` if
+/// minutes or seconds run past 60
+fn dms_degrees(degrees: &str, minutes: &str, seconds: Option<&str>, hemisphere: &str) -> Option<f64> {
+    let minutes: f64 = minutes.parse().ok()?;
+    let seconds: f64 = seconds.map_or(Ok(0.0), str::parse).ok()?;
+    if minutes >= 60.0 || seconds >= 60.0 {
+        return None;
+    }
+    let value = degrees.parse::<f64>().ok()? + minutes / 60.0 + seconds / 3600.0;
+    Some(if matches!(hemisphere, "S" | "W") { -value } else { value })
+}
+
+fn coordinate_entity(text: &str, m: regex::Match, lat: f64, lon: f64, confidence: f64, ctx: &ExtractCtx) -> Option<Entity> {
+    if !(-90.0..=90.0).contains(&lat) || !(-180.0..=180.0).contains(&lon) {
+        return None;
+    }
+    Some(Entity {
+        id: 0,
+        utf16_start: None,
+        utf16_end: None,
+        value: m.as_str().to_string(),
+        entity_type: EntityKind::Coordinate.as_str().to_string(),
+        start: m.start(),
+        end: m.end(),
+        confidence,
+        context: ctx.context(EntityKind::Coordinate, text, m.start(), m.end()),
+        metadata: Some(HashMap::from([
+            ("latitude".to_string(), format!("{:.6}", lat)),
+            ("longitude".to_string(), format!("{:.6}", lon)),
+        ])),
+    })
+}
+
+/// Latitude/longitude pairs, as decimal degrees or DMS, with the
+/// normalized decimal values in `metadata`. Decimals need three or more
+/// places on both halves and must be in range; lone numbers never match.
+fn extract_coordinates(text: &str, ctx: &ExtractCtx) -> Vec<Entity> {
+    let mut entities: Vec<Entity> = ctx.captures_all(&DMS_COORDINATE_PATTERN, text).iter()
+        .filter_map(|cap| {
+            let lat = dms_degrees(&cap[1], &cap[2], cap.get(3).map(|m| m.as_str()), &cap[4])?;
+            let lon = dms_degrees(&cap[5], &cap[6], cap.get(7).map(|m| m.as_str()), &cap[8])?;
+            coordinate_entity(text, cap.get(0).unwrap(), lat, lon, 0.90, ctx)
+        })
+        .collect();
+
+    for cap in ctx.captures_all(&DECIMAL_COORDINATE_PATTERN, text) {
+        let m = cap.get(0).unwrap();
+        // Part of a longer number, version or list ("1.2.345, 6.789")
+        if text[..m.start()].ends_with(|c: char| c.is_alphanumeric() || c == '.')
+            || text[m.end()..].starts_with(|c: char| c.is_ascii_digit() || c == '.')
+            || entities.iter().any(|e| e.start < m.end() && m.start() < e.end)
+        {
+            continue;
+        }
+        let (lat_hemisphere, lon_hemisphere) = (cap.get(2).map(|h| h.as_str()), cap.get(4).map(|h| h.as_str()));
+        let (Some(lat), Some(lon)) = (signed_degrees(&cap[1], lat_hemisphere), signed_degrees(&cap[3], lon_hemisphere)) else {
+            continue;
+        };
+        let confidence = if lat_hemisphere.is_some() && lon_hemisphere.is_some() { 0.90 } else { 0.80 };
+        entities.extend(coordinate_entity(text, m, lat, lon, confidence, ctx));
+    }
+
+    entities.sort_by(|a, b| a.order_key().cmp(&b.order_key()));
+    entities
+}
+
+const US_STATE_CODES: &[&str] = &[
+    "AL", "AK", "AZ", "AR", "CA", "CO", "CT", "DE", "FL", "GA", "HI", "ID", "IL", "IN", "IA",
+    "KS", "KY", "LA", "ME", "MD", "MA", "MI", "MN", "MS", "MO", "MT", "NE", "NV", "NH", "NJ",
+    "NM", "NY", "NC", "ND", "OH", "OK", "OR", "PA", "RI", "SC", "SD", "TN", "TX", "UT", "VT",
+    "VA", "WA", "WV", "WI", "WY", "DC", "PR", "VI", "GU",
+];
+
+const US_STATE_NAMES: &[&str] = &[
+    "Alabama", "Alaska", "Arizona", "Arkansas", "California", "Colorado", "Connecticut",
+    "Delaware", "Florida", "Georgia", "Hawaii", "Idaho", "Illinois", "Indiana", "Iowa",
+    "Kansas", "Kentucky", "Louisiana", "Maine", "Maryland", "Massachusetts", "Michigan",
+    "Minnesota", "Mississippi", "Missouri", "Montana", "Nebraska", "Nevada", "New Hampshire",
+    "New Jersey", "New Mexico", "New York", "North Carolina", "North Dakota", "Ohio",
+    "Oklahoma", "Oregon", "Pennsylvania", "Rhode Island", "South Carolina", "South Dakota",
+    "Tennessee", "Texas", "Utah", "Vermont", "Virginia", "Washington", "West Virginia",
+    "Wisconsin", "Wyoming",
+];
+
+const COUNTRY_NAMES: &[&str] = &[
+    "Afghanistan", "Argentina", "Australia", "Austria", "Bahamas", "Belgium", "Bermuda",
+    "Brazil", "Canada", "Cayman Islands", "Chile", "China", "Colombia", "Cuba", "Cyprus",
+    "Czech Republic", "Denmark", "Egypt", "England", "Finland", "France", "Germany", "Greece",
+    "Hungary", "Iceland", "India", "Indonesia", "Iran", "Iraq", "Ireland", "Israel", "Italy",
+    "Jamaica", "Japan", "Jordan", "Kenya", "Lebanon", "Luxembourg", "Malaysia", "Mexico",
+    "Monaco", "Morocco", "Netherlands", "New Zealand", "Nigeria", "Norway", "Pakistan",
+    "Panama", "Peru", "Philippines", "Poland", "Portugal", "Qatar", "Romania", "Russia",
+    "Saudi Arabia", "Scotland", "Singapore", "South Africa", "South Korea", "Spain", "Sweden",
+    "Switzerland", "Syria", "Taiwan", "Thailand", "Turkey", "Ukraine", "United Arab Emirates",
+    "United Kingdom", "United States", "Uruguay", "Venezuela", "Vietnam", "Wales",
+];
+
+/// Known-place values that are cities; the rest of that list are states,
+/// or places (islands, neighbourhoods) with no finer type
+const KNOWN_CITIES: &[&str] = &[
+    "New York", "Los Angeles", "London", "Paris", "Tokyo", "Hong Kong", "Dubai", "Miami",
+    "Washington", "Palm Beach",
+];
+
+/// The state code a plate's context names: "NY plate", "Florida tag",
+/// "plate ABC-1234 (NY)"
+fn plate_jurisdiction(before: &str, after: &str) -> Option<&'static str> {
+    let before = before.trim_end();
+    // US_STATE_NAMES is in US_STATE_CODES order
+    let named = US_STATE_NAMES.iter().zip(US_STATE_CODES).find(|(name, _)| {
+        before.strip_suffix(**name).is_some_and(|rest| !rest.ends_with(char::is_alphanumeric))
+    });
+    if let Some((_, code)) = named {
+        return Some(code);
+    }
+    let word = before.rsplit(|c: char| !c.is_ascii_alphabetic()).next().unwrap_or("");
+    let bracketed = after.strip_prefix(" (").and_then(|s| s.get(..3)).and_then(|s| s.strip_suffix(')'));
+    US_STATE_CODES.iter().copied().find(|&code| code == word || Some(code) == bracketed)
+}
+
+fn is_us_state(name: &str) -> bool {
+    US_STATE_CODES.contains(&name) || US_STATE_NAMES.contains(&name)
+}
+
+/// `metadata["location_type"]` plus components, from the entity's shape:
+/// "City, ST" pairs, street addresses, or a reference-set lookup
+fn classify_location(entity: &mut Entity) {
+    let mut metadata = HashMap::new();
+    let value = entity.value.as_str();
+
+    let location_type = if LOCATION_PATTERNS[2].find(value).is_some_and(|m| m.len() == value.len()) {
+        "street_address"
+    } else if let Some((first, second)) = value.split_once(',') {
+        let (first, second) = (first.trim(), second.trim());
+        if is_us_state(second) {
+            metadata.insert("city".to_string(), first.to_string());
+            metadata.insert("state".to_string(), second.to_string());
+            "city"
+        } else if COUNTRY_NAMES.contains(&second) {
+            metadata.insert("city".to_string(), first.to_string());
+            metadata.insert("country".to_string(), second.to_string());
+            "city"
+        } else {
+            "place"
+        }
+    } else if KNOWN_CITIES.contains(&value) {
+        "city"
+    } else if is_us_state(value) {
+        "state"
+    } else if COUNTRY_NAMES.contains(&value) {
+        "country"
+    } else {
+        "place"
+    };
+
+    metadata.insert("location_type".to_string(), location_type.to_string());
+    entity.metadata.get_or_insert_with(HashMap::new).extend(metadata);
+}
+
+fn redaction_entity(text: &str, m: regex::Match, style: &str, confidence: f64, ctx: &ExtractCtx) -> Entity {
+    Entity {
+        id: 0,
+        utf16_start: None,
+        utf16_end: None,
+        value: m.as_str().to_string(),
+        entity_type: EntityKind::Redaction.as_str().to_string(),
+        start: m.start(),
+        end: m.end(),
+        confidence,
+        context: ctx.context(EntityKind::Redaction, text, m.start(), m.end()),
+        metadata: Some(HashMap::from([
+            ("style".to_string(), style.to_string()),
+            ("length".to_string(), m.as_str().chars().count().to_string()),
+        ])),
+    }
+}
+
+/// Placeholders left by an earlier redaction pass, so callers can see how
+/// much of a document is blacked out. Every occurrence is kept.
+fn extract_redactions(text: &str, ctx: &ExtractCtx) -> Vec<Entity> {
+    let mut entities: Vec<Entity> = ctx.find_all(&BRACKETED_REDACTION_PATTERN, text).into_iter()
+        .map(|m| redaction_entity(text, m, "bracketed", 0.95, ctx))
+        .collect();
+    entities.extend(ctx.find_all(&BLOCK_REDACTION_PATTERN, text).into_iter()
+        .map(|m| redaction_entity(text, m, "block", 0.90, ctx)));
+    entities.extend(ctx.find_all(&X_REDACTION_PATTERN, text).into_iter()
+        .filter(|m| m.len() >= ctx.opts.redaction_min_x)
+        .map(|m| redaction_entity(text, m, "x_run", 0.70, ctx)));
+    entities.sort_by(|a, b| a.order_key().cmp(&b.order_key()));
+    entities
+}
+
+fn extract_locations(text: &str, ctx: &ExtractCtx) -> Vec<Entity> {
+    let mut entities = extract_with_patterns(text, &LOCATION_PATTERNS, EntityKind::Location, EntityKind::Location.default_confidence(), ctx);
+    entities.iter_mut().for_each(classify_location);
+    entities
+}
+
+fn extract_emails(text: &str, ctx: &ExtractCtx) -> Vec<Entity> {
+    let strict = ctx.opts.strict_email;
+    let mut emails: Vec<Entity> = ctx.find_all(&EMAIL_PATTERN, text).into_iter().filter_map(|m| {
+        let confidence = if strict { strict_email_confidence(m.as_str())? } else { 0.95 };
+        Some(Entity {
+            id: 0,
+            utf16_start: None,
+            utf16_end: None,
+            value: m.as_str().to_string(),
+            entity_type: EntityKind::Email.as_str().to_string(),
+            start: m.start(),
+            end: m.end(),
+            confidence,
+            context: ctx.context(EntityKind::Email, text, m.start(), m.end()),
+            metadata: email_role(text, m.start()).map(|role| HashMap::from([("role".to_string(), role)])),
+        })
+    }).collect();
+    if ctx.sniff.spelled_at {
+        emails.extend(extract_obfuscated_emails(text, ctx));
+    }
+    emails
+}
+
+/// Emails written "jeff [at] example [dot] com" to get past scrapers. The
+/// `value` is the text as written and `metadata["normalized"]` the address.
+/// A bare " at " needs a spelled-out " dot " as well, so "arrived at
+/// example.com" is no email.
+fn extract_obfuscated_emails(text: &str, ctx: &ExtractCtx) -> Vec<Entity> {
+    ctx.find_all(&OBFUSCATED_EMAIL_PATTERN, text).into_iter().filter_map(|m| {
+        let at = SPELLED_AT.find(m.as_str())?;
+        let bracketed = at.as_str().contains(['[', '(', '{']);
+        let spelled_dot = SPELLED_DOT.is_match(m.as_str());
+        if !bracketed && !spelled_dot {
+            return None;
+        }
+        let address = SPELLED_DOT.replace_all(&SPELLED_AT.replace(m.as_str(), "@"), ".").into_owned();
+        if EMAIL_PATTERN.find(&address).is_none_or(|e| e.len() != address.len()) {
+            return None;
+        }
+        let confidence: f64 = if bracketed { 0.75 } else { 0.6 };
+        let confidence = if ctx.opts.strict_email { confidence.min(strict_email_confidence(&address)?) } else { confidence };
+        let mut metadata = HashMap::from([("normalized".to_string(), address.to_lowercase())]);
+        if let Some(role) = email_role(text, m.start()) {
+            metadata.insert("role".to_string(), role);
+        }
+        Some(Entity {
+            id: 0,
+            utf16_start: None,
+            utf16_end: None,
+            value: m.as_str().to_string(),
+            entity_type: EntityKind::Email.as_str().to_string(),
+            start: m.start(),
+            end: m.end(),
+            confidence,
+            context: ctx.context(EntityKind::Email, text, m.start(), m.end()),
+            metadata: Some(metadata),
+        })
+    }).collect()
+}
+
+/// Header an address at `start` sits under ("from", "to", "cc", "bcc"),
+/// following folded continuation lines back to the header they belong to
+fn email_role(text: &str, start: usize) -> Option<String> {
+    let mut line_start = text[..start].rfind('\n').map_or(0, |i| i + 1);
+    loop {
+        if let Some(cap) = EMAIL_HEADER_PATTERN.captures(&text[line_start..start]) {
+            return Some(cap[1].to_lowercase());
+        }
+        // A line opening with whitespace continues the header above it
+        if line_start == 0 || !text[line_start..].starts_with([' ', '\t']) {
+            return None;
+        }
+        let prev_end = line_start - 1;
+        line_start = text[..prev_end].rfind('\n').map_or(0, |i| i + 1);
+    }
+}
+
+/// Top-level domains common enough in email to take at full confidence
+const COMMON_TLDS: &[&str] = &[
+    "com", "org", "net", "edu", "gov", "mil", "io", "co", "us", "uk", "ca", "au", "de",
+    "fr", "it", "es", "nl", "ch", "se", "jp", "cn", "in", "br", "ru", "eu",
+];
+
+/// Other real TLDs: the remaining country codes and the generic ones seen
+/// in practice
+const OTHER_TLDS: &[&str] = &[
+    "ac", "ad", "ae", "af", "ag", "ai", "al", "am", "ao", "aq", "ar", "as", "at", "aw", "ax",
+    "az", "ba", "bb", "bd", "be", "bf", "bg", "bh", "bi", "bj", "bm", "bn", "bo", "bs", "bt",
+    "bw", "by", "bz", "cc", "cd", "cf", "cg", "ci", "ck", "cl", "cm", "cr", "cu", "cv", "cw",
+    "cx", "cy", "cz", "dj", "dk", "dm", "do", "dz", "ec", "ee", "eg", "er", "et", "fi", "fj",
+    "fk", "fm", "fo", "ga", "gd", "ge", "gf", "gg", "gh", "gi", "gl", "gm", "gn", "gp", "gq",
+    "gr", "gs", "gt", "gu", "gw", "gy", "hk", "hm", "hn", "hr", "ht", "hu", "id", "ie", "il",
+    "im", "iq", "ir", "is", "je", "jm", "jo", "ke", "kg", "kh", "ki", "km", "kn", "kp", "kr",
+    "kw", "ky", "kz", "la", "lb", "lc", "li", "lk", "lr", "ls", "lt", "lu", "lv", "ly", "ma",
+    "mc", "md", "me", "mg", "mh", "mk", "ml", "mm", "mn", "mo", "mp", "mq", "mr", "ms", "mt",
+    "mu", "mv", "mw", "mx", "my", "mz", "na", "nc", "ne", "nf", "ng", "ni", "no", "np", "nr",
+    "nu", "nz", "om", "pa", "pe", "pf", "pg", "ph", "pk", "pl", "pm", "pn", "pr", "ps", "pt",
+    "pw", "py", "qa", "re", "ro", "rs", "rw", "sa", "sb", "sc", "sd", "sg", "sh", "si", "sk",
+    "sl", "sm", "sn", "so", "sr", "ss", "st", "su", "sv", "sx", "sy", "sz", "tc", "td", "tf",
+    "tg", "th", "tj", "tk", "tl", "tm", "tn", "to", "tr", "tt", "tv", "tw", "tz", "ua", "ug",
+    "uy", "uz", "va", "vc", "ve", "vg", "vi", "vn", "vu", "wf", "ws", "ye", "yt", "za", "zm",
+    "zw",
+    "int", "info", "biz", "name", "pro", "mobi", "aero", "coop", "museum", "jobs", "travel",
+    "app", "dev", "xyz", "online", "site", "tech", "store", "shop", "blog", "cloud", "email",
+    "news", "law", "legal", "agency", "company", "media", "network", "page", "club", "live",
+    "world", "group", "global", "solutions", "services", "consulting", "digital", "capital",
+    "fund", "finance", "bank", "money", "partners", "ventures", "holdings", "foundation",
+    "church", "health", "care", "school", "academy", "university",
+];
+
+/// Confidence for `email` under `strict_email`, or `None` to drop it
+fn strict_email_confidence(email: &str) -> Option<f64> {
+    let (local, domain) = email.rsplit_once('@')?;
+    if local.starts_with('.') || local.ends_with('.') || local.contains("..") {
+        return None;
+    }
+    let labels: Vec<&str> = domain.split('.').collect();
+    if labels.iter().any(|l| l.is_empty() || l.starts_with('-') || l.ends_with('-')) {
+        return None;
+    }
+    let tld = labels.last()?.to_ascii_lowercase();
+    if COMMON_TLDS.contains(&tld.as_str()) {
+        Some(0.95)
+    } else if OTHER_TLDS.contains(&tld.as_str()) {
+        Some(0.75)
+    } else {
+        None
+    }
+}
+
+/// "ext 56", "ext. 56", "x56" after a number; the digits are the last group
+const PHONE_EXTENSION: &str = r"(?:,?\s*(?i:ext(?:ension)?\.?|x)\s*([0-9]{1,6}))?";
+
+/// Country codes `phone.international` accepts: ISO country (none for the
+/// shared North American plan) and the digit count of national numbers,
+/// without the trunk 0
+const PHONE_COUNTRIES: &[(&str, Option<&str>, usize, usize)] = &[
+    ("1", None, 10, 10), ("7", Some("RU"), 10, 10), ("20", Some("EG"), 9, 10), ("27", Some("ZA"), 9, 9),
+    ("31", Some("NL"), 9, 9), ("32", Some("BE"), 8, 9), ("33", Some("FR"), 9, 9), ("34", Some("ES"), 9, 9),
+    ("39", Some("IT"), 6, 11), ("41", Some("CH"), 9, 9), ("44", Some("GB"), 9, 10), ("49", Some("DE"), 6, 11),
+    ("52", Some("MX"), 10, 10), ("55", Some("BR"), 10, 11), ("61", Some("AU"), 9, 9), ("65", Some("SG"), 8, 8),
+    ("81", Some("JP"), 9, 10), ("82", Some("KR"), 8, 10), ("86", Some("CN"), 10, 11), ("91", Some("IN"), 10, 10),
+    ("971", Some("AE"), 8, 9), ("972", Some("IL"), 8, 9),
+];
+
+/// E.164 form and country of an international number, the extension cut
+/// off; None for unknown country codes and national numbers of the wrong
+/// length. Country codes are prefix-free, so at most one matches.
+fn international_phone(number: &str) -> Option<(String, Option<&'static str>)> {
+    // "+44 (0)20 ..." writes the trunk 0 dialled at home
+    let digits: String = number.replace("(0)", "").chars().filter(char::is_ascii_digit).collect();
+    let &(code, country, min, max) = PHONE_COUNTRIES.iter().find(|(code, ..)| digits.starts_with(code))?;
+    let national = &digits[code.len()..];
+    // Italian numbers keep it
+    let national = match national.strip_prefix('0') {
+        Some(rest) if country != Some("IT") => rest,
+        _ => national,
+    };
+    (min..=max).contains(&national.len()).then(|| (format!("+{code}{national}"), country))
+}
+
+/// North American numbers, then international ones with a known country
+/// code and a national number of its length. Both carry their E.164 form
+/// as `normalized` and any extension as `ext`.
+fn extract_phones(text: &str, ctx: &ExtractCtx) -> Vec<Entity> {
+    let mut phones: Vec<Entity> = ctx.captures_all(&INTERNATIONAL_PHONE_PATTERN, text).into_iter()
+        .filter_map(|caps| {
+            let m = caps.get(0).unwrap();
+            let ext = caps.get(1);
+            let number = &text[m.start()..ext.map_or(m.end(), |_| m.start() + m.as_str().trim_end_matches(|c: char| c.is_ascii_digit()).len())];
+            let (normalized, country) = international_phone(number)?;
+            let mut metadata = HashMap::from([("normalized".to_string(), normalized)]);
+            if let Some(country) = country {
+                metadata.insert("country".to_string(), country.to_string());
+            }
+            if let Some(ext) = ext {
+                metadata.insert("ext".to_string(), ext.as_str().to_string());
+            }
+            Some(Entity {
+                id: 0,
+                utf16_start: None,
+                utf16_end: None,
+                value: m.as_str().to_string(),
+                entity_type: EntityKind::Phone.as_str().to_string(),
+                start: m.start(),
+                end: m.end(),
+                confidence: 0.85,
+                context: ctx.context(EntityKind::Phone, text, m.start(), m.end()),
+                metadata: Some(metadata),
+            })
+        })
+        .collect();
+
+    let north_american: Vec<Entity> = ctx.captures_all(&PHONE_PATTERN, text).into_iter()
+        .filter_map(|caps| {
+            let m = caps.get(0).unwrap();
+            if phones.iter().any(|p| m.start() < p.end && p.start < m.end()) {
+                return None;
+            }
+            let mut metadata = HashMap::from([("normalized".to_string(), format!("+1{}{}{}", &caps[1], &caps[2], &caps[3]))]);
+            if let Some(ext) = caps.get(4) {
+                metadata.insert("ext".to_string(), ext.as_str().to_string());
+            }
+            Some(Entity {
+                id: 0,
+                utf16_start: None,
+                utf16_end: None,
+                value: m.as_str().to_string(),
+                entity_type: EntityKind::Phone.as_str().to_string(),
+                start: m.start(),
+                end: m.end(),
+                confidence: 0.80,
+                context: ctx.context(EntityKind::Phone, text, m.start(), m.end()),
+                metadata: Some(metadata),
+            })
+        })
+        .collect();
+    phones.extend(north_american);
+    phones.sort_by_key(|p| p.start);
+    phones
+}
+
+fn extract_urls(text: &str, ctx: &ExtractCtx) -> Vec<Entity> {
+    let defanged = if ctx.sniff.defanged { extract_defanged_urls(text, ctx) } else { Vec::new() };
+    let mut urls: Vec<Entity> = ctx.find_all(&URL_PATTERN, text).into_iter()
+        // "https://evil[.]com" reads as "https://evil" here
+        .filter(|m| !defanged.iter().any(|d| d.start < m.end() && m.start() < d.end))
+        .map(|m| {
+            Entity {
+                id: 0,
+                utf16_start: None,
+                utf16_end: None,
+                value: m.as_str().to_string(),
+                entity_type: EntityKind::Url.as_str().to_string(),
+                start: m.start(),
+                end: m.end(),
+                confidence: 0.95,
+                context: ctx.context(EntityKind::Url, text, m.start(), m.end()),
+                metadata: None,
+            }
+        })
+        .collect();
+    if !defanged.is_empty() {
+        urls.extend(defanged);
+        urls.sort_by(|a, b| a.order_key().cmp(&b.order_key()));
+    }
+    urls
+}
+
+/// Defanged URLs, with the clickable form as `metadata["normalized"]` and
+/// `metadata["defanged"]` "true". A bare domain needs a defanged dot and
+/// an alphabetic TLD, and isn't the domain after an "@" or "[at]".
+fn extract_defanged_urls(text: &str, ctx: &ExtractCtx) -> Vec<Entity> {
+    ctx.find_all(&DEFANGED_URL_PATTERN, text).into_iter()
+        .filter_map(|m| {
+            let value = m.as_str();
+            let lower = value.to_ascii_lowercase();
+            let scheme_end = lower.find("://").or_else(|| lower.find("[:]//"));
+            let defanged_scheme = lower.starts_with("hxxp") || lower.starts_with("fxp") || lower.contains("[:]");
+            let defanged_dot = DEFANGED_DOT.is_match(value);
+            if !defanged_scheme && !defanged_dot {
+                return None;
+            }
+
+            let normalized = DEFANGED_DOT.replace_all(value, ".").replace("[:]", ":");
+            let normalized = match normalized.get(..4) {
+                Some(scheme) if scheme.eq_ignore_ascii_case("hxxp") => format!("http{}", &normalized[4..]),
+                _ if normalized.get(..3).is_some_and(|s| s.eq_ignore_ascii_case("fxp")) => format!("ftp{}", &normalized[3..]),
+                _ => normalized,
+            };
+            if scheme_end.is_none() {
+                let host = normalized.split(['/', ':']).next().unwrap_or("");
+                let tld = host.rsplit('.').next().unwrap_or("");
+                if !(2..=24).contains(&tld.len()) || !tld.bytes().all(|b| b.is_ascii_alphabetic()) {
+                    return None;
+                }
+                let before = text[..m.start()].trim_end();
+                let before = &before[before.floor_char_boundary(before.len().saturating_sub(4))..];
+                if before.ends_with('@') || SPELLED_AT.find(before).is_some_and(|at| at.end() == before.len()) {
+                    return None;
+                }
+            }
+
+            Some(Entity {
+                id: 0,
+                utf16_start: None,
+                utf16_end: None,
+                value: value.to_string(),
+                entity_type: EntityKind::Url.as_str().to_string(),
+                start: m.start(),
+                end: m.end(),
+                confidence: if scheme_end.is_some() { 0.9 } else { 0.75 },
+                context: ctx.context(EntityKind::Url, text, m.start(), m.end()),
+                metadata: Some(HashMap::from([
+                    ("normalized".to_string(), normalized),
+                    ("defanged".to_string(), "true".to_string()),
+                ])),
+            })
+        })
+        .collect()
+}
+
+fn postal_entity(text: &str, m: regex::Match, country: &str, confidence: f64, ctx: &ExtractCtx) -> Entity {
+    Entity {
+        id: 0,
+        utf16_start: None,
+        utf16_end: None,
+        value: m.as_str().to_string(),
+        entity_type: EntityKind::PostalCode.as_str().to_string(),
+        start: m.start(),
+        end: m.end(),
+        confidence,
+        context: ctx.context(EntityKind::PostalCode, text, m.start(), m.end()),
+        metadata: Some(HashMap::from([("country".to_string(), country.to_string())])),
+    }
+}
+
+/// Whether the text just before a 5-digit candidate makes it a US ZIP: a state
+/// code or "ZIP" label right before it, or a street address earlier on the line
+fn is_us_zip_context(text: &str, start: usize) -> bool {
+    let line_start = text[..start].rfind('\n').map_or(0, |i| i + 1);
+    let before = &text[line_start..start];
+    if US_ZIP_CONTEXT.is_match(before) {
+        return true;
+    }
+    LOCATION_PATTERNS[2].find_iter(before).any(|street| before.len() - street.end() <= 40)
+}
+
+fn extract_postal_codes(text: &str, ctx: &ExtractCtx) -> Vec<Entity> {
+    let mut entities: Vec<Entity> = ctx.find_all(&US_ZIP_PATTERN, text).into_iter()
+        .filter(|m| is_us_zip_context(text, m.start()))
+        .map(|m| postal_entity(text, m, "US", 0.85, ctx))
+        .collect();
+    entities.extend(ctx.find_all(&CA_POSTAL_PATTERN, text).into_iter()
+        .map(|m| postal_entity(text, m, "CA", 0.90, ctx)));
+    entities.extend(ctx.find_all(&UK_POSTCODE_PATTERN, text).into_iter()
+        .map(|m| postal_entity(text, m, "GB", 0.85, ctx)));
+    entities.sort_by(|a, b| a.order_key().cmp(&b.order_key()));
+    entities
+}
+
+fn citation_entity(text: &str, cap: &regex::Captures, metadata: HashMap<String, String>, ctx: &ExtractCtx) -> Entity {
+    let m = cap.get(0).unwrap();
+    Entity {
+        id: 0,
+        utf16_start: None,
+        utf16_end: None,
+        value: m.as_str().to_string(),
+        entity_type: EntityKind::Citation.as_str().to_string(),
+        start: m.start(),
+        end: m.end(),
+        confidence: 0.90,
+        context: ctx.context(EntityKind::Citation, text, m.start(), m.end()),
+        metadata: Some(metadata),
+    }
+}
+
+fn extract_citations(text: &str, ctx: &ExtractCtx) -> Vec<Entity> {
+    let mut entities: Vec<Entity> = ctx.captures_all(&CASE_CITATION_PATTERN, text).iter()
+        .map(|cap| citation_entity(text, cap, HashMap::from([
+            ("kind".to_string(), "case".to_string()),
+            ("volume".to_string(), cap[1].to_string()),
+            ("reporter".to_string(), cap[2].to_string()),
+            ("page".to_string(), cap[3].to_string()),
+        ]), ctx))
+        .collect();
+
+    entities.extend(ctx.captures_all(&STATUTE_CITATION_PATTERN, text).iter()
+        .map(|cap| {
+            let code: String = cap[2].chars().filter(|c| c.is_ascii_alphabetic()).collect();
+            citation_entity(text, cap, HashMap::from([
+                ("kind".to_string(), "statute".to_string()),
+                ("title".to_string(), cap[1].to_string()),
+                ("code".to_string(), code),
+                ("section".to_string(), cap[3].to_string()),
+            ]), ctx)
+        }));
+
+    entities.sort_by(|a, b| a.order_key().cmp(&b.order_key()));
+    entities
+}
+
+fn reference_entity(text: &str, m: regex::Match, kind: &str, number: &str, ctx: &ExtractCtx) -> Entity {
+    Entity {
+        id: 0,
+        utf16_start: None,
+        utf16_end: None,
+        value: m.as_str().to_string(),
+        entity_type: EntityKind::Reference.as_str().to_string(),
+        start: m.start(),
+        end: m.end(),
+        confidence: 0.85,
+        context: ctx.context(EntityKind::Reference, text, m.start(), m.end()),
+        metadata: Some(HashMap::from([
+            ("kind".to_string(), kind.to_string()),
+            ("number".to_string(), number.to_string()),
+        ])),
+    }
+}
+
+/// Exhibit, item, section and paragraph references; `metadata["kind"]` is
+/// one of those four and `metadata["number"]` the designator as written
+fn extract_references(text: &str, ctx: &ExtractCtx) -> Vec<Entity> {
+    let mut entities: Vec<Entity> = ctx.captures_all(&REFERENCE_PATTERN, text).iter()
+        .map(|cap| {
+            let kind = match cap[1].to_ascii_lowercase().trim_end_matches(['s', '.']) {
+                "exhibit" => "exhibit",
+                "item" => "item",
+                "section" | "sec" => "section",
+                _ => "paragraph",
+            };
+            reference_entity(text, cap.get(0).unwrap(), kind, &cap[2], ctx)
+        })
+        .collect();
+    entities.extend(ctx.captures_all(&PARAGRAPH_SIGN_PATTERN, text).iter()
+        .map(|cap| reference_entity(text, cap.get(0).unwrap(), "paragraph", &cap[1], ctx)));
+    entities.sort_by(|a, b| a.order_key().cmp(&b.order_key()));
+    entities
+}
+
+// =============================================================================
+// PARALLEL EXTRACTION
+// =============================================================================
+
+/// Extract all entities from text using parallel processing
+pub fn extract_all(text: &str) -> ExtractionResult {
+    extract_with_options(text, &ExtractOptions::default())
+}
+
+/// Extract all entities, scheduling the extractors according to `options.parallelism`
+///
+/// Results are identical whichever strategy runs; only the timing differs.
+pub fn extract_with_options(text: &str, options: &ExtractOptions) -> ExtractionResult {
+    extract_with_registry(text, options, &BUILTIN_EXTRACTORS)
+}
+
+/// Extract from at most the first `max_bytes` of `text`, rounded down to a
+/// char boundary, for quick previews of large inputs
+///
+/// Offsets are into `text` itself. An entity cut by the boundary comes back
+/// as the part before it ("John Smi").
+pub fn extract_preview(text: &str, max_bytes: usize, options: &ExtractOptions) -> ExtractionResult {
+    let mut end = max_bytes.min(text.len());
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    let mut result = extract_with_options(&text[..end], options);
+    result.preview_truncated = end < text.len();
+    result
+}
+
+pub(crate) fn extract_with_registry(text: &str, options: &ExtractOptions, extractors: &Registry) -> ExtractionResult {
+    extract_selected(text, options, &extractors.iter().map(|e| e.as_ref()).collect::<Vec<_>>())
+}
+
+fn extract_selected(text: &str, options: &ExtractOptions, extractors: &[&(dyn EntityExtractor + Sync)]) -> ExtractionResult {
+    let start = std::time::Instant::now();
+    let result = match scan_spans(text, options) {
+        Some(spans) => spans.into_iter().fold(ExtractionResult::default(), |result, (from, to)| {
+            result.merge(run_unfinalized(&text[from..to], options, extractors), from)
+        }),
+        None => run_unfinalized(text, options, extractors),
+    };
+    finish(result, text, options, start.elapsed())
+}
+
+/// Matches of `scan_within`, if set
+fn scan_spans(text: &str, options: &ExtractOptions) -> Option<Vec<(usize, usize)>> {
+    let regex = options.scan_within.as_ref()?;
+    Some(regex.find_iter(text).map(|m| (m.start(), m.end())).collect())
+}
+
+/// Run the extractors over `text`, in its own offsets, without the
+/// whole-document passes
+pub(crate) fn extract_unfinalized(text: &str, options: &ExtractOptions, extractors: &Registry) -> ExtractionResult {
+    run_unfinalized(text, options, &extractors.iter().map(|e| e.as_ref()).collect::<Vec<_>>())
+}
+
+fn run_unfinalized(text: &str, options: &ExtractOptions, extractors: &[&(dyn EntityExtractor + Sync)]) -> ExtractionResult {
+    match options.normalize_unicode.then(|| nfc::to_nfc(text)).flatten() {
+        Some((normalized, map)) => {
+            let mut result = run_each(&normalized, options, extractors, &|_| {});
+            for entity in result.entities_mut() {
+                map.remap(entity, text, options.context_chars);
+            }
+            result
+        }
+        None => run_each(text, options, extractors, &|_| {}),
+    }
+}
+
+pub(crate) fn finish(mut result: ExtractionResult, text: &str, options: &ExtractOptions, elapsed: std::time::Duration) -> ExtractionResult {
+    finalize(&mut result, text, options);
+
+    if cfg!(debug_assertions) && options.validate_offsets {
+        if let Err(err) = validate_offsets(text, &result) {
+            panic!("{}", err);
+        }
+    }
+
+    result.processing_time_ms = elapsed.as_millis() as u64;
+    result.text_len = text.len();
+    result
+}
+
+/// Check that every entity's offsets select exactly its `value` in `text`
+pub fn validate_offsets(text: &str, result: &ExtractionResult) -> Result<(), ExtractError> {
+    let mismatches: Vec<OffsetMismatch> = result.entities()
+        .filter_map(|e| {
+            let found = text.get(e.start..e.end);
+            (found != Some(e.value.as_str())).then(|| OffsetMismatch {
+                entity_type: e.entity_type.clone(),
+                value: e.value.clone(),
+                start: e.start,
+                end: e.end,
+                found: found.map(str::to_string),
+            })
+        })
+        .collect();
+
+    if mismatches.is_empty() {
+        Ok(())
+    } else {
+        Err(ExtractError::OffsetMismatch(mismatches))
+    }
+}
+
+/// Passes over the merged result, in original-text offsets
+fn finalize(result: &mut ExtractionResult, text: &str, options: &ExtractOptions) {
+    let regions = Regions::new(text, options);
+    for &kind in EntityKind::ALL {
+        finalize_entities(result.field_mut(kind), options, &regions);
+    }
+    finalize_entities(&mut result.custom, options, &regions);
+    drop_emails_in_urls(result, text, options);
+    link_aliases(result, text, options, &regions);
+    if options.coref {
+        add_person_coreferences(result, text, options, &regions);
+    }
+    // Aliases and mentions found over the whole text
+    if regions.scan.is_some() {
+        result.retain(|e| regions.scanned(e.start, e.end));
+    }
+    result.recount();
+    link_persons(result, text);
+    if options.utf16_offsets {
+        assign_utf16_offsets(result.entities_mut(), text);
+    }
+    if options.line_columns {
+        assign_line_columns(result.entities_mut(), text, &line_starts(text));
+    }
+    if options.tag_scripts {
+        assign_scripts(result.entities_mut());
+    }
+    if options.tag_assertions {
+        assign_assertions(result.entities_mut(), text, &options.assertion_cues);
+    }
+    if let Some(decay) = options.repeat_decay {
+        decay_repeats(result.entities_mut(), decay);
+    }
+    result.renumber();
+}
+
+/// Set `utf16_start`/`utf16_end` from the byte offsets, counting code units
+/// in a single walk over the text up to the last boundary
+fn assign_utf16_offsets<'e>(entities: impl Iterator<Item = &'e mut Entity>, text: &str) {
+    let mut entities: Vec<&mut Entity> = entities.collect();
+    let mut bounds: Vec<usize> = entities.iter().flat_map(|e| [e.start, e.end]).collect();
+    bounds.sort_unstable();
+    bounds.dedup();
+
+    let mut units = Vec::with_capacity(bounds.len());
+    let (mut byte, mut count) = (0, 0);
+    for &bound in &bounds {
+        count += text[byte..bound].encode_utf16().count();
+        units.push(count);
+        byte = bound;
+    }
+    let utf16 = |offset: usize| units[bounds.binary_search(&offset).unwrap()];
+    for entity in entities.iter_mut() {
+        entity.utf16_start = Some(utf16(entity.start));
+        entity.utf16_end = Some(utf16(entity.end));
+    }
+}
+
+/// Byte offset where each line starts, for `assign_line_columns`
+fn line_starts(text: &str) -> Vec<usize> {
+    std::iter::once(0).chain(text.match_indices('\n').map(|(i, _)| i + 1)).collect()
+}
+
+/// Set `metadata["line"]`/`["col"]` and `["end_line"]`/`["end_col"]` from
+/// the byte offsets; `lines` is `line_starts(text)`, built once per document
+fn assign_line_columns<'e>(entities: impl Iterator<Item = &'e mut Entity>, text: &str, lines: &[usize]) {
+    let position = |offset: usize| {
+        let line = lines.partition_point(|&start| start <= offset);
+        let column = text[lines[line - 1]..offset].chars().count();
+        (line.to_string(), column.to_string())
+    };
+    for entity in entities {
+        let (line, col) = position(entity.start);
+        let (end_line, end_col) = position(entity.end);
+        let metadata = entity.metadata.get_or_insert_with(HashMap::new);
+        metadata.insert("line".to_string(), line);
+        metadata.insert("col".to_string(), col);
+        metadata.insert("end_line".to_string(), end_line);
+        metadata.insert("end_col".to_string(), end_col);
+    }
+}
+
+/// The writing system of a letter, by Unicode block; None for non-letters
+fn letter_script(c: char) -> Option<&'static str> {
+    if !c.is_alphabetic() {
+        return None;
+    }
+    Some(match c as u32 {
+        0x0041..=0x024F | 0x1E00..=0x1EFF => "Latin",
+        0x0370..=0x03FF | 0x1F00..=0x1FFF => "Greek",
+        0x0400..=0x052F | 0x2DE0..=0x2DFF | 0xA640..=0xA69F => "Cyrillic",
+        0x0590..=0x05FF | 0xFB1D..=0xFB4F => "Hebrew",
+        0x0600..=0x06FF | 0x0750..=0x077F | 0x08A0..=0x08FF | 0xFB50..=0xFDFF | 0xFE70..=0xFEFF => "Arabic",
+        0x0900..=0x097F => "Devanagari",
+        0x0E00..=0x0E7F => "Thai",
+        // Hangul, kana and Han
+        0x1100..=0x11FF | 0x3040..=0x30FF | 0x3130..=0x318F | 0x3400..=0x4DBF | 0x4E00..=0x9FFF
+        | 0xAC00..=0xD7AF | 0xF900..=0xFAFF | 0x20000..=0x2FFFF => "CJK",
+        _ => "Other",
+    })
+}
+
+/// The script most of `value`'s letters are in, on a tie the one that
+/// appears first; "Common" without letters
+fn dominant_script(value: &str) -> &'static str {
+    let mut counts: Vec<(&'static str, usize)> = Vec::new();
+    for script in value.chars().filter_map(letter_script) {
+        match counts.iter_mut().find(|(s, _)| *s == script) {
+            Some((_, count)) => *count += 1,
+            None => counts.push((script, 1)),
+        }
+    }
+    counts.iter()
+        .fold(None, |best: Option<(&'static str, usize)>, &(script, count)| match best {
+            Some((_, most)) if most >= count => best,
+            _ => Some((script, count)),
+        })
+        .map_or("Common", |(script, _)| script)
+}
+
+/// Set `metadata["script"]` from each entity's value
+fn assign_scripts<'e>(entities: impl Iterator<Item = &'e mut Entity>) {
+    for entity in entities {
+        let script = dominant_script(&entity.value);
+        entity.metadata.get_or_insert_with(HashMap::new).insert("script".to_string(), script.to_string());
+    }
+}
+
+/// Least confidence `repeat_decay` lowers a repeat to; one already below it
+/// is left as is
+pub const REPEAT_DECAY_FLOOR: f64 = 0.1;
+
+/// Scale the confidence of each repeat of a type and normalized value by
+/// `decay` per earlier occurrence, down to `REPEAT_DECAY_FLOOR`
+fn decay_repeats<'e>(entities: impl Iterator<Item = &'e mut Entity>, decay: f64) {
+    let decay = decay.clamp(0.0, 1.0);
+    let mut entities: Vec<&mut Entity> = entities.collect();
+    entities.sort_by(|a, b| a.order_key().cmp(&b.order_key()));
+    let mut seen: HashMap<(String, String), i32> = HashMap::new();
+    for entity in entities {
+        let earlier = seen.entry((entity.entity_type.clone(), entity.normalized_value())).or_insert(0);
+        if *earlier > 0 {
+            let floor = REPEAT_DECAY_FLOOR.min(entity.confidence);
+            entity.confidence = (entity.confidence * decay.powi(*earlier)).max(floor);
+        }
+        *earlier += 1;
+    }
+}
+
+/// Whether `window` holds `cue` as whole words; both are lowercase
+fn has_cue(window: &str, cue: &str) -> bool {
+    window.match_indices(cue).any(|(i, _)| {
+        !window[..i].ends_with(|c: char| c.is_alphanumeric() || c == '\'')
+            && !window[i + cue.len()..].starts_with(|c: char| c.is_alphanumeric() || c == '\'')
+    })
+}
+
+/// Set `metadata["negated"]`/`["hedged"]` from the cues in the clause
+/// before each entity, at most `cues.window_chars` back
+fn assign_assertions<'e>(entities: impl Iterator<Item = &'e mut Entity>, text: &str, cues: &AssertionCues) {
+    let lower = |list: &[String]| -> Vec<String> { list.iter().map(|c| c.to_lowercase()).collect() };
+    let (negation, hedging) = (lower(&cues.negation), lower(&cues.hedging));
+    for entity in entities {
+        let from = match cues.window_chars {
+            0 => entity.start,
+            n => text[..entity.start].char_indices().rev().nth(n - 1).map_or(0, |(i, _)| i),
+        };
+        let window = &text[from..entity.start];
+        // Not at a decimal point ("5.5 million")
+        let break_at = window.rfind([';', '!', '?', '\n']).map(|i| i + 1).max(window.rfind(". ").map(|i| i + 2));
+        let clause = window[break_at.unwrap_or(0)..].to_lowercase().replace('’', "'");
+        let negated = negation.iter().any(|cue| has_cue(&clause, cue));
+        let hedged = hedging.iter().any(|cue| has_cue(&clause, cue));
+        let metadata = entity.metadata.get_or_insert_with(HashMap::new);
+        metadata.insert("negated".to_string(), negated.to_string());
+        metadata.insert("hedged".to_string(), hedged.to_string());
+    }
+}
+
+/// The parts of the text `finalize_entities` tags or filters entities by
+struct Regions {
+    /// `paragraph_starts`
+    paragraphs: Vec<usize>,
+    /// `quote_spans`, unless `quote_handling` is `Include`
+    quotes: Vec<(usize, usize)>,
+    /// `scan_spans`
+    scan: Option<Vec<(usize, usize)>>,
+    /// `line_timestamps`
+    timestamps: Vec<LineTimestamp>,
+}
+
+/// A transcript line with a leading timestamp
+struct LineTimestamp {
+    line_start: usize,
+    /// At the line's `\n`, or the end of the text
+    line_end: usize,
+    /// Where the timestamp, brackets included, ends
+    stamp_end: usize,
+    seconds: u64,
+}
+
+impl Regions {
+    fn new(text: &str, options: &ExtractOptions) -> Self {
+        let quotes = match options.quote_handling {
+            QuoteHandling::Include => Vec::new(),
+            _ => quote_spans(text),
+        };
+        let timestamps = if options.transcript_timestamps { line_timestamps(text) } else { Vec::new() };
+        Regions { paragraphs: paragraph_starts(text, options), quotes, scan: scan_spans(text, options), timestamps }
+    }
+
+    /// Whether `start..end` lies within one `scan_within` match, or there
+    /// is no `scan_within`
+    fn scanned(&self, start: usize, end: usize) -> bool {
+        self.scan.as_ref().is_none_or(|spans| spans.iter().any(|&(from, to)| from <= start && end <= to))
+    }
+
+    /// The timestamped line `start` is on, if any
+    fn timestamp(&self, start: usize) -> Option<&LineTimestamp> {
+        let i = self.timestamps.partition_point(|t| t.line_start <= start).checked_sub(1)?;
+        Some(&self.timestamps[i]).filter(|t| start <= t.line_end)
+    }
+
+    /// Whether `start..end` lies within one quoted span
+    fn quoted(&self, start: usize, end: usize) -> bool {
+        let before = self.quotes.partition_point(|&(from, _)| from <= start);
+        self.quotes[..before].iter().any(|&(_, to)| end <= to)
+    }
+}
+
+/// Lines of `text` starting with a timestamp, in order. Minutes and seconds
+/// past 59 are a clock reading or a ratio, not a timestamp, except the
+/// minutes of "MM:SS" ("75:10" into a long recording).
+fn line_timestamps(text: &str) -> Vec<LineTimestamp> {
+    LINE_TIMESTAMP_PATTERN.captures_iter(text)
+        .filter_map(|cap| {
+            let whole = cap.get(0).unwrap();
+            let stamp = cap.get(1).or_else(|| cap.get(2)).unwrap().as_str();
+            let parts: Vec<u64> = stamp.split(':').map(|p| p.parse().unwrap()).collect();
+            if parts[1..].iter().any(|&part| part > 59) {
+                return None;
+            }
+            Some(LineTimestamp {
+                line_start: whole.start(),
+                line_end: text[whole.end()..].find('\n').map_or(text.len(), |i| whole.end() + i),
+                stamp_end: whole.end(),
+                seconds: parts.iter().fold(0, |total, part| total * 60 + part),
+            })
+        })
+        .collect()
+}
+
+/// Byte ranges between matching double quotes, the quotes excluded and
+/// sorted by start. Straight quotes pair up in order; a curly “ pairs with
+/// the next ”. A quote left unmatched opens no span.
+fn quote_spans(text: &str) -> Vec<(usize, usize)> {
+    let mut spans = Vec::new();
+    let (mut straight, mut curly) = (None, None);
+    for (i, c) in text.char_indices() {
+        match c {
+            '"' => match straight.take() {
+                Some(open) => spans.push((open, i)),
+                None => straight = Some(i + 1),
+            },
+            '“' => curly = Some(i + c.len_utf8()),
+            '”' => {
+                if let Some(open) = curly.take() {
+                    spans.push((open, i));
+                }
+            }
+            _ => {}
+        }
+    }
+    spans.sort_unstable();
+    spans
+}
+
+/// Byte offset where each paragraph starts, if `tag_paragraphs` is set;
+/// empty paragraphs (leading or repeated delimiters) get no index
+fn paragraph_starts(text: &str, options: &ExtractOptions) -> Vec<usize> {
+    if !options.tag_paragraphs {
+        return Vec::new();
+    }
+    let breaks: Vec<(usize, usize)> = match options.paragraph_delimiter.as_deref() {
+        Some("") => Vec::new(),
+        Some(delimiter) => text.match_indices(delimiter).map(|(i, d)| (i, i + d.len())).collect(),
+        None => PARAGRAPH_BREAK_PATTERN.find_iter(text).map(|m| (m.start(), m.end())).collect(),
+    };
+
+    let mut starts = vec![0];
+    for (start, end) in breaks {
+        let last = starts.last_mut().unwrap();
+        if text[*last..start].trim().is_empty() {
+            *last = end;
+        } else {
+            starts.push(end);
+        }
+    }
+    starts
+}
+
+/// Whether the email at `start..end` is part of one of the `urls` (a query
+/// parameter, a path), as opposed to the address of a `mailto:`
+fn inside_url(text: &str, (start, end): (usize, usize), urls: &[(usize, usize)]) -> bool {
+    let mailto = start.checked_sub("mailto:".len())
+        .and_then(|from| text.get(from..start))
+        .is_some_and(|prefix| prefix.eq_ignore_ascii_case("mailto:"));
+    !mailto && urls.iter().any(|&(from, to)| from <= start && end <= to)
+}
+
+/// Apply `ExtractOptions.email_in_url` against the URLs in `result`
+fn drop_emails_in_urls(result: &mut ExtractionResult, text: &str, options: &ExtractOptions) {
+    if options.email_in_url == EmailInUrl::Both || result.urls.is_empty() {
+        return;
+    }
+    let urls: Vec<(usize, usize)> = result.urls.iter().map(|e| (e.start, e.end)).collect();
+    result.emails.retain(|e| !inside_url(text, (e.start, e.end), &urls));
+}
+
+/// `value` as `ExtractOptions.denylist` compares it
+fn denylist_key(value: &str) -> String {
+    value.split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase()
+}
+
+fn drop_denylisted(entities: &mut Vec<Entity>, options: &ExtractOptions) {
+    if options.denylist.is_empty() {
+        return;
+    }
+    let denied: HashSet<String> = options.denylist.iter().map(|v| denylist_key(v)).collect();
+    entities.retain(|e| !denied.contains(&denylist_key(&e.value)));
+}
+
+/// The part of `finalize` that looks at one entity at a time
+fn finalize_entities(entities: &mut Vec<Entity>, options: &ExtractOptions, regions: &Regions) {
+    drop_denylisted(entities, options);
+    if !options.exclude_ranges.is_empty() {
+        entities.retain(|e| {
+            !options.exclude_ranges.iter().any(|&(start, end)| e.start < end && start < e.end)
+        });
+    }
+    if regions.scan.is_some() {
+        entities.retain(|e| regions.scanned(e.start, e.end));
+    }
+    apply_quote_handling(entities, options, regions);
+    if options.assign_ids {
+        for entity in entities.iter_mut() {
+            let id = entity.stable_id();
+            entity.metadata.get_or_insert_with(HashMap::new).insert("entity_id".to_string(), id);
+        }
+    }
+    if !regions.timestamps.is_empty() {
+        entities.retain_mut(|e| match regions.timestamp(e.start) {
+            Some(stamp) if e.start < stamp.stamp_end => false,
+            Some(stamp) => {
+                e.metadata.get_or_insert_with(HashMap::new).insert("timestamp".to_string(), stamp.seconds.to_string());
+                true
+            }
+            None => true,
+        });
+    }
+    if !regions.paragraphs.is_empty() {
+        for entity in entities.iter_mut() {
+            let index = regions.paragraphs.partition_point(|&start| start <= entity.start).saturating_sub(1);
+            entity.metadata.get_or_insert_with(HashMap::new).insert("paragraph".to_string(), index.to_string());
+        }
+    }
+}
+
+fn apply_quote_handling(entities: &mut Vec<Entity>, options: &ExtractOptions, regions: &Regions) {
+    if options.quote_handling == QuoteHandling::Include {
+        return;
+    }
+    let only_quoted = options.quote_handling == QuoteHandling::OnlyQuoted;
+    entities.retain_mut(|e| {
+        let quoted = regions.quoted(e.start, e.end);
+        if quoted == only_quoted {
+            e.metadata.get_or_insert_with(HashMap::new).insert("quoted".to_string(), quoted.to_string());
+        }
+        quoted == only_quoted
+    });
+}
+
+/// Extract like `extract_with_registry`, handing each extractor's entities
+/// to `sink` (sorted by offset) as soon as that extractor finishes
+///
+/// Streamed entities are remapped, filtered by `exclude_ranges` and carry
+/// `entity_id`s, but not the `person_offset` and `alias_of` links, `coref`
+/// mentions, `email_in_url` or their final `id` (these need every
+/// extractor's output); the returned result, built once every extractor is
+/// done, has them all. `kinds` limits the run to extractors of those kinds.
+pub(crate) fn extract_streaming_with_registry(
+    text: &str,
+    options: &ExtractOptions,
+    extractors: &Registry,
+    kinds: Option<&[EntityKind]>,
+    sink: &(dyn Fn(&[Entity]) + Sync),
+) -> ExtractionResult {
+    let start = std::time::Instant::now();
+    let selected: Vec<&(dyn EntityExtractor + Sync)> = extractors.iter()
+        .map(|e| e.as_ref())
+        .filter(|e| kinds.is_none_or(|kinds| e.kind().is_some_and(|kind| kinds.contains(&kind))))
+        .collect();
+
+    let nfc = options.normalize_unicode.then(|| nfc::to_nfc(text)).flatten();
+    let scanned = nfc.as_ref().map_or(text, |(normalized, _)| normalized.as_str());
+    let regions = Regions::new(text, options);
+    let lines = if options.line_columns { line_starts(text) } else { Vec::new() };
+    let result = run_each(scanned, options, &selected, &|entities| {
+        if let Some((_, map)) = &nfc {
+            for entity in entities.iter_mut() {
+                map.remap(entity, text, options.context_chars);
+            }
+        }
+        finalize_entities(entities, options, &regions);
+        if options.utf16_offsets {
+            assign_utf16_offsets(entities.iter_mut(), text);
+        }
+        if options.line_columns {
+            assign_line_columns(entities.iter_mut(), text, &lines);
+        }
+        if options.tag_scripts {
+            assign_scripts(entities.iter_mut());
+        }
+        if options.tag_assertions {
+            assign_assertions(entities.iter_mut(), text, &options.assertion_cues);
+        }
+        if let Some(decay) = options.repeat_decay {
+            decay_repeats(entities.iter_mut(), decay);
+        }
+        entities.sort_by(|a, b| a.order_key().cmp(&b.order_key()));
+        sink(entities);
+    });
+    finish(result, text, options, start.elapsed())
+}
+
+/// Run `extractors`, passing each one's output through `each` as it finishes
+fn run_each(
+    text: &str,
+    options: &ExtractOptions,
+    extractors: &[&(dyn EntityExtractor + Sync)],
+    each: &(dyn Fn(&mut Vec<Entity>) + Sync),
+) -> ExtractionResult {
+    let ctx = ExtractCtx::new(options, text);
+    let run = |e: &&(dyn EntityExtractor + Sync)| {
+        let mut entities = guarded(e.kind(), || e.extract_in(text, &ctx))?;
+        each(&mut entities);
+        Ok(entities)
+    };
+
+    // Each extractor is its own task, and the multi-pattern built-ins fan
+    // out further per pattern
+    let outputs: Vec<Result<Vec<Entity>, String>> = if ctx.parallel {
+        extractors.par_iter().map(run).collect()
+    } else {
+        extractors.iter().map(run).collect()
+    };
+
+    let mut result = ExtractionResult::default();
+    for (extractor, entities) in extractors.iter().zip(outputs) {
+        match (extractor.kind(), entities) {
+            (_, Err(err)) => result.errors.push(err),
+            (Some(kind), Ok(entities)) => result.field_mut(kind).extend(entities),
+            (None, Ok(entities)) => result.custom.extend(entities),
+        }
+    }
+
+    result.recount();
+    result.timed_out = ctx.timed_out.load(Ordering::Relaxed);
+    result
+}
+
+/// `extract` unless it panics, then the panic message under the kind's
+/// selector ("custom" for none), so one broken extractor costs only its
+/// own entities
+fn guarded(kind: Option<EntityKind>, extract: impl FnOnce() -> Vec<Entity>) -> Result<Vec<Entity>, String> {
+    std::panic::catch_unwind(std::panic::AssertUnwindSafe(extract)).map_err(|payload| {
+        format!("{}: {}", kind.map_or("custom", |k| k.selectors()[0]), panic_message(payload.as_ref()))
+    })
+}
+
+/// Extract specific entity types only
+///
+/// Selectors are the `EntityKind::selectors` strings ("dates", "orgs", ...);
+/// unknown ones are ignored.
+pub fn extract_types(text: &str, types: &[&str]) -> ExtractionResult {
+    extract_types_with(text, types, &ExtractOptions::default())
+}
+
+/// `extract_types` under `options`, through the same passes as
+/// `extract_with_options`
+pub fn extract_types_with(text: &str, types: &[&str], options: &ExtractOptions) -> ExtractionResult {
+    extract_types_with_registry(text, types, options, &BUILTIN_EXTRACTORS)
+}
+
+/// The `extractors` of the kinds `types` selects, and the custom ones
+/// whose `EntityExtractor::entity_type` it names
+pub(crate) fn extract_types_with_registry(text: &str, types: &[&str], options: &ExtractOptions, extractors: &Registry) -> ExtractionResult {
+    let kinds: Vec<EntityKind> = types.iter().flat_map(|t| EntityKind::selected(t)).collect();
+    let selected: Vec<&(dyn EntityExtractor + Sync)> = extractors.iter()
+        .map(|e| e.as_ref())
+        .filter(|e| match e.kind() {
+            Some(kind) => kinds.contains(&kind),
+            None => e.entity_type().is_some_and(|t| types.contains(&t)),
+        })
+        .collect();
+    extract_selected(text, options, &selected)
+}
+
+/// ICD-10 codes and drug names only (the "medical" selector)
+pub fn extract_medical(text: &str) -> ExtractionResult {
+    extract_types(text, &["medical"])
+}
+
+/// License plates only (the "plates" selector)
+pub fn extract_plates(text: &str) -> ExtractionResult {
+    extract_types(text, &["plates"])
+}
+
+/// ISBNs and DOIs only (the "identifiers" selector)
+pub fn extract_identifiers(text: &str) -> ExtractionResult {
+    extract_types(text, &["identifiers"])
+}
+
+/// Touches most of the patterns when extracted; see `warmup`
+const WARMUP_SAMPLE: &str = "From: jeff@example.com\n\
+    On January 15, 2024, Mr. Jeffrey Epstein (age 66) of Acme Holdings LLC, aka Jeff, wired $5 million \
+    and 1 234,56 EUR to jane@example.com from (212) 555-0100 via https://example.com/pay, New York, NY 10022. \
+    See 123 F.3d 456, 18 U.S.C. § 1591, Exhibit 12 and ¶ 37; ISBN 978-0-306-40615-7, doi:10.1038/nphys1170.\n\n\
+    Epstein held 12.5% at a 2:1 ratio, [REDACTED] XXXX ████, 5 kg, EIN 12-3456789, diagnosis E11.9, \
+    oxycodone, at 40.7128, -74.0060 and 40°42'46\"N 74°00'22\"W.\n\
+    | Payee | 2003 |\n| Jean Luc | $100,000 |\n";
+
+/// Compile the built-in patterns and the default drug automaton, and run
+/// them once, so the first request a server handles doesn't pay for it
+///
+/// Patterns are compiled on first use and their match caches grow on the
+/// first searches; this does both ahead of time on the calling thread.
+pub fn warmup() {
+    let options = ExtractOptions {
+        strict_email: true,
+        tag_paragraphs: true,
+        coref: true,
+        number_locale: NumberLocale::European,
+        ..Default::default()
+    };
+    extract_with_options(WARMUP_SAMPLE, &options);
+    count_all(WARMUP_SAMPLE, &options);
+    table::extract_amount_table(WARMUP_SAMPLE, &options);
+    sentences::group_by_sentence(WARMUP_SAMPLE, &ExtractionResult::default());
+}
+
+/// Names of every built-in pattern, for `ExtractOptions.disabled_patterns`
+pub fn builtin_pattern_names() -> Vec<&'static str> {
+    let lists: [&[NamedPattern]; 8] = [
+        &DATE_PATTERNS, &PERSON_PATTERNS, &ORG_PATTERNS, &AMOUNT_PATTERNS,
+        &EUROPEAN_AMOUNT_PATTERNS, &SWISS_AMOUNT_PATTERNS, &LOCATION_PATTERNS, &AGE_PATTERNS,
+    ];
+    let singles: [&NamedPattern; 37] = [
+        &BIRTH_YEAR_PATTERN, &DATE_RANGE_PATTERN, &QUARTER_PATTERN, &WRITTEN_AMOUNT_PATTERN, &WRITTEN_QUARTER_PATTERN, &FISCAL_YEAR_PATTERN, &EMAIL_PATTERN, &OBFUSCATED_EMAIL_PATTERN, &PHONE_PATTERN, &INTERNATIONAL_PHONE_PATTERN, &URL_PATTERN, &DEFANGED_URL_PATTERN,
+        &US_ZIP_PATTERN, &CA_POSTAL_PATTERN, &UK_POSTCODE_PATTERN,
+        &CASE_CITATION_PATTERN, &STATUTE_CITATION_PATTERN, &REFERENCE_PATTERN, &PARAGRAPH_SIGN_PATTERN,
+        &PERCENT_PATTERN, &FRACTION_PATTERN, &RATIO_PATTERN, &EIN_PATTERN, &ROUTING_NUMBER_PATTERN, &ICD10_PATTERN, &QUANTITY_NUMBER_PATTERN,
+        &DECIMAL_COORDINATE_PATTERN, &DMS_COORDINATE_PATTERN, &ISBN_PATTERN, &DOI_PATTERN,
+        &LICENSE_PLATE_PATTERN, &DURATION_PATTERN, &QUOTED_TITLE_PATTERN, &CASE_NAME_PATTERN, &BRACKETED_REDACTION_PATTERN, &BLOCK_REDACTION_PATTERN, &X_REDACTION_PATTERN,
+    ];
+    lists.iter().flat_map(|l| l.iter()).chain(singles).map(|p| p.name).collect()
+}
+
+lazy_static! {
+    // Any alias connector, for `count_all` to tell whether aliases may be added
+    static ref ALIAS_CUE_PATTERN: Regex = compile_pattern(&format!(r"(?i:{ALIAS_CONNECTORS})\.?\s")).unwrap();
+}
+
+/// Per-type entity counts, keyed by canonical selector ("persons",
+/// "emails"), equal to the vector lengths `extract_with_options` would
+/// return
+///
+/// Emails, URLs and amounts are counted straight off the matches
+/// without building entities; the kinds whose filters need the entities
+/// (persons, orgs, locations, ...) are extracted without context and
+/// counted. The passes over the whole result can't be counted per kind,
+/// so this runs the full extraction without context when they may change
+/// a count: with an alias connector ("aka") in the text, or any of
+/// `normalize_unicode`, `scan_within`, `transcript_timestamps`, `coref`,
+/// `denylist` or a `quote_handling` other than `Include` set.
+///
+/// On a 113 KB document with about 5,000 entities (release build, one
+/// core) this took 10.0 ms against 10.8-13.1 ms for `extract_all`: the
+/// matching itself dominates, so skipping the entities saves the
+/// allocations and context strings, not the scan.
+pub fn count_all(text: &str, options: &ExtractOptions) -> HashMap<String, usize> {
+    let counts = |result: &ExtractionResult| -> HashMap<String, usize> {
+        EntityKind::ALL.iter()
+            .map(|&kind| (kind.selectors()[0].to_string(), result.entities().filter(|e| e.entity_type == kind.as_str()).count()))
+            .collect()
+    };
+    let options = ExtractOptions { include_context: false, ..options.clone() };
+    if options.normalize_unicode
+        || options.scan_within.is_some()
+        || options.transcript_timestamps
+        || options.coref
+        || !options.denylist.is_empty()
+        || options.quote_handling != QuoteHandling::Include
+        || ALIAS_CUE_PATTERN.is_match(text)
+    {
+        return counts(&extract_with_options(text, &options));
+    }
+
+    let ctx = ExtractCtx::new(&options, text);
+    EntityKind::ALL.par_iter()
+        .map(|&kind| (kind.selectors()[0].to_string(), count_kind(kind, text, &ctx)))
+        .collect()
+}
+
+fn count_kind(kind: EntityKind, text: &str, ctx: &ExtractCtx) -> usize {
+    let kept = |start: usize, end: usize| {
+        !ctx.opts.exclude_ranges.iter().any(|&(from, to)| start < to && from < end)
+    };
+    let count_matches = |pattern: &NamedPattern, valid: &dyn Fn(&str) -> bool| {
+        ctx.find_all(pattern, text).into_iter()
+            .filter(|m| kept(m.start(), m.end()) && valid(m.as_str()))
+            .count()
+    };
+    if !ctx.sniff.admits(kind) {
+        return 0;
+    }
+
+    match kind {
+        EntityKind::Email => {
+            let urls: Vec<(usize, usize)> = match ctx.opts.email_in_url {
+                EmailInUrl::PreferUrl => ctx.find_all(&URL_PATTERN, text).into_iter()
+                    .filter(|m| kept(m.start(), m.end()))
+                    .map(|m| (m.start(), m.end()))
+                    .collect(),
+                EmailInUrl::Both => Vec::new(),
+            };
+            ctx.find_all(&EMAIL_PATTERN, text).into_iter()
+                .filter(|m| kept(m.start(), m.end()) && !inside_url(text, (m.start(), m.end()), &urls))
+                .filter(|m| !ctx.opts.strict_email || strict_email_confidence(m.as_str()).is_some())
+                .count()
+                + if ctx.sniff.spelled_at {
+                    extract_obfuscated_emails(text, ctx).iter().filter(|e| kept(e.start, e.end)).count()
+                } else {
+                    0
+                }
+        }
+        EntityKind::Url if !ctx.sniff.defanged => count_matches(&URL_PATTERN, &|_| true),
+        // Same first-spelling-wins dedup as `extract_with_patterns`; an
+        // excluded first occurrence drops the value, as in `finalize`
+        EntityKind::Amount if ctx.opts.number_locale == NumberLocale::English && !ctx.opts.parse_written_amounts && !ctx.opts.raw => {
+            let mut seen = HashSet::new();
+            AMOUNT_PATTERNS.iter()
+                .flat_map(|p| ctx.find_all(p, text))
+                .filter(|m| seen.insert(m.as_str().to_lowercase()) && kept(m.start(), m.end()))
+                .count()
+        }
+        _ => extract_kind(kind, text, ctx).iter().filter(|e| kept(e.start, e.end)).count(),
+    }
+}
+
+fn extract_kind(kind: EntityKind, text: &str, ctx: &ExtractCtx) -> Vec<Entity> {
+    if !ctx.sniff.admits(kind) {
+        return Vec::new();
+    }
+    match kind {
+        EntityKind::Date => extract_dates(text, ctx),
+        EntityKind::Person => extract_persons(text, ctx),
+        EntityKind::Organization => extract_organizations(text, ctx),
+        EntityKind::Amount => extract_amounts(text, ctx),
+        EntityKind::Location => extract_locations(text, ctx),
+        EntityKind::Email => extract_emails(text, ctx),
+        EntityKind::Phone => extract_phones(text, ctx),
+        EntityKind::Url => extract_urls(text, ctx),
+        EntityKind::PostalCode => extract_postal_codes(text, ctx),
+        EntityKind::Citation => extract_citations(text, ctx),
+        EntityKind::Age => extract_ages(text, ctx),
+        EntityKind::Percent => extract_percentages(text, ctx),
+        EntityKind::Ratio => extract_ratios(text, ctx),
+        EntityKind::Redaction => extract_redactions(text, ctx),
+        EntityKind::Quantity => extract_quantities(text, ctx),
+        EntityKind::Ein => extract_eins(text, ctx),
+        EntityKind::IcdCode => extract_icd_codes(text, ctx),
+        EntityKind::Drug => extract_drugs(text, ctx),
+        EntityKind::Coordinate => extract_coordinates(text, ctx),
+        EntityKind::Reference => extract_references(text, ctx),
+        EntityKind::Isbn => extract_isbns(text, ctx),
+        EntityKind::Doi => extract_dois(text, ctx),
+        EntityKind::LicensePlate => extract_license_plates(text, ctx),
+        EntityKind::Duration => extract_durations(text, ctx),
+        EntityKind::WorkTitle => extract_titles(text, ctx),
+        EntityKind::RoutingNumber => extract_routing_numbers(text, ctx),
+    }
+}
+
+// =============================================================================
+// BATCH PROCESSING
+// =============================================================================
+
+/// Process multiple documents in parallel
+///
+/// A document whose extraction panics yields an `Err` with the panic message
+/// at its index instead of unwinding the whole batch.
+///
+/// Work is balanced by bytes rather than by document: a document much
+/// larger than its share of the batch is cut into overlapping windows that
+/// run as separate tasks and are merged back into document offsets (see
+/// `window`). `processing_time_ms` is then the sum of its windows' times,
+/// and a `timeout` applies to each window.
+///
+/// On an imbalanced batch (one 1.95 MB document plus 63 of 7.4 KB, release
+/// build, one core) the batch went from 37.0 s per-document to 10.7 s
+/// windowed, with identical entity counts. With one core there is no
+/// parallelism to gain, so this is the effect of some passes scaling worse
+/// than linearly with document length; more cores add the load balancing
+/// on top.
+pub fn extract_batch(documents: &[&str]) -> Vec<Result<ExtractionResult, String>> {
+    batch_with_registry(documents, &ExtractOptions::default(), &BUILTIN_EXTRACTORS)
+}
+
+pub(crate) fn batch_with_registry(documents: &[&str], options: &ExtractOptions, extractors: &Registry) -> Vec<Result<ExtractionResult, String>> {
+    let total: usize = documents.iter().map(|doc| doc.len()).sum();
+    let target = total / (rayon::current_num_threads() * 4);
+    let units: Vec<(usize, window::Window)> = documents.iter()
+        .enumerate()
+        .flat_map(|(i, doc)| window::plan(doc, target).into_iter().map(move |w| (i, w)))
+        .collect();
+
+    let outputs = batch_isolated(&units, |&(i, w)| {
+        let start = std::time::Instant::now();
+        let result = extract_unfinalized(&documents[i][w.start..w.end], options, extractors);
+        (result, start.elapsed())
+    });
+
+    let mut parts: Vec<Result<Vec<(window::Window, ExtractionResult)>, String>> =
+        documents.iter().map(|_| Ok(Vec::new())).collect();
+    let mut elapsed = vec![std::time::Duration::ZERO; documents.len()];
+    for (&(i, w), output) in units.iter().zip(outputs) {
+        match (&mut parts[i], output) {
+            (Ok(windows), Ok((result, took))) => {
+                windows.push((w, result));
+                elapsed[i] += took;
+            }
+            (slot @ Ok(_), Err(err)) => *slot = Err(err),
+            (Err(_), _) => {}
+        }
+    }
+
+    let merged: Vec<(usize, Result<Vec<_>, String>)> = parts.into_iter().enumerate().collect();
+    batch_isolated(merged, |(i, part)| {
+        let start = std::time::Instant::now();
+        let result = window::merge(part?);
+        Ok(finish(result, documents[i], options, elapsed[i] + start.elapsed()))
+    })
+    .into_iter()
+    .map(|r| r.and_then(|inner| inner))
+    .collect()
+}
+
+/// Run `f` over `items` in parallel, turning a panic into an `Err` at that
+/// item's index
+pub(crate) fn batch_isolated<I, R, F>(items: I, f: F) -> Vec<Result<R, String>>
+where
+    I: IntoParallelIterator,
+    R: Send,
+    F: Fn(I::Item) -> R + Sync,
+{
+    items.into_par_iter()
+        .map(|item| {
+            std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| f(item)))
+                .map_err(|payload| panic_message(payload.as_ref()))
+        })
+        .collect()
+}
+
+fn panic_message(payload: &(dyn std::any::Any + Send)) -> String {
+    if let Some(msg) = payload.downcast_ref::<&str>() {
+        format!("extraction panicked: {}", msg)
+    } else if let Some(msg) = payload.downcast_ref::<String>() {
+        format!("extraction panicked: {}", msg)
+    } else {
+        "extraction panicked".to_string()
+    }
+}
+
+// =============================================================================
+// TESTS
+// =============================================================================
+
+#[cfg(test)]
+// The baseline `test_parallel_extraction` compares lengths to zero
+#[allow(clippy::len_zero)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_date_extraction() {
+        let text = "Meeting scheduled for January 15, 2024 and 2024-01-20";
+        let result = extract_dates(text, &ExtractCtx::new(&ExtractOptions::default(), text));
+        assert!(result.len() >= 2);
+    }
+
+    #[test]
+    fn test_person_extraction() {
+        let text = "Jeffrey Epstein met with Bill Clinton at the estate.";
+        let result = extract_persons(text, &ExtractCtx::new(&ExtractOptions::default(), text));
+        assert!(result.iter().any(|e| e.value.contains("Epstein")));
+    }
+
+    #[test]
+    fn test_amount_extraction() {
+        let text = "The transaction was $5.5 million USD and another $100,000";
+        let result = extract_amounts(text, &ExtractCtx::new(&ExtractOptions::default(), text));
+        assert!(result.len() >= 2);
+    }
+
+    #[test]
+    fn test_parallel_extraction() {
+        let text = "On January 15, 2024, Jeffrey Epstein transferred $5 million to Clinton Foundation in New York. Contact: jeff@example.com";
+        let result = extract_all(text);
+        assert!(result.dates.len() > 0);
+        assert!(result.persons.len() > 0);
+        assert!(result.amounts.len() > 0);
+        assert!(result.emails.len() > 0);
+    }
+
+    #[test]
+    fn test_parallelism_strategies_agree() {
+        let paragraph = "On January 15, 2024, Jeffrey Epstein transferred $5 million to Acme Holdings in Palm Beach, FL. \
+                         Contact jeff@example.com or (212) 555-1234, see https://example.com/doc. ";
+        let text = paragraph.repeat(400);
+        assert!(text.len() > PARALLEL_THRESHOLD_BYTES);
+
+        let run = |parallelism| extract_with_options(&text, &ExtractOptions { parallelism, ..Default::default() });
+        let seq = run(Parallelism::Sequential);
+        let par = run(Parallelism::Parallel);
+        let auto = run(Parallelism::Auto);
+
+        for other in [&par, &auto] {
+            assert_eq!(seq.entities().collect::<Vec<_>>(), other.entities().collect::<Vec<_>>());
+            assert_eq!(seq.total_count, other.total_count);
+        }
+    }
+
+    #[test]
+    fn test_nfd_name_matches_with_original_offsets() {
+        let text = "Payment approved by Jose\u{301} Garci\u{301}a on Monday.";
+        let options = ExtractOptions { normalize_unicode: true, ..Default::default() };
+        let result = extract_with_options(text, &options);
+
+        let person = result.persons.iter()
+            .find(|e| e.value.starts_with("Jose"))
+            .expect("NFD name should be matched after normalization");
+        let start = text.find("Jose").unwrap();
+        assert_eq!(person.start, start);
+        assert_eq!(person.end, text.find(" on").unwrap());
+        assert_eq!(&text[person.start..person.end], person.value);
+
+        // Without normalization the combining mark splits the name
+        let plain = extract_all(text);
+        assert!(!plain.persons.iter().any(|e| e.value.contains("Garci")));
+    }
+
+    #[test]
+    fn test_timeout_returns_partial_results() {
+        let text = "Alpha Bravo Charlie Delta Echo Foxtrot Golf Hotel ".repeat(40_000);
+        let budget = Duration::from_millis(5);
+        let options = ExtractOptions { timeout: Some(budget), ..Default::default() };
+
+        let started = Instant::now();
+        let result = extract_with_options(&text, &options);
+        let elapsed = started.elapsed();
+
+        assert!(result.timed_out);
+        assert!(elapsed < budget + Duration::from_millis(250), "took {:?}", elapsed);
+        assert!(!extract_all("John Smith").timed_out);
+    }
+
+    #[test]
+    fn test_us_zip_plus_four() {
+        let text = "Mail it to 358 El Brillo Way, Palm Beach, FL 33480-4013 by Friday. Invoice 33480 is unrelated.";
+        let result = extract_types(text, &["postal_codes"]);
+        assert_eq!(result.postal_codes.len(), 1);
+        let zip = &result.postal_codes[0];
+        assert_eq!(zip.value, "33480-4013");
+        assert_eq!(zip.metadata.as_ref().unwrap()["country"], "US");
+    }
+
+    #[test]
+    fn test_uk_postcode() {
+        let text = "The London flat at 44 Kinnerton Street, London SW1X 8ES was sold.";
+        let result = extract_all(text);
+        let pc = result.postal_codes.iter().find(|e| e.value == "SW1X 8ES").unwrap();
+        assert_eq!(pc.metadata.as_ref().unwrap()["country"], "GB");
+    }
+
+    #[test]
+    fn test_all_caps_org() {
+        let text = "THIS AGREEMENT is made with ACME HOLDINGS INC and the FBI.";
+        let result = extract_types(text, &["orgs"]);
+        let acme = result.organizations.iter().find(|e| e.value.contains("ACME")).unwrap();
+        assert_eq!(acme.value, "ACME HOLDINGS INC");
+        assert_eq!(acme.metadata.as_ref().unwrap()["display"], "Acme Holdings Inc");
+        assert_eq!(acme.metadata.as_ref().unwrap()["normalized"], "ACME HOLDINGS INC");
+        assert!(!result.organizations.iter().any(|e| e.value == "ACME"));
+        assert!(result.organizations.iter().any(|e| e.value == "FBI"));
+    }
+
+    #[test]
+    fn test_entity_ids_stable_across_documents() {
+        let options = ExtractOptions { assign_ids: true, ..Default::default() };
+        let a = extract_with_options("Jeffrey Epstein arrived first.", &options);
+        let b = extract_with_options("Later that year, Jeffrey  Epstein left for Paris.", &options);
+
+        let id = |r: &ExtractionResult| {
+            r.persons.iter().find(|e| e.value.starts_with("Jeffrey")).unwrap()
+                .metadata.as_ref().unwrap()["entity_id"].clone()
+        };
+        assert_eq!(id(&a), id(&b));
+        assert_eq!(id(&a).len(), 16);
+        assert!(extract_all("Jeffrey Epstein").persons[0].metadata.is_none());
+    }
+
+    #[test]
+    fn test_case_citation() {
+        let text = "See Doe v. Epstein, 123 F.3d 456 (2d Cir. 1997).";
+        let result = extract_types(text, &["citations"]);
+        assert_eq!(result.citations.len(), 1);
+        let c = &result.citations[0];
+        assert_eq!(c.value, "123 F.3d 456");
+        let meta = c.metadata.as_ref().unwrap();
+        assert_eq!(meta["kind"], "case");
+        assert_eq!(meta["volume"], "123");
+        assert_eq!(meta["reporter"], "F.3d");
+        assert_eq!(meta["page"], "456");
+    }
+
+    #[test]
+    fn test_statute_citation() {
+        let text = "Plaintiffs sue under 42 U.S.C. § 1983 and 18 U.S.C. § 1591(a)(1).";
+        let result = extract_all(text);
+        assert_eq!(result.citations.len(), 2);
+        let meta = result.citations[0].metadata.as_ref().unwrap();
+        assert_eq!(meta["kind"], "statute");
+        assert_eq!(meta["title"], "42");
+        assert_eq!(meta["code"], "USC");
+        assert_eq!(meta["section"], "1983");
+        assert_eq!(result.citations[1].metadata.as_ref().unwrap()["section"], "1591(a)(1)");
+    }
+
+    #[test]
+    fn test_batch_isolates_panicking_document() {
+        let docs = ["Jeffrey Epstein met Bill Clinton.", "POISON", "Wire $5,000 to jeff@example.com"];
+        let results = batch_isolated(&docs, |doc| {
+            if *doc == "POISON" {
+                let bytes = "é".as_bytes();
+                let _ = std::str::from_utf8(&bytes[..1]).unwrap();
+            }
+            extract_all(doc)
+        });
+
+        assert_eq!(results.len(), 3);
+        assert!(results[0].as_ref().unwrap().persons.iter().any(|e| e.value == "Jeffrey Epstein"));
+        assert!(results[1].as_ref().unwrap_err().starts_with("extraction panicked"));
+        assert!(!results[2].as_ref().unwrap().emails.is_empty());
+        assert!(extract_batch(&docs).iter().all(|r| r.is_ok()));
+    }
+
+    #[test]
+    fn test_context_only_for_selected_types() {
+        let text = "On March 3, 2021 John Smith emailed jeff@example.com about the deal.";
+        let options = ExtractOptions {
+            context_types: Some(vec![EntityKind::Person, EntityKind::Email]),
+            context_chars: 10,
+            ..Default::default()
+        };
+        let result = extract_with_options(text, &options);
+
+        let person = result.persons.iter().find(|e| e.value == "John Smith").unwrap();
+        assert_eq!(person.context.as_deref(), Some("h 3, 2021 John Smith emailed j"));
+        assert!(result.emails[0].context.is_some());
+        assert!(result.dates.iter().all(|e| e.context.is_none()));
+
+        let only_persons = ExtractOptions { context_types: Some(vec![EntityKind::Person]), ..Default::default() };
+        let result = extract_with_options(text, &only_persons);
+        assert!(result.emails.iter().all(|e| e.context.is_none()));
+        assert!(result.persons.iter().all(|e| e.context.is_some()));
+
+        let none = ExtractOptions { include_context: false, ..only_persons };
+        assert!(extract_with_options(text, &none).entities().all(|e| e.context.is_none()));
+    }
+
+    #[test]
+    fn test_person_strictness() {
+        let text = "United States officials met John Smith on Friday. Board Members declined to comment.";
+        let persons = |strictness| {
+            let options = ExtractOptions { person_strictness: strictness, ..Default::default() };
+            extract_persons(text, &ExtractCtx::new(&options, text))
+        };
+
+        let normal = persons(PersonStrictness::Normal);
+        assert!(normal.iter().any(|e| e.value == "John Smith" && e.confidence == 0.75));
+        assert!(!normal.iter().any(|e| e.value == "United States"));
+        assert!(!normal.iter().any(|e| e.value == "Board Members"));
+
+        let off = persons(PersonStrictness::Off);
+        assert!(off.iter().any(|e| e.value == "United States"));
+    }
+
+    #[test]
+    fn test_person_sentence_start_downranked() {
+        let text = "Garcia Lopez arrived late.";
+        let options = ExtractOptions::default();
+        let normal = extract_persons(text, &ExtractCtx::new(&options, text));
+        assert!(normal[0].confidence < 0.75);
+
+        // Seen capitalized mid-sentence, so the sentence start is confirmed
+        let text = "Garcia Lopez arrived late. We thanked Garcia for waiting.";
+        let confirmed = extract_persons(text, &ExtractCtx::new(&options, text));
+        assert_eq!(confirmed[0].confidence, 0.75);
+
+        let text = "Garcia Lopez arrived late.";
+        let strict = ExtractOptions { person_strictness: PersonStrictness::Strict, ..Default::default() };
+        assert!(extract_persons(text, &ExtractCtx::new(&strict, text)).is_empty());
+    }
+
+    #[test]
+    fn test_age_linked_to_person() {
+        let text = "The witness, John Smith, age 42, lives in Ohio. A 35-year-old clerk testified.";
+        let result = extract_all(text);
+
+        let age = result.ages.iter().find(|e| e.value == "age 42").unwrap();
+        let metadata = age.metadata.as_ref().unwrap();
+        assert_eq!(metadata["age"], "42");
+        assert_eq!(metadata["person_offset"], text.find("John").unwrap().to_string());
+
+        let clerk = result.ages.iter().find(|e| e.value == "35-year-old").unwrap();
+        assert_eq!(clerk.metadata.as_ref().unwrap()["age"], "35");
+        assert!(!clerk.metadata.as_ref().unwrap().contains_key("person_offset"));
+
+        // Too far from the name, even within the sentence
+        let far = extract_all("John Smith, who has lived for many years in a small town, age 42, testified");
+        assert!(!far.ages[0].metadata.as_ref().unwrap().contains_key("person_offset"));
+
+        // Non-ASCII digits are no age, and leave the others be
+        let arabic = extract_types("John Smith, age ٤٢, and a 35-year-old clerk.", &["ages"]);
+        assert!(arabic.errors.is_empty(), "{:?}", arabic.errors);
+        assert_eq!(arabic.ages.iter().map(|e| e.value.as_str()).collect::<Vec<_>>(), ["35-year-old"]);
+    }
+
+    #[test]
+    fn test_date_ranges_with_non_ascii_digits() {
+        let result = extract_all("From 1/5/٢٠٢٠ to 2/7/2021 we met, and again on March 3, 2004.");
+        assert!(result.errors.is_empty(), "{:?}", result.errors);
+        let dates: Vec<&str> = result.dates.iter().map(|e| e.value.as_str()).collect();
+        assert_eq!(dates, ["2/7/2021", "March 3, 2004"]);
+    }
+
+    #[test]
+    fn test_birthdates() {
+        let text = "Jane Doe was born 1970 in Ohio. The filing is dated January 3, 1990.";
+        let result = extract_all(text);
+
+        let born = result.dates.iter().find(|e| e.value == "1970").unwrap();
+        let metadata = born.metadata.as_ref().unwrap();
+        assert_eq!(metadata["kind"], "birthdate");
+        assert_eq!(metadata["person_offset"], "0");
+
+        let filed = result.dates.iter().find(|e| e.value == "January 3, 1990").unwrap();
+        assert!(filed.metadata.is_none());
+
+        let text = "Mary Major (b. March 4, 1961) chairs the board.";
+        let result = extract_types(text, &["dates"]);
+        assert_eq!(result.dates[0].metadata.as_ref().unwrap()["kind"], "birthdate");
+    }
+
+    struct Xyzzy;
+
+    impl EntityExtractor for Xyzzy {
+        fn extract(&self, text: &str) -> Vec<Entity> {
+            text.match_indices("XYZZY")
+                .map(|(start, value)| Entity {
+                    id: 0,
+                    utf16_start: None,
+                    utf16_end: None,
+                    value: value.to_string(),
+                    entity_type: "magic_word".to_string(),
+                    start,
+                    end: start + value.len(),
+                    confidence: 1.0,
+                    context: None,
+                    metadata: None,
+                })
+                .collect()
+        }
+    }
+
+    #[test]
+    fn test_custom_extractor() {
+        let extractor = Extractor::builder().extractor(Xyzzy).build().unwrap();
+        let text = "John Smith said XYZZY twice: XYZZY.";
+
+        for parallelism in [Parallelism::Sequential, Parallelism::Parallel] {
+            let options = ExtractOptions { parallelism, ..Default::default() };
+            let result = extractor.extract_with(text, &options);
+            assert_eq!(result.custom.len(), 2);
+            assert_eq!(result.custom[1].start, text.rfind("XYZZY").unwrap());
+            assert!(result.persons.iter().any(|e| e.value == "John Smith"));
+            assert_eq!(result.total_count, result.entities().count());
+        }
+
+        assert!(extract_all(text).custom.is_empty());
+    }
+
+    struct Panicky;
+
+    impl EntityExtractor for Panicky {
+        fn extract(&self, text: &str) -> Vec<Entity> {
+            panic!("pathological input of {} bytes", text.len())
+        }
+    }
+
+    #[test]
+    fn test_panicking_extractor_keeps_other_types() {
+        let extractor = Extractor::builder().threads(2).extractor(Panicky).extractor(Xyzzy).build().unwrap();
+        let text = "Jeffrey Epstein wired $5 million to jeff@example.com, XYZZY.";
+
+        for parallelism in [Parallelism::Sequential, Parallelism::Parallel] {
+            let result = extractor.extract_with(text, &ExtractOptions { parallelism, ..Default::default() });
+            assert!(result.persons.iter().any(|e| e.value == "Jeffrey Epstein"));
+            assert_eq!(result.emails.len(), 1);
+            assert_eq!(result.custom.len(), 1);
+            assert_eq!(result.errors, vec![format!("custom: extraction panicked: pathological input of {} bytes", text.len())]);
+        }
+        let batch = extractor.extract_batch(&[text]);
+        assert_eq!(batch[0].as_ref().unwrap().errors.len(), 1);
+        assert!(extract_all(text).errors.is_empty());
+    }
+
+    #[test]
+    fn test_validate_offsets_reports_mismatches() {
+        let text = "Please call John Smith at jeff@example.com on 2024-01-15. Café Noir, Zoë Lane.";
+        let mut result = extract_with_options(text, &ExtractOptions { validate_offsets: true, ..Default::default() });
+        assert!(validate_offsets(text, &result).is_ok());
+
+        let person = result.persons.iter_mut().find(|e| e.value == "John Smith").unwrap();
+        person.start += 1;
+        result.emails[0].end = text.len() + 10;
+        let err = validate_offsets(text, &result).unwrap_err();
+        let ExtractError::OffsetMismatch(mismatches) = &err else { panic!("unexpected error {}", err) };
+        assert_eq!(mismatches.len(), 2);
+        assert_eq!(mismatches[0].value, "John Smith");
+        assert_eq!(mismatches[0].found.as_deref(), Some("ohn Smith"));
+        assert_eq!(mismatches[1].found, None);
+        assert!(err.to_string().starts_with("2 entities don't match their offsets: person \"John Smith\""));
+    }
+
+    struct OffByOne;
+
+    impl EntityExtractor for OffByOne {
+        fn extract(&self, text: &str) -> Vec<Entity> {
+            vec![Entity {
+                id: 0,
+                utf16_start: None,
+                utf16_end: None,
+                value: text[..4].to_string(),
+                entity_type: "broken".to_string(),
+                start: 1,
+                end: 5,
+                confidence: 1.0,
+                context: None,
+                metadata: None,
+            }]
+        }
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "broken \"John\" at 1..5 reads \"ohn \"")]
+    fn test_validate_offsets_asserts_in_debug() {
+        let extractor = Extractor::builder().extractor(OffByOne).build().unwrap();
+        extractor.extract_with("John Smith", &ExtractOptions { validate_offsets: true, ..Default::default() });
+    }
+
+    #[test]
+    fn test_acronym_filtering() {
+        let orgs = |text: &str, options: &ExtractOptions| {
+            extract_organizations(text, &ExtractCtx::new(options, text))
+                .into_iter()
+                .map(|e| e.value)
+                .collect::<Vec<_>>()
+        };
+        let defaults = ExtractOptions::default();
+
+        assert!(orgs("THE QUICK BROWN FOX", &defaults).is_empty());
+        assert!(orgs("SECTION IV. Chapter II applies.", &defaults).is_empty());
+
+        // Repeated, or next to org-context words, or defined in parentheses
+        assert_eq!(orgs("ACME shipped late. Later ACME apologized.", &defaults), ["ACME"]);
+        assert_eq!(orgs("Agents from the DEA and officials at FIFA met.", &defaults), ["DEA", "FIFA"]);
+        assert_eq!(orgs("Global Widgets (GWX) reported.", &defaults), ["GWX"]);
+
+        let strict = ExtractOptions {
+            acronyms: AcronymOptions { min_occurrences: 3, stoplist: vec!["acme".to_string()], ..Default::default() },
+            ..Default::default()
+        };
+        assert!(orgs("ACME shipped late. Later ACME said the AXE broke. AXE again.", &strict).is_empty());
+
+        // Once is noise, three times a document's own acronym, unless known
+        let thrice = ExtractOptions { acronyms: AcronymOptions { min_occurrences: 3, ..Default::default() }, ..Default::default() };
+        let text = "FBI agents came. GLOBEX shipped late. GLOBEX apologized. GLOBEX would pay, said the ZQX memo.";
+        assert_eq!(orgs(text, &thrice), ["FBI", "GLOBEX"]);
+        let known = ExtractOptions {
+            acronyms: AcronymOptions { known: vec!["zqx".to_string()], ..thrice.acronyms.clone() },
+            ..Default::default()
+        };
+        assert_eq!(orgs(text, &known), ["FBI", "GLOBEX", "ZQX"]);
+    }
+
+    #[test]
+    fn test_acronym_min_frequency() {
+        let text = "GLOBEX shipped late. HOOLI sued GLOBEX. GLOBEX settled, HOOLI paid, and the ZQX memo leaked.";
+        let orgs = |min_occurrences: usize| -> Vec<String> {
+            let options = ExtractOptions { acronyms: AcronymOptions { min_occurrences, ..Default::default() }, ..Default::default() };
+            let mut orgs: Vec<String> = extract_with_options(text, &options).organizations.into_iter().map(|e| e.value).collect();
+            orgs.sort();
+            orgs
+        };
+        // GLOBEX three times, HOOLI twice, ZQX once: kept at the threshold,
+        // dropped below it
+        assert_eq!(orgs(1), ["GLOBEX", "HOOLI", "ZQX"]);
+        assert_eq!(orgs(2), ["GLOBEX", "HOOLI"]);
+        assert_eq!(orgs(3), ["GLOBEX"]);
+        assert!(orgs(4).is_empty());
+    }
+
+    #[test]
+    fn test_org_suffix_normalization() {
+        for variant in ["Acme Inc", "Acme Inc.", "ACME, Inc.", "Acme Incorporated"] {
+            assert_eq!(canonical_org(variant), "ACME INC", "{}", variant);
+        }
+        assert_eq!(canonical_org("Widget Holdings, L.L.C."), "WIDGET HOLDINGS LLC");
+
+        let text = "Acme Inc sued ACME, Inc. over Acme Incorporated assets.";
+        let result = extract_types(text, &["orgs"]);
+        assert_eq!(result.organizations.len(), 3);
+        for org in &result.organizations {
+            assert_eq!(org.metadata.as_ref().unwrap()["normalized"], "ACME INC", "{}", org.value);
+            assert_eq!(&text[org.start..org.end], org.value);
+        }
+    }
+
+    #[test]
+    fn test_exclude_ranges() {
+        let header = "From: Jane Doe <jane@example.com>, Acme Holdings Inc, 12 Main Street, Springfield";
+        let text = format!("{:-<99}\nJeffrey Epstein wired $5 million; contact jeff@example.com.", header);
+        let options = ExtractOptions { exclude_ranges: vec![(0, 100)], ..Default::default() };
+
+        let all = extract_all(&text);
+        assert!(all.entities().any(|e| e.end <= 100));
+        let result = extract_with_options(&text, &options);
+        assert!(result.entities().all(|e| e.start >= 100));
+        assert_eq!(result.total_count, all.entities().filter(|e| e.start >= 100).count());
+        assert!(result.persons.iter().any(|e| e.value == "Jeffrey Epstein"));
+        assert_eq!(result.emails.len(), 1);
+
+        // Partial overlap drops the entity too
+        let options = ExtractOptions { exclude_ranges: vec![(105, 106)], ..Default::default() };
+        assert!(!extract_with_options(&text, &options).persons.iter().any(|e| e.value == "Jeffrey Epstein"));
+    }
+
+    #[test]
+    fn test_extract_types_matches_extraction() {
+        let text = "Jose\u{301} Garcia wrote jane@example.com.\n[00:01:05] Jeffrey Epstein wired $5 million to jeff@example.com.";
+        // Ids are numbered over the whole result
+        let kept = |entities: &[Entity]| -> Vec<Entity> { entities.iter().map(|e| Entity { id: 0, ..e.clone() }).collect() };
+        for options in [
+            ExtractOptions { exclude_ranges: vec![(0, 20)], ..Default::default() },
+            ExtractOptions { assign_ids: true, ..Default::default() },
+            ExtractOptions { transcript_timestamps: true, ..Default::default() },
+            ExtractOptions { normalize_unicode: true, tag_paragraphs: true, ..Default::default() },
+        ] {
+            let all = extract_with_options(text, &options);
+            let some = extract_types_with(text, &["emails", "amounts"], &options);
+            assert_eq!(kept(&some.emails), kept(&all.emails), "{:?}", options);
+            assert_eq!(kept(&some.amounts), kept(&all.amounts), "{:?}", options);
+            assert!(some.persons.is_empty());
+        }
+    }
+
+    #[test]
+    fn test_scan_within() {
+        let text = "Record 1\nFrom Bill Clinton, $10,000 on May 5, 2004.\nSummary: Jeffrey Epstein wired $5 million to jeff@example.com.\n\n\
+                    Record 2\nGhislaine Maxwell paid $20,000.\nSummary: Sarah Kellen called on March 3, 2004.\n\n";
+        let options = ExtractOptions { scan_within: Some(Regex::new(r"(?s)Summary:.*?\n\n").unwrap()), ..Default::default() };
+        let result = extract_with_options(text, &options);
+
+        let values: Vec<&str> = result.entities().map(|e| e.value.as_str()).collect();
+        for outside in ["Bill Clinton", "$10,000", "May 5, 2004", "Ghislaine Maxwell", "$20,000"] {
+            assert!(!values.contains(&outside), "{outside} in {values:?}");
+        }
+        for inside in ["Jeffrey Epstein", "$5 million", "jeff@example.com", "Sarah Kellen", "March 3, 2004"] {
+            let entity = result.entities().find(|e| e.value == inside).unwrap_or_else(|| panic!("{inside} missing from {values:?}"));
+            assert_eq!(entity.start, text.find(inside).unwrap());
+            assert_eq!(&text[entity.start..entity.end], inside);
+        }
+        assert_eq!(count_all(text, &options).values().sum::<usize>(), result.total_count);
+
+        let json = serde_json::to_string(&options).unwrap();
+        let parsed: ExtractOptions = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed.scan_within.unwrap().as_str(), r"(?s)Summary:.*?\n\n");
+        assert!(serde_json::from_str::<ExtractOptions>(r#"{"scan_within": "("}"#).is_err());
+    }
+
+    #[test]
+    fn test_percentages() {
+        let text = "Revenue rose 12.5% while one-third of staff left; margins fell 4 percent.";
+        let result = extract_types(text, &["percentages"]);
+        let normalized: Vec<(&str, &str)> = result.percentages.iter()
+            .map(|e| (e.value.as_str(), e.metadata.as_ref().unwrap()["normalized"].as_str()))
+            .collect();
+        assert_eq!(normalized, [("12.5%", "0.125"), ("one-third", "0.3333333333333333"), ("4 percent", "0.04")]);
+        assert!(result.percentages.iter().all(|e| e.entity_type == "percent"));
+    }
+
+    #[test]
+    fn test_ratios() {
+        let text = "The board approved a 2:1 ratio split at 10:30 and a ratio of 3:40.";
+        let result = extract_types(text, &["ratios"]);
+        let values: Vec<(&str, &str)> = result.ratios.iter()
+            .map(|e| (e.value.as_str(), e.metadata.as_ref().unwrap()["normalized"].as_str()))
+            .collect();
+        assert_eq!(values, [("2:1", "2"), ("3:40", "0.075")]);
+    }
+
+    #[test]
+    fn test_ratio_cue_window() {
+        let ratios = |text: &str| -> Vec<String> {
+            extract_types(text, &["ratios"]).ratios.into_iter().map(|e| e.value).collect()
+        };
+        // The cue is looked up a few bytes either side, on char boundaries
+        assert_eq!(ratios("€€€€€€ ratio 10:30 held"), ["10:30"]);
+        assert_eq!(ratios("held 10:30 ratio €€€€€€"), ["10:30"]);
+        assert_eq!(ratios("A ratio of 10:30"), ["10:30"]);
+        assert!(ratios("The ratio came up in the meeting at 10:30").is_empty());
+    }
+
+    #[test]
+    fn test_location_types() {
+        let text = "Flights from Miami, FL and Lyon, France reached London and Texas. \
+                    Deliver to 358 El Brillo Way by Friday.";
+        let result = extract_types(text, &["locations"]);
+        let meta = |value: &str| {
+            result.locations.iter().find(|e| e.value == value)
+                .unwrap_or_else(|| panic!("no location {}", value))
+                .metadata.clone().unwrap()
+        };
+
+        let miami = meta("Miami, FL");
+        assert_eq!(miami["location_type"], "city");
+        assert_eq!(miami["city"], "Miami");
+        assert_eq!(miami["state"], "FL");
+
+        let lyon = meta("Lyon, France");
+        assert_eq!((lyon["city"].as_str(), lyon["country"].as_str()), ("Lyon", "France"));
+        assert_eq!(meta("London")["location_type"], "city");
+        assert_eq!(meta("Texas")["location_type"], "state");
+        assert_eq!(meta("358 El Brillo Way")["location_type"], "street_address");
+    }
+
+    #[test]
+    fn test_extract_preview() {
+        let text = format!("Jeffrey Epstein paid $5 million. {}Bill Clinton emailed jeff@example.com.", "~".repeat(200));
+        let options = ExtractOptions::default();
+
+        let preview = extract_preview(&text, 100, &options);
+        assert!(preview.preview_truncated);
+        assert!(preview.persons.iter().any(|e| e.value == "Jeffrey Epstein"));
+        assert!(!preview.amounts.is_empty());
+        assert!(preview.entities().all(|e| e.end <= 100));
+        assert!(preview.emails.is_empty());
+
+        let full = extract_preview(&text, text.len() + 1, &options);
+        assert!(!full.preview_truncated);
+        assert_eq!(full.emails.len(), 1);
+
+        // The cut backs off to a char boundary instead of splitting "é"
+        let preview = extract_preview("José García", 4, &options);
+        assert!(preview.preview_truncated);
+        assert_eq!(preview.total_count, 0);
+    }
+
+    #[test]
+    fn test_redactions() {
+        let text = "Witness [REDACTED] met ██████ ███ in XXXXX on [Redacted - (b)(6)]. Room XX, Exhibit XXX.";
+        let result = extract_types(text, &["redactions"]);
+        let found: Vec<(&str, &str)> = result.redactions.iter()
+            .map(|e| (e.value.as_str(), e.metadata.as_ref().unwrap()["style"].as_str()))
+            .collect();
+        assert_eq!(found, [
+            ("[REDACTED]", "bracketed"),
+            ("██████ ███", "block"),
+            ("XXXXX", "x_run"),
+            ("[Redacted - (b)(6)]", "bracketed"),
+        ]);
+        let block = &result.redactions[1];
+        assert_eq!(block.metadata.as_ref().unwrap()["length"], "10");
+        assert_eq!(&text[block.start..block.end], block.value);
+
+        let lenient = ExtractOptions { redaction_min_x: 2, ..Default::default() };
+        assert_eq!(extract_with_options(text, &lenient).redactions.len(), 6);
+    }
+
+    #[test]
+    fn test_batch_splits_large_document_with_global_offsets() {
+        let text: String = (0..9_000)
+            .map(|i| format!("On March {}, 2019 Jeffrey Epstein wired ${},000 to user{}@example.com via Acme Holdings LLC. ", i % 28 + 1, i, i))
+            .collect();
+        assert!(text.len() > 2 * window::MIN_WINDOW_BYTES);
+
+        let whole = extract_all(&text);
+        let split = extract_batch(&[&text]).pop().unwrap().unwrap();
+        validate_offsets(&text, &split).unwrap();
+
+        let key = |r: &ExtractionResult| {
+            let mut keys: Vec<(String, usize, usize)> = r.entities().map(|e| (e.entity_type.clone(), e.start, e.end)).collect();
+            keys.sort();
+            keys
+        };
+        assert_eq!(key(&split), key(&whole));
+        assert_eq!(split.emails.len(), 9_000);
+        assert_eq!(split.total_count, whole.total_count);
+    }
+
+    #[test]
+    fn test_quantities() {
+        let text = "Seized 5 kg of powder, 200 lbs of cargo, 3.2 liters and a 10-acre lot; 12 fl oz; 5 in the van.";
+        let result = extract_types(text, &["quantities"]);
+        let found: Vec<(&str, &str, &str)> = result.quantities.iter()
+            .map(|e| {
+                let meta = e.metadata.as_ref().unwrap();
+                (e.value.as_str(), meta["unit"].as_str(), meta["si_unit"].as_str())
+            })
+            .collect();
+        assert_eq!(found, vec![
+            ("5 kg", "kg", "kg"),
+            ("200 lbs", "lb", "kg"),
+            ("3.2 liters", "L", "L"),
+            ("10-acre", "acre", "m2"),
+            ("12 fl oz", "fl oz", "L"),
+        ]);
+        let normalized = |i: usize| result.quantities[i].metadata.as_ref().unwrap()["normalized"].parse::<f64>().unwrap();
+        assert_eq!(normalized(0), 5.0);
+        assert!((normalized(1) - 90.718).abs() < 0.01);
+
+        let custom = ExtractOptions {
+            quantity_units: vec![QuantityUnit::new(&["bbl", "barrels"], None, 1.0)],
+            ..Default::default()
+        };
+        let result = extract_with_options("Shipped 1,200 barrels and 5 kg.", &custom);
+        assert_eq!(result.quantities.len(), 1);
+        let meta = result.quantities[0].metadata.as_ref().unwrap();
+        assert_eq!((meta["quantity"].as_str(), meta["unit"].as_str()), ("1200", "bbl"));
+        assert!(!meta.contains_key("normalized"));
+    }
+
+    #[test]
+    fn test_lowercase_org_suffixes() {
+        let text = "We paid Acme inc and later Globex llc; Initech LLC invoiced both.";
+        let result = extract_types(text, &["orgs"]);
+        let found: Vec<(&str, f64)> = result.organizations.iter().map(|e| (e.value.as_str(), e.confidence)).collect();
+
+        assert!(found.contains(&("Acme inc", 0.80 * 0.9)), "{:?}", found);
+        assert!(found.contains(&("Globex llc", 0.80 * 0.9)), "{:?}", found);
+        assert!(found.contains(&("Initech LLC", 0.80)), "{:?}", found);
+        assert_eq!(result.organizations[0].metadata.as_ref().unwrap()["normalized"], "ACME INC");
+        // No bare suffix picked up as an acronym
+        assert!(!found.iter().any(|(v, _)| v.eq_ignore_ascii_case("llc") || v.eq_ignore_ascii_case("inc")));
+        // Ordinary lowercase words are still not suffixes
+        assert!(extract_types("Access limited to Staff company only.", &["orgs"]).organizations.is_empty());
+    }
+
+    #[test]
+    fn test_entity_ids_follow_document_order() {
+        let text = "Jeffrey Epstein, 66, wired $5 million to jeff@example.com on January 15, 2024 via Acme Holdings LLC.";
+        let result = extract_all(text);
+
+        let mut ids: Vec<usize> = result.entities().map(|e| e.id).collect();
+        ids.sort_unstable();
+        assert_eq!(ids, (0..result.total_count).collect::<Vec<_>>());
+
+        let mut by_id: Vec<&Entity> = result.entities().collect();
+        by_id.sort_by_key(|e| e.id);
+        assert!(by_id.windows(2).all(|w| w[0].start <= w[1].start));
+        let again = extract_all(text);
+        assert_eq!(ExtractionResult { processing_time_ms: 0, ..again }, ExtractionResult { processing_time_ms: 0, ..result });
+    }
+
+    #[test]
+    fn test_strict_email() {
+        let text = "Mail a@b.com, first..last@example.com, foo@bar.invalidtld and tips@press.museum.";
+        let strict = ExtractOptions { strict_email: true, ..Default::default() };
+
+        let lenient = extract_with_options(text, &ExtractOptions::default());
+        assert_eq!(lenient.emails.len(), 4);
+
+        let result = extract_with_options(text, &strict);
+        let found: Vec<(&str, f64)> = result.emails.iter().map(|e| (e.value.as_str(), e.confidence)).collect();
+        assert_eq!(found, vec![("a@b.com", 0.95), ("tips@press.museum", 0.75)]);
+    }
+
+    #[test]
+    fn test_merge_halves_matches_single_pass() {
+        let first = "On March 3, 2004 Jeffrey Epstein wired $5 million to Acme Holdings LLC in Palm Beach, Florida. ";
+        let second = "Later Jeffrey Epstein emailed jeff@example.com and paid $250,000 to Initech Corp in New York, NY.";
+        let text = format!("{first}{second}");
+
+        let merged = extract_all(first).merge(extract_all(second), first.len());
+        let single = extract_all(&text);
+        let spans = |result: &ExtractionResult| {
+            let mut spans: Vec<(String, String, usize, usize)> = result.entities()
+                .map(|e| (e.entity_type.clone(), e.value.clone(), e.start, e.end))
+                .collect();
+            spans.sort();
+            spans
+        };
+        assert_eq!(spans(&merged), spans(&single));
+        assert_eq!(merged.total_count, single.total_count);
+        assert_eq!(merged.text_len, text.len());
+        // The second "Jeffrey Epstein" is deduplicated, as in one pass
+        assert_eq!(merged.persons.iter().filter(|e| e.value == "Jeffrey Epstein").count(), 1);
+        let mut ids: Vec<usize> = merged.entities().map(|e| e.id).collect();
+        ids.sort();
+        assert_eq!(ids, (0..merged.total_count).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_quote_handling() {
+        let text = "Jeffrey Epstein said \"Bill Clinton never flew with us\" and “Ghislaine Maxwell booked it” to Sarah Kellen.";
+        let persons = |quote_handling: QuoteHandling| -> Vec<(String, Option<String>)> {
+            let options = ExtractOptions { quote_handling, ..Default::default() };
+            extract_with_options(text, &options).persons.iter()
+                .map(|e| (e.value.clone(), e.metadata.as_ref().and_then(|m| m.get("quoted").cloned())))
+                .collect()
+        };
+        let names = |found: Vec<(String, Option<String>)>| -> Vec<String> { found.into_iter().map(|(name, _)| name).collect() };
+
+        assert_eq!(names(persons(QuoteHandling::Include)), ["Jeffrey Epstein", "Bill Clinton", "Ghislaine Maxwell", "Sarah Kellen"]);
+        assert!(persons(QuoteHandling::Include).iter().all(|(_, quoted)| quoted.is_none()));
+
+        let outside = persons(QuoteHandling::ExcludeQuoted);
+        assert_eq!(names(outside.clone()), ["Jeffrey Epstein", "Sarah Kellen"]);
+        assert!(outside.iter().all(|(_, quoted)| quoted.as_deref() == Some("false")));
+
+        let inside = persons(QuoteHandling::OnlyQuoted);
+        assert_eq!(names(inside.clone()), ["Bill Clinton", "Ghislaine Maxwell"]);
+        assert!(inside.iter().all(|(_, quoted)| quoted.as_deref() == Some("true")));
+
+        // An unmatched quote opens nothing
+        let options = ExtractOptions { quote_handling: QuoteHandling::OnlyQuoted, ..Default::default() };
+        assert!(extract_with_options("He said \"Bill Clinton flew.", &options).persons.is_empty());
+    }
+
+    #[test]
+    fn test_date_ranges_and_durations() {
+        let text = "He worked there from January 2020 to March 2021, then 2019–2021 and 2016-18 in Paris. \
+                    He was jailed for three years and paid for 18 months, at 45 years old, twice a week.";
+        let result = extract_all(text);
+        let ranges: Vec<(&str, &str, &str)> = result.dates.iter()
+            .filter_map(|e| {
+                let metadata = e.metadata.as_ref()?;
+                (metadata.get("kind")? == "range").then(|| (e.value.as_str(), metadata["range_start"].as_str(), metadata["range_end"].as_str()))
+            })
+            .collect();
+        assert_eq!(ranges, vec![
+            ("January 2020 to March 2021", "2020-01-01", "2021-03-31"),
+            ("2019–2021", "2019-01-01", "2021-12-31"),
+            ("2016-18", "2016-01-01", "2018-12-31"),
+        ]);
+        // The range replaces its ends
+        assert!(!result.dates.iter().any(|e| e.value == "January 2020" || e.value == "March 2021"));
+
+        let durations: Vec<(&str, &str, &str)> = result.durations.iter()
+            .map(|e| {
+                let metadata = e.metadata.as_ref().unwrap();
+                (e.value.as_str(), metadata["normalized"].as_str(), metadata["days"].as_str())
+            })
+            .collect();
+        assert_eq!(durations, vec![("three years", "P3Y", "1095"), ("18 months", "P18M", "540")]);
+        assert_eq!(result.durations[0].metadata.as_ref().unwrap()["months"], "36");
+    }
+
+    #[test]
+    fn test_routing_numbers() {
+        let text = "Wire to ABA routing 021000021, account 123456789. \
+                    Invoice 011000015 also matched; EIN 12-3456789 and SSN 123-45-6789 are not routing numbers.";
+        let result = extract_all(text);
+        let found: Vec<(&str, f64)> = result.routing_numbers.iter().map(|e| (e.value.as_str(), e.confidence)).collect();
+        // 123456789 fails the checksum
+        assert_eq!(found, vec![("021000021", 0.95), ("011000015", 0.70)]);
+        let chase = result.routing_numbers[0].metadata.as_ref().unwrap();
+        assert_eq!((chase["district"].as_str(), chase["reserve_bank"].as_str()), ("02", "New York"));
+        assert_eq!(result.eins.len(), 1);
+        assert_eq!(count_all(text, &ExtractOptions::default())["routing_numbers"], 2);
+
+        // Off by one digit: no checksum, no entity; an unassigned prefix neither
+        assert!(extract_types("ABA 021000022", &["routing_numbers"]).routing_numbers.is_empty());
+        assert!(extract_types("ABA 401000010", &["routing_numbers"]).routing_numbers.is_empty());
+        // Nine non-ASCII digits are no routing number
+        assert!(extract_types("ABA ٠٢١٠٠٠٠٢١", &["routing_numbers"]).routing_numbers.is_empty());
+    }
+
+    #[test]
+    fn test_work_titles() {
+        let text = "See United States v. Smith, 123 F.3d 456, and \"In re Acme Holdings.\" The report \"The Panama Papers,\" \
+                    the book *Filthy Rich* and \"a quick note\" were cited. He said \"I will go\" and left.";
+        let result = extract_all(text);
+        let titles: Vec<(&str, &str)> = result.work_titles.iter()
+            .map(|e| (e.value.as_str(), e.metadata.as_ref().unwrap()["kind"].as_str()))
+            .collect();
+        assert_eq!(titles, vec![
+            ("United States v. Smith", "case"),
+            ("In re Acme Holdings", "case"),
+            ("The Panama Papers", "quoted"),
+            ("Filthy Rich", "italic"),
+        ]);
+        let smith = &result.work_titles[0];
+        assert_eq!(&text[smith.start..smith.end], "United States v. Smith");
+        assert_eq!(extract_types(text, &["titles"]).work_titles.len(), 4);
+    }
+
+    #[test]
+    fn test_raw_keeps_every_match() {
+        let text = "Filed 2024-01-15, refiled 2024-01-15.";
+        let patterns = [
+            named("date.loose", r"\d{4}-\d{2}-\d{2}"),
+            named("date.strict", r"\b\d{4}-(?:0[1-9]|1[0-2])-15\b").with_confidence(0.95),
+        ];
+        let options = ExtractOptions { raw: true, ..Default::default() };
+        let ctx = ExtractCtx::new(&options, text);
+        let dates = extract_with_patterns(text, &patterns, EntityKind::Date, 0.85, &ctx);
+        let found: Vec<(usize, &str)> = dates.iter().map(|e| (e.start, e.metadata.as_ref().unwrap()["pattern"].as_str())).collect();
+        assert_eq!(found, vec![(6, "date.loose"), (26, "date.loose"), (6, "date.strict"), (26, "date.strict")]);
+
+        // Repeated values and acronyms without evidence come through too
+        let text = "Jeffrey Epstein met the FBI agent. Jeffrey Epstein left.";
+        let raw = extract_with_options(text, &options);
+        assert_eq!(raw.persons.iter().filter(|e| e.value == "Jeffrey Epstein").count(), 2);
+        assert!(raw.organizations.iter().any(|e| e.value == "FBI"));
+        assert_eq!(extract_all(text).persons.iter().filter(|e| e.value == "Jeffrey Epstein").count(), 1);
+    }
+
+    #[test]
+    fn test_defanged_urls() {
+        let text = "C2 at hxxp://evil[.]com and hxxps[:]//cdn.bad-actor[.]net/a/payload[.]php, beacon to example(dot)com. \
+                    Mail jeff[at]corp[dot]com; v1.2 of https://example.org is clean.";
+        let result = extract_all(text);
+        let urls: Vec<(&str, Option<&str>)> = result.urls.iter()
+            .map(|e| (e.value.as_str(), e.metadata.as_ref().map(|m| m["normalized"].as_str())))
+            .collect();
+        assert_eq!(urls, vec![
+            ("hxxp://evil[.]com", Some("http://evil.com")),
+            ("hxxps[:]//cdn.bad-actor[.]net/a/payload[.]php", Some("https://cdn.bad-actor.net/a/payload.php")),
+            ("example(dot)com", Some("example.com")),
+            ("https://example.org", None),
+        ]);
+        assert!(result.urls[..3].iter().all(|e| e.entity_type == "url" && e.metadata.as_ref().unwrap()["defanged"] == "true"));
+        assert_eq!(count_all(text, &ExtractOptions::default())["urls"], 4);
+
+        // Letters only case-fold to ASCII ones; "ſ" (long s) is no "s"
+        let result = extract_all("see ſſ[.]com now, or https://example.org");
+        assert!(result.errors.is_empty(), "{:?}", result.errors);
+        assert_eq!(result.urls.iter().map(|e| e.value.as_str()).collect::<Vec<_>>(), ["https://example.org"]);
+    }
+
+    #[test]
+    fn test_obfuscated_emails() {
+        let text = "Write to jeff [at] example [dot] com or a (at) b.com, or JANE at MAIL dot ORG. \
+                    She arrived at example.com and met him at noon.";
+        let result = extract_all(text);
+        let found: Vec<(&str, &str, f64)> = result.emails.iter()
+            .map(|e| (e.value.as_str(), e.metadata.as_ref().unwrap()["normalized"].as_str(), e.confidence))
+            .collect();
+        assert_eq!(found, vec![
+            ("jeff [at] example [dot] com", "jeff@example.com", 0.75),
+            ("a (at) b.com", "a@b.com", 0.75),
+            ("JANE at MAIL dot ORG", "jane@mail.org", 0.6),
+        ]);
+        assert_eq!(count_all(text, &ExtractOptions::default())["emails"], 3);
+
+        // A bare "at" with only a bracketed dot
+        let text = "Write to jeff at example [dot] com today.";
+        let result = extract_all(text);
+        assert_eq!(result.emails.len(), 1);
+        assert_eq!(result.emails[0].metadata.as_ref().unwrap()["normalized"], "jeff@example.com");
+        assert_eq!(count_all(text, &ExtractOptions::default())["emails"], 1);
+    }
+
+    #[test]
+    fn test_phone_extensions_and_international() {
+        let text = "Call (212) 555-1234 ext 56 or +44 20 7946 0958, in Paris +33 1 23 45 67 89 x12. \
+                    Not +44 20 7946 09 nor +999 1234 5678.";
+        let result = extract_all(text);
+        let found: Vec<(&str, &str, Option<&str>, Option<&str>)> = result.phones.iter()
+            .map(|e| {
+                let metadata = e.metadata.as_ref().unwrap();
+                (e.value.as_str(), metadata["normalized"].as_str(), metadata.get("ext").map(String::as_str), metadata.get("country").map(String::as_str))
+            })
+            .collect();
+        assert_eq!(found, vec![
+            ("(212) 555-1234 ext 56", "+12125551234", Some("56"), None),
+            ("+44 20 7946 0958", "+442079460958", None, Some("GB")),
+            ("+33 1 23 45 67 89 x12", "+33123456789", Some("12"), Some("FR")),
+        ]);
+
+        let trunk = extract_all("Tel. +44 (0)20 7946 0958.");
+        assert_eq!(trunk.phones[0].metadata.as_ref().unwrap()["normalized"], "+442079460958");
+        assert_eq!(count_all(text, &ExtractOptions::default())["phones"], 3);
+    }
+
+    #[test]
+    fn test_count_all_matches_extraction() {
+        let text = "Jeffrey Epstein met Bill Clinton in Palm Beach, Florida on March 3, 2004. \
+                    He wired $5 million and $5 million again to Acme Holdings LLC (see 42 U.S.C. § 1983). \
+                    Call 555-123-4567, mail jeff@example.com or visit https://example.com; 12.5% of 5 kg [REDACTED].";
+        for options in [
+            ExtractOptions::default(),
+            ExtractOptions { exclude_ranges: vec![(0, 30)], strict_email: true, ..Default::default() },
+            ExtractOptions { email_in_url: EmailInUrl::Both, raw: true, ..Default::default() },
+        ] {
+            assert_counts_match(text, &options);
+        }
+
+        // The options and passes that work on the whole result
+        assert_counts_match("John Smith, aka Jack, met Acme Corp.", &ExtractOptions::default());
+        let text = "Jeffrey Epstein met Acme Corp. \"Bill Clinton\" wrote jeff@example.com.\n\n\
+                    ```\nJohn Smith = 1\n```\nLater Epstein paid $5 million.";
+        for options in [
+            ExtractOptions::default(),
+            ExtractOptions { denylist: HashSet::from(["jeff@example.com".to_string()]), ..Default::default() },
+            ExtractOptions { quote_handling: QuoteHandling::ExcludeQuoted, ..Default::default() },
+            ExtractOptions { quote_handling: QuoteHandling::OnlyQuoted, ..Default::default() },
+            ExtractOptions { coref: true, skip_code_blocks: true, ..Default::default() },
+            ExtractOptions { transcript_timestamps: true, ..Default::default() },
+        ] {
+            assert_counts_match(text, &options);
+        }
+    }
+
+    fn assert_counts_match(text: &str, options: &ExtractOptions) {
+        let result = extract_with_options(text, options);
+        let counts = count_all(text, options);
+        for &kind in EntityKind::ALL {
+            let expected = result.entities().filter(|e| e.entity_type == kind.as_str()).count();
+            assert_eq!(counts[kind.selectors()[0]], expected, "{:?} under {:?}", kind, options);
+        }
+        assert_eq!(counts.values().sum::<usize>(), result.total_count);
+    }
+
+    #[test]
+    fn test_locale_thousands_separators() {
+        let amounts = |text: &str, locale: NumberLocale| -> Vec<(String, String)> {
+            let options = ExtractOptions { number_locale: locale, ..Default::default() };
+            extract_with_options(text, &options).amounts.iter()
+                .map(|e| (e.value.clone(), e.metadata.as_ref().unwrap()["normalized"].clone()))
+                .collect()
+        };
+
+        let text = "In 2019 the fund paid 1 234 567,89 EUR to the trust.";
+        assert_eq!(amounts(text, NumberLocale::European), vec![("1 234 567,89 EUR".to_string(), "1234567.89".to_string())]);
+        assert_eq!(
+            amounts("Wired 1'234'567.89 CHF, then CHF 2'500.", NumberLocale::Swiss),
+            vec![("1'234'567.89 CHF".to_string(), "1234567.89".to_string()), ("CHF 2'500".to_string(), "2500".to_string())],
+        );
+        // The following number is not a fourth group
+        assert_eq!(amounts("EUR 1 234 and 5 678 later", NumberLocale::European)[0].0, "EUR 1 234");
+        // Off by default
+        assert!(amounts("1'234'567.89 CHF", NumberLocale::English).is_empty());
+    }
+
+    #[test]
+    fn test_paragraph_tags() {
+        let text = "\r\nJeffrey Epstein flew to Paris.\r\n\r\n  \r\n\r\nHe wired $5 million\r\nto jeff@example.com.\n\nCall 555-123-4567.";
+        let options = ExtractOptions { tag_paragraphs: true, ..Default::default() };
+        let result = extract_with_options(text, &options);
+        let paragraph = |value: &str| {
+            let entity = result.entities().find(|e| e.value == value).unwrap();
+            entity.metadata.as_ref().unwrap()["paragraph"].clone()
+        };
+
+        assert_eq!(paragraph("Jeffrey Epstein"), "0");
+        assert_eq!(paragraph("$5 million"), "1");
+        assert_eq!(paragraph("jeff@example.com"), "1");
+        assert_eq!(paragraph("555-123-4567"), "2");
+
+        let options = ExtractOptions { tag_paragraphs: true, paragraph_delimiter: Some("---".to_string()), ..Default::default() };
+        let result = extract_with_options("Jeffrey Epstein\n\n---\njeff@example.com", &options);
+        assert_eq!(result.emails[0].metadata.as_ref().unwrap()["paragraph"], "1");
+        assert!(extract_all(text).emails[0].metadata.is_none());
+    }
+
+    #[test]
+    fn test_eins() {
+        let text = "Acme Holdings LLC (EIN: 12-3456789) filed the return.\n\
+                    Unassigned prefix, Tax ID 07-1234567.\n\
+                    Federal ID on the form: 99-12-3456789-0, SSN 123-45-6789.";
+        let result = extract_types(text, &["eins"]);
+        let found: Vec<(&str, &str)> = result.eins.iter()
+            .map(|e| (e.value.as_str(), e.metadata.as_ref().unwrap()["prefix"].as_str()))
+            .collect();
+        assert_eq!(found, vec![("12-3456789", "12")]);
+
+        assert!(extract_types("Wire ref 12-3456789 received.", &["eins"]).eins.is_empty());
+    }
+
+    #[test]
+    fn test_disabled_patterns() {
+        let text = "The FBI and the FBI interviewed staff at Acme Holdings LLC on 15.01.2024.";
+        let orgs = |options: &ExtractOptions| -> Vec<String> {
+            extract_with_options(text, options).organizations.iter().map(|e| e.value.clone()).collect()
+        };
+        assert!(orgs(&ExtractOptions::default()).contains(&"FBI".to_string()));
+
+        let options = ExtractOptions {
+            disabled_patterns: ["org.acronym", "date.eu"].iter().map(|s| s.to_string()).collect(),
+            ..Default::default()
+        };
+        let found = orgs(&options);
+        assert!(!found.contains(&"FBI".to_string()), "{:?}", found);
+        assert!(found.contains(&"Acme Holdings LLC".to_string()), "{:?}", found);
+        assert!(extract_with_options(text, &options).dates.is_empty());
+
+        let names = builtin_pattern_names();
+        assert!(names.contains(&"org.acronym") && names.contains(&"date.eu"));
+        assert_eq!(names.iter().collect::<HashSet<_>>().len(), names.len());
+    }
+
+    #[test]
+    fn test_email_header_roles() {
+        let text = "From: Jeffrey Epstein <jeff@example.com>\n\
+                    To: ghislaine@example.com,\n \
+                    \tjohn.smith@example.org\n\
+                    Cc: legal@acme.com\n\
+                    Subject: Wire to jane@example.net\n\
+                    \n\
+                    Forward this to bob@example.com.";
+        let result = extract_types(text, &["emails"]);
+        let roles: Vec<(&str, Option<&str>)> = result.emails.iter()
+            .map(|e| (e.value.as_str(), e.metadata.as_ref().map(|m| m["role"].as_str())))
+            .collect();
+        assert_eq!(roles, vec![
+            ("jeff@example.com", Some("from")),
+            ("ghislaine@example.com", Some("to")),
+            ("john.smith@example.org", Some("to")),
+            ("legal@acme.com", Some("cc")),
+            ("jane@example.net", None),
+            ("bob@example.com", None),
+        ]);
+    }
+
+    #[test]
+    fn test_same_span_keeps_highest_confidence() {
+        let text = "Filed 2024-01-15, amended 2024-02-01.";
+        let patterns = [
+            named("date.loose", r"\d{4}-\d{2}-\d{2}"),
+            named("date.strict", r"\b\d{4}-(?:0[1-9]|1[0-2])-15\b").with_confidence(0.95),
+        ];
+        let options = ExtractOptions::default();
+        let ctx = ExtractCtx::new(&options, text);
+        let dates = extract_with_patterns(text, &patterns, EntityKind::Date, 0.85, &ctx);
+
+        assert_eq!(dates.len(), 2);
+        assert_eq!((dates[0].value.as_str(), dates[0].confidence), ("2024-01-15", 0.95));
+        assert_eq!(dates[0].metadata.as_ref().unwrap()["patterns"], "date.loose,date.strict");
+        assert_eq!((dates[1].value.as_str(), dates[1].confidence), ("2024-02-01", 0.85));
+        assert!(dates[1].metadata.is_none());
+
+        // Built-in patterns carry their own confidence over the date base
+        let result = extract_types("Paid 2024-01-15, settled January 2024.", &["dates"]);
+        let found: Vec<(&str, f64)> = result.dates.iter().map(|e| (e.value.as_str(), e.confidence)).collect();
+        assert_eq!(found, vec![("2024-01-15", 0.95), ("January 2024", 0.75)]);
+    }
+
+    #[test]
+    fn test_medical() {
+        let text = "Diagnosis: E11.9, type 2 diabetes; see also E95.1. Engine model B52. \
+                    Prescribed Xanax 1mg and oxycodone; denied taking ALPRAZOLAM-free aspirinate.";
+        let result = extract_medical(text);
+
+        let codes: Vec<(&str, &str)> = result.icd_codes.iter()
+            .map(|e| (e.value.as_str(), e.metadata.as_ref().unwrap()["category"].as_str()))
+            .collect();
+        assert_eq!(codes, vec![("E11.9", "E11")]);
+        assert_eq!(result.icd_codes[0].metadata.as_ref().unwrap()["chapter"], "Endocrine, nutritional and metabolic diseases");
+
+        let drugs: Vec<(&str, &str)> = result.drugs.iter()
+            .map(|e| (e.value.as_str(), e.metadata.as_ref().unwrap()["generic"].as_str()))
+            .collect();
+        assert_eq!(drugs, vec![("Xanax", "alprazolam"), ("oxycodone", "oxycodone"), ("ALPRAZOLAM", "alprazolam")]);
+        assert!(result.persons.is_empty() && result.quantities.is_empty());
+
+        let options = ExtractOptions {
+            drugs: vec![DrugName::new("ivermectin", &["Stromectol"], None)],
+            ..Default::default()
+        };
+        let custom = extract_types_with("Shipped Stromectol, not Xanax.", &["drugs"], &options);
+        assert_eq!(custom.drugs.len(), 1);
+        assert_eq!(custom.drugs[0].metadata.as_ref().unwrap()["generic"], "ivermectin");
+    }
+
+    #[test]
+    fn test_output_is_reproducible() {
+        let text = "On 2024-01-15 Jeffrey Epstein wired $5 million (about 12% of 40 kg of gold) to \
+                    Acme Holdings LLC in New York, NY 10022. Tax ID 12-3456789. Diagnosis E11.9, \
+                    prescribed Xanax. Contact jeff@example.com or (212) 555-0143. See 18 U.S.C. § 1591.";
+        let serialize = || {
+            let mut result = extract_all(text);
+            result.processing_time_ms = 0;
+            serde_json::to_string(&result).unwrap()
+        };
+        let first = serialize();
+        assert!(first.contains("\"metadata\""));
+        for _ in 0..50 {
+            assert_eq!(serialize(), first);
+        }
+    }
+
+    #[test]
+    fn test_coordinates() {
+        let text = "Dropped at 40.7128, -74.0060 then flew to 18°20'N, 64°55'W (Little St. James, \
+                    18°18'02.5\"N 64°49'33\"W). Readings 95.1234, 10.0000, build 1.2.345, 6.789 and 12.5, 19.9 are not.";
+        let result = extract_types(text, &["coordinates"]);
+        let found: Vec<(&str, &str, &str)> = result.coordinates.iter()
+            .map(|e| {
+                let meta = e.metadata.as_ref().unwrap();
+                (e.value.as_str(), meta["latitude"].as_str(), meta["longitude"].as_str())
+            })
+            .collect();
+        assert_eq!(found, vec![
+            ("40.7128, -74.0060", "40.712800", "-74.006000"),
+            ("18°20'N, 64°55'W", "18.333333", "-64.916667"),
+            ("18°18'02.5\"N 64°49'33\"W", "18.300694", "-64.825833"),
+        ]);
+    }
+
+    #[test]
+    fn test_person_coref() {
+        let text = "Jeffrey Epstein met Ghislaine Maxwell in Paris. Days later, Epstein flew home \
+                    and Ghislaine stayed. Jeffrey Epstein denied it.";
+        let options = ExtractOptions { coref: true, ..Default::default() };
+        let result = extract_with_options(text, &options);
+
+        let linked: Vec<(&str, usize, &str)> = result.persons.iter()
+            .filter_map(|p| {
+                let meta = p.metadata.as_ref()?;
+                Some((p.value.as_str(), meta["coref"].parse().unwrap(), meta["resolved_name"].as_str()))
+            })
+            .collect();
+        assert_eq!(linked, vec![("Epstein", 0, "Jeffrey Epstein"), ("Ghislaine", 20, "Ghislaine Maxwell")]);
+        assert_eq!(&text[linked[0].1..linked[0].1 + 15], "Jeffrey Epstein");
+
+        assert!(extract_all(text).persons.iter().all(|p| p.value.contains(' ')));
+    }
+
+    #[test]
+    fn test_utf16_offsets() {
+        let text = "👍 Jeffrey Epstein wired $5 million; 💸 to jeff@example.com";
+        let options = ExtractOptions { utf16_offsets: true, ..Default::default() };
+        let result = extract_with_options(text, &options);
+        let utf16: Vec<u16> = text.encode_utf16().collect();
+
+        assert!(result.total_count >= 3);
+        for entity in result.entities() {
+            let (start, end) = (entity.utf16_start.unwrap(), entity.utf16_end.unwrap());
+            assert_eq!(String::from_utf16(&utf16[start..end]).unwrap(), entity.value);
+        }
+        let person = &result.persons[0];
+        // The emoji is one char but two UTF-16 units and four bytes
+        assert_eq!((person.start, person.utf16_start, person.utf16_end), (5, Some(3), Some(18)));
+
+        assert!(extract_all(text).entities().all(|e| e.utf16_start.is_none()));
+    }
+
+    #[test]
+    fn test_denylist() {
+        let text = "Acme Corp paid Jeffrey Epstein. ACME   CORP staff met Initech Corp in New York.";
+        assert!(extract_all(text).organizations.iter().any(|e| e.value == "Acme Corp"));
+
+        let options = ExtractOptions {
+            denylist: ["acme corp", "New York"].iter().map(|s| s.to_string()).collect(),
+            ..Default::default()
+        };
+        let result = extract_with_options(text, &options);
+        let orgs: Vec<&str> = result.organizations.iter().map(|e| e.value.as_str()).collect();
+        assert!(!orgs.iter().any(|v| v.eq_ignore_ascii_case("acme corp") || v.starts_with("ACME")), "{:?}", orgs);
+        assert!(orgs.contains(&"Initech Corp"));
+        assert!(result.locations.iter().all(|e| e.value != "New York"));
+        assert!(result.persons.iter().any(|e| e.value == "Jeffrey Epstein"));
+
+        let typed = extract_types_with(text, &["orgs"], &options);
+        assert!(typed.organizations.iter().all(|e| e.value != "Acme Corp"));
+    }
+
+    #[test]
+    fn test_references() {
+        let text = "As shown in Exhibit 12 and Item 4(b), the wire (¶ 37) preceded Section 3.2.";
+        let result = extract_types(text, &["references"]);
+        let found: Vec<(&str, &str, &str)> = result.references.iter()
+            .map(|e| {
+                let meta = e.metadata.as_ref().unwrap();
+                (e.value.as_str(), meta["kind"].as_str(), meta["number"].as_str())
+            })
+            .collect();
+        assert_eq!(found, vec![
+            ("Exhibit 12", "exhibit", "12"),
+            ("Item 4(b)", "item", "4(b)"),
+            ("¶ 37", "paragraph", "37"),
+            ("Section 3.2", "section", "3.2"),
+        ]);
+        assert!(extract_all("Exhibit A was sealed.").references.iter().any(|e| e.value == "Exhibit A"));
+    }
+
+    #[test]
+    fn test_alias_links() {
+        let text = "John Smith, aka Jack, wired the funds. Acme Corp, f/k/a Widgets Inc, was sued.";
+        let result = extract_all(text);
+        let alias = |value: &str, kind: &[Entity]| kind.iter()
+            .find(|e| e.value == value)
+            .and_then(|e| e.metadata.as_ref()?.get("alias_of").cloned());
+
+        assert_eq!(alias("John Smith", &result.persons), None);
+        assert_eq!(alias("Jack", &result.persons), Some("0".to_string()));
+        let acme = result.organizations.iter().find(|e| e.value == "Acme Corp").unwrap();
+        assert_eq!(alias("Widgets Inc", &result.organizations), Some(acme.start.to_string()));
+        assert!(extract_all("John Smith met Jack.").persons.iter().all(|e| e.value != "Jack"));
+    }
+
+    #[test]
+    fn test_sniff_skips_impossible_kinds() {
+        let text = "Jeffrey Epstein met the board and said nothing about money.";
+        let sniff = Sniff::new(text);
+        assert!(!sniff.admits(EntityKind::Amount));
+        assert!(!sniff.admits(EntityKind::Email));
+        assert!(sniff.admits(EntityKind::Person));
+        let result = extract_all(text);
+        assert!(result.amounts.is_empty());
+        assert!(result.persons.iter().any(|e| e.value == "Jeffrey Epstein"));
+
+        // Non-ASCII digits still count: `\d` matches them
+        assert!(Sniff::new("paid $\u{0665}\u{0660}").admits(EntityKind::Amount));
+    }
+
+    #[test]
+    fn test_isbns() {
+        let text = "Cited: ISBN 978-0-306-40615-7, ISBN-10: 0-306-40615-2 and ISBN 978-0-306-40615-8.";
+        let result = extract_identifiers(text);
+        let found: Vec<(&str, &str)> = result.isbns.iter()
+            .map(|e| (e.value.as_str(), e.metadata.as_ref().unwrap()["normalized"].as_str()))
+            .collect();
+        // The last one's check digit is wrong
+        assert_eq!(found, vec![("978-0-306-40615-7", "9780306406157"), ("0-306-40615-2", "0306406152")]);
+        assert_eq!(result.isbns[1].metadata.as_ref().unwrap()["format"], "isbn10");
+
+        // A bare ten-digit number needs the label, even with a valid check digit
+        assert!(extract_identifiers("Call 0306406152 today.").isbns.is_empty());
+    }
+
+    #[test]
+    fn test_dois() {
+        let text = "See doi:10.1038/nphys1170. Also (https://doi.org/10.1000/ABC(2)x).";
+        let result = extract_identifiers(text);
+        let found: Vec<(&str, &str)> = result.dois.iter()
+            .map(|e| (e.value.as_str(), e.metadata.as_ref().unwrap()["normalized"].as_str()))
+            .collect();
+        assert_eq!(found, vec![("10.1038/nphys1170", "10.1038/nphys1170"), ("10.1000/ABC(2)x", "10.1000/abc(2)x")]);
+        assert_eq!(result.dois[0].metadata.as_ref().unwrap()["prefix"], "10.1038");
+    }
+
+    #[test]
+    fn test_emails_inside_urls() {
+        let text = "Open https://site.com/?email=a@b.com or write to mailto:jeff@example.com, \
+                    or https://x.com/share?u=mailto:c@d.com.";
+        let result = extract_all(text);
+        let emails: Vec<&str> = result.emails.iter().map(|e| e.value.as_str()).collect();
+        assert_eq!(emails, vec!["jeff@example.com", "c@d.com"]);
+        assert_eq!(result.urls.len(), 2);
+        assert_eq!(count_all(text, &ExtractOptions::default())["emails"], 2);
+
+        let both = ExtractOptions { email_in_url: EmailInUrl::Both, ..Default::default() };
+        assert_eq!(extract_with_options(text, &both).emails.len(), 3);
+        assert_eq!(extract_types_with(text, &["emails", "urls"], &ExtractOptions::default()).emails.len(), 2);
+    }
+
+    #[test]
+    fn test_warmup_speeds_up_first_extraction() {
+        // Each measurement needs a process where nothing has compiled the
+        // patterns yet, so the test runs itself in two children
+        const MODE: &str = "L_EXTRACT_WARMUP_MODE";
+        let name = "tests::test_warmup_speeds_up_first_extraction";
+        match std::env::var(MODE).as_deref() {
+            Ok(mode) => {
+                if mode == "warm" {
+                    warmup();
+                }
+                let start = Instant::now();
+                extract_all("Jeffrey Epstein wired $5 million to jeff@example.com on January 15, 2024.");
+                println!("elapsed_us={}", start.elapsed().as_micros());
+            }
+            Err(_) => {
+                let first_extraction = |mode: &str| -> u128 {
+                    let output = std::process::Command::new(std::env::current_exe().unwrap())
+                        .args(["--exact", name, "--nocapture", "--test-threads=1"])
+                        .env(MODE, mode)
+                        .output()
+                        .unwrap();
+                    assert!(output.status.success());
+                    String::from_utf8_lossy(&output.stdout).lines()
+                        .find_map(|line| line.split("elapsed_us=").nth(1)?.trim().parse().ok())
+                        .unwrap()
+                };
+                let (cold, warm) = (first_extraction("cold"), first_extraction("warm"));
+                assert!(warm * 3 < cold, "warm {warm} µs, cold {cold} µs");
+            }
+        }
+    }
+
+    #[test]
+    fn test_skip_code_blocks() {
+        let text = "Jeffrey Epstein wrote the handler.\n\n\
+                    ```rust\n\
+                    let FooBar = Widget Corp::new(\"https://example.com\", \"dev@example.com\");\n\
+                    ```\n\n    \
+                    Baz Qux = Acme Inc\n\n\
+                    It was reviewed by Jane Doe.";
+        let options = ExtractOptions { skip_code_blocks: true, ..Default::default() };
+        let result = extract_with_options(text, &options);
+        let persons: Vec<&str> = result.persons.iter().map(|e| e.value.as_str()).collect();
+        assert_eq!(persons, vec!["Jeffrey Epstein", "Jane Doe"]);
+        assert!(result.organizations.is_empty());
+        assert_eq!(result.urls.len(), 1);
+        assert_eq!(&text[result.emails[0].start..result.emails[0].end], "dev@example.com");
+        assert_eq!(count_all(text, &options)["persons"], 2);
+
+        let prose = extract_all(text);
+        assert!(prose.persons.iter().any(|e| e.value.contains("Baz Qux")));
+        assert!(prose.organizations.iter().any(|e| e.value.contains("Widget Corp")));
+    }
+
+    #[test]
+    fn test_quarters_and_fiscal_years() {
+        let text = "Revenue fell in Q3 2024 and the third quarter of 2023, against guidance for FY2023 and Q1 FY25.";
+        let result = extract_types(text, &["dates"]);
+        let period = |value: &str| result.dates.iter()
+            .find(|e| e.value == value)
+            .unwrap_or_else(|| panic!("{value} not in {:?}", result.dates))
+            .metadata.clone().unwrap();
+        assert_eq!(result.dates.len(), 4);
+        assert_eq!(period("Q3 2024")["period"], "2024-Q3");
+        assert_eq!(period("third quarter of 2023")["period"], "2023-Q3");
+        assert_eq!(period("FY2023")["period"], "FY2023");
+        assert_eq!(period("Q1 FY25")["period"], "FY2025-Q1");
+
+        let q3 = period("Q3 2024");
+        assert_eq!((q3["range_start"].as_str(), q3["range_end"].as_str()), ("2024-07-01", "2024-09-30"));
+        let fy = period("FY2023");
+        assert_eq!(fy["kind"], "fiscal_year");
+        assert!(!fy.contains_key("range_start"));
+
+        // Years in non-ASCII digits are no period, and leave other dates be
+        let arabic = extract_types("Q3 ٢٠٢٤ and FY ٢٠٢٣ closed on January 15, 2024.", &["dates"]);
+        assert!(arabic.errors.is_empty(), "{:?}", arabic.errors);
+        assert_eq!(arabic.dates.iter().map(|e| e.value.as_str()).collect::<Vec<_>>(), ["January 15, 2024"]);
+    }
+
+    #[test]
+    fn test_tag_scripts() {
+        let text = "Иван Петров met John Smith and paid $5,000.";
+        let options = ExtractOptions { tag_scripts: true, ..Default::default() };
+        let result = extract_with_options(text, &options);
+        let script = |value: &str| result.entities()
+            .find(|e| e.value == value)
+            .map(|e| e.metadata.as_ref().unwrap()["script"].as_str())
+            .unwrap_or_else(|| panic!("no {value}"));
+        assert_eq!(script("Иван Петров"), "Cyrillic");
+        assert_eq!(script("John Smith"), "Latin");
+        assert_eq!(script("$5,000"), "Common");
+        assert!(extract_all(text).persons.iter().all(|e| e.metadata.is_none()));
+
+        assert_eq!(dominant_script("東京タワー"), "CJK");
+        assert_eq!(dominant_script("محمد علي"), "Arabic");
+        assert_eq!(dominant_script("Müller-Lüdenscheidt"), "Latin");
+    }
+
+    #[test]
+    fn test_repeat_decay() {
+        let text = "Jeffrey Epstein met Bill Clinton. Then, Jeffrey Epstein left and Jeffrey  Epstein returned.";
+        let options = ExtractOptions { raw: true, repeat_decay: Some(0.5), ..Default::default() };
+        let result = extract_with_options(text, &options);
+        let epstein: Vec<f64> = result.persons.iter()
+            .filter(|e| e.normalized_value() == "jeffrey epstein")
+            .map(|e| e.confidence)
+            .collect();
+        assert_eq!(epstein.len(), 3);
+        assert!(epstein[1] < epstein[0]);
+        assert_eq!(epstein[1], epstein[0] * 0.5);
+        assert_eq!(epstein[2], epstein[0] * 0.25);
+        let clinton = result.persons.iter().find(|e| e.value == "Bill Clinton").unwrap();
+        assert_eq!(clinton.confidence, extract_with_options(text, &ExtractOptions { raw: true, ..Default::default() })
+            .persons.iter().find(|e| e.value == "Bill Clinton").unwrap().confidence);
+
+        // No decay goes below the floor
+        let emails = "jeff@example.com ".repeat(6);
+        let options = ExtractOptions { repeat_decay: Some(0.1), ..Default::default() };
+        let decayed = extract_with_options(&emails, &options);
+        assert_eq!(decayed.emails.len(), 6);
+        assert!(decayed.emails[1..].iter().all(|e| e.confidence == REPEAT_DECAY_FLOOR));
+    }
+
+    #[test]
+    fn test_assertion_cues() {
+        let text = "Jeffrey Epstein did not meet Bill Clinton. He allegedly paid $5M in 2002. \
+                    Prince Andrew flew to London.";
+        let options = ExtractOptions { tag_assertions: true, ..Default::default() };
+        let result = extract_with_options(text, &options);
+        let flags = |value: &str| {
+            let metadata = result.entities().find(|e| e.value == value).unwrap().metadata.as_ref().unwrap();
+            (metadata["negated"].as_str(), metadata["hedged"].as_str())
+        };
+        assert_eq!(flags("Bill Clinton"), ("true", "false"));
+        assert_eq!(flags("Jeffrey Epstein"), ("false", "false"));
+        assert_eq!(flags("$5M"), ("false", "true"));
+        // The period ends the cue's clause
+        assert_eq!(flags("Prince Andrew"), ("false", "false"));
+
+        let cues = AssertionCues { negation: vec!["refused to".to_string()], ..Default::default() };
+        let options = ExtractOptions { tag_assertions: true, assertion_cues: cues, ..Default::default() };
+        let result = extract_with_options("She refused to name Bill Clinton but did not name Prince Andrew.", &options);
+        let negated: Vec<&str> = result.persons.iter().map(|e| e.metadata.as_ref().unwrap()["negated"].as_str()).collect();
+        assert_eq!(negated, vec!["true", "false"]);
+    }
+
+    #[test]
+    fn test_transcript_timestamps() {
+        let text = "[00:12:34] Jeffrey Epstein said he flew to Palm Beach.\n\
+                    12:05 Ghislaine Maxwell paid $5,000.\n\
+                    Off the record, Bill Clinton.";
+        let options = ExtractOptions { transcript_timestamps: true, ..Default::default() };
+        let result = extract_with_options(text, &options);
+        let stamp = |value: &str| {
+            let entity = result.entities().find(|e| e.value == value).unwrap();
+            entity.metadata.as_ref().and_then(|m| m.get("timestamp")).map(String::as_str)
+        };
+        assert_eq!(stamp("Jeffrey Epstein"), Some("754"));
+        assert_eq!(stamp("Ghislaine Maxwell"), Some("725"));
+        assert_eq!(stamp("$5,000"), Some("725"));
+        assert_eq!(stamp("Bill Clinton"), None);
+        assert!(result.entities().all(|e| !e.value.contains(':')));
+
+        // 10:75 is no timestamp
+        let clock = extract_with_options("10:75 Jeffrey Epstein", &options);
+        assert!(clock.persons[0].metadata.as_ref().is_none_or(|m| !m.contains_key("timestamp")));
+
+        // Non-ASCII digits are no timestamp either
+        let arabic = extract_with_options("[٠٠:١٢] Jeffrey Epstein said hello", &options);
+        assert!(arabic.persons[0].metadata.as_ref().is_none_or(|m| !m.contains_key("timestamp")));
+    }
+
+    #[test]
+    fn test_line_columns() {
+        let text = "Flight log\r\nPassengers listed below.\r\n  Jeffrey Epstein and Jane Doe, March 3, 2005\nEnd";
+        let options = ExtractOptions { line_columns: true, ..Default::default() };
+        let result = extract_with_options(text, &options);
+
+        let epstein = result.persons.iter().find(|e| e.value == "Jeffrey Epstein").unwrap();
+        let meta = epstein.metadata.as_ref().unwrap();
+        assert_eq!((meta["line"].as_str(), meta["col"].as_str()), ("3", "2"));
+        assert_eq!((meta["end_line"].as_str(), meta["end_col"].as_str()), ("3", "17"));
+        let date = result.dates.iter().find(|e| e.value == "March 3, 2005").unwrap();
+        assert_eq!(date.metadata.as_ref().unwrap()["col"], "32");
+
+        let plain = extract_with_options(text, &ExtractOptions::default());
+        assert!(plain.entities().all(|e| !e.metadata.as_ref().is_some_and(|m| m.contains_key("line"))));
+    }
+
+    #[test]
+    fn test_license_plates() {
+        let text = "A black sedan with NY plate ABC-1234 was seen; a Florida tag 7XYZ 123 and \
+                    registration no. KT5 882 (TX) were noted. The tag line and plate 2024 are not plates.";
+        let result = extract_plates(text);
+        let found: Vec<(&str, &str, Option<&str>)> = result.license_plates.iter()
+            .map(|e| {
+                let meta = e.metadata.as_ref().unwrap();
+                (e.value.as_str(), meta["normalized"].as_str(), meta.get("jurisdiction").map(String::as_str))
+            })
+            .collect();
+        assert_eq!(found, vec![
+            ("ABC-1234", "ABC1234", Some("NY")),
+            ("7XYZ 123", "7XYZ123", Some("FL")),
+            ("KT5 882", "KT5882", Some("TX")),
+        ]);
+        assert_eq!(result.license_plates[0].confidence, 0.85);
+
+        let bare = extract_plates("Order ABC-1234 shipped.");
+        assert!(bare.license_plates.is_empty());
+    }
+
+    #[test]
+    fn test_written_amounts() {
+        let text = "The settlement was five hundred thousand dollars, plus one million dollars in fees \
+                    and twenty-five thousand three hundred and ten euros. One million two hundred thousand dollars \
+                    went to Acme; seven people attended.";
+        let options = ExtractOptions { parse_written_amounts: true, ..Default::default() };
+        let result = extract_with_options(text, &options);
+        let found: Vec<(&str, &str)> = result.amounts.iter()
+            .map(|e| (e.value.as_str(), e.metadata.as_ref().unwrap()["normalized"].as_str()))
+            .collect();
+        assert_eq!(found, vec![
+            ("five hundred thousand dollars", "500000"),
+            ("one million dollars", "1000000"),
+            ("twenty-five thousand three hundred and ten euros", "25310"),
+            ("One million two hundred thousand dollars", "1200000"),
+        ]);
+        assert_eq!(count_all(text, &options)["amounts"], 4);
+
+        assert!(extract_all(text).amounts.is_empty());
+    }
+
+    #[test]
+    fn test_currency_symbols() {
+        let text = "Paid ₹5,00,000 in Mumbai, ₩1,000,000 in Seoul, ¥1,500 in Tokyo, 2,000 THB in Bangkok and ₽750.50 in Moscow.";
+        let result = extract_all(text);
+        let mut found: Vec<(&str, &str, &str)> = result.amounts.iter()
+            .map(|e| {
+                let metadata = e.metadata.as_ref().unwrap();
+                (e.value.as_str(), metadata["normalized"].as_str(), metadata["currency"].as_str())
+            })
+            .collect();
+        found.sort();
+        assert_eq!(found, vec![
+            ("2,000 THB", "2000", "THB"),
+            ("¥1,500", "1500", "JPY"),
+            ("₩1,000,000", "1000000", "KRW"),
+            ("₹5,00,000", "500000", "INR"),
+            ("₽750.50", "750.5", "RUB"),
+        ]);
+
+        // No minor unit: KRW amounts are whole
+        let won = Entity { value: "₩1,000.40".to_string(), ..result.amounts[0].clone() };
+        assert_eq!(AmountNormalizer { locale: NumberLocale::English }.normalize(&won).as_deref(), Some("1000"));
     }
 }
@@ -0,0 +1,155 @@
+//! Compact JSON form of `ExtractionResult` (`OutputProfile::Minimal`)
+//!
+//! Each entity is a `[value, start, end, confidence]` array under its
+//! kind's key, so the type isn't repeated and context and metadata are
+//! left out:
+//!
+//! ```json
+//! {
+//!   "persons": [["Jeffrey Epstein", 21, 36, 0.75]],
+//!   "amounts": [["$5 million", 49, 59, 0.9]],
+//!   "custom": {"case_number": [["1:15-cv-07433", 80, 93, 0.9]]},
+//!   "total_count": 3,
+//!   "processing_time_ms": 1,
+//!   "text_len": 98
+//! }
+//! ```
+//!
+//! - Kind keys are the canonical selectors ("persons", "postal_codes");
+//!   kinds without entities are left out.
+//! - `start` and `end` are byte offsets, as in the full form.
+//! - `custom` holds entities of custom extractors keyed by `entity_type`,
+//!   and is left out when empty.
+//! - `total_count`, `processing_time_ms` and `text_len` are always there;
+//!   `timed_out`, `preview_truncated` (when true) and `errors` (when not
+//!   empty) only when set.
+//!
+//! `from_minimal` rebuilds a result whose entities have no context,
+//! metadata or UTF-16 offsets, and their `id`s renumbered.
+//!
+//! On the 64 KB medium benchmark fixture (1,528 entities) this form is 49
+//! KB of JSON, against 373 KB for the full form and 217 KB for the full form
+//! without context.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::{Entity, EntityKind, ExtractionResult};
+
+/// `[value, start, end, confidence]`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MinimalEntity(pub String, pub usize, pub usize, pub f64);
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct MinimalResult {
+    /// Keyed by canonical selector; see the module docs
+    #[serde(flatten)]
+    pub entities: BTreeMap<String, Vec<MinimalEntity>>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub custom: BTreeMap<String, Vec<MinimalEntity>>,
+    pub total_count: usize,
+    pub processing_time_ms: u64,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub timed_out: bool,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub preview_truncated: bool,
+    #[serde(default)]
+    pub text_len: usize,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<String>,
+}
+
+fn minimal(entity: &Entity) -> MinimalEntity {
+    MinimalEntity(entity.value.clone(), entity.start, entity.end, entity.confidence)
+}
+
+fn full(entity: &MinimalEntity, entity_type: &str) -> Entity {
+    let MinimalEntity(value, start, end, confidence) = entity.clone();
+    Entity {
+        id: 0,
+        utf16_start: None,
+        utf16_end: None,
+        value,
+        entity_type: entity_type.to_string(),
+        start,
+        end,
+        confidence,
+        context: None,
+        metadata: None,
+    }
+}
+
+pub fn to_minimal(result: &ExtractionResult) -> MinimalResult {
+    let mut entities = BTreeMap::new();
+    for &kind in EntityKind::ALL {
+        let field = result.field(kind);
+        if !field.is_empty() {
+            entities.insert(kind.selectors()[0].to_string(), field.iter().map(minimal).collect());
+        }
+    }
+    let mut custom: BTreeMap<String, Vec<MinimalEntity>> = BTreeMap::new();
+    for entity in &result.custom {
+        custom.entry(entity.entity_type.clone()).or_default().push(minimal(entity));
+    }
+    MinimalResult {
+        entities,
+        custom,
+        total_count: result.total_count,
+        processing_time_ms: result.processing_time_ms,
+        timed_out: result.timed_out,
+        preview_truncated: result.preview_truncated,
+        text_len: result.text_len,
+        errors: result.errors.clone(),
+    }
+}
+
+/// The result `minimal` was made from, less what it leaves out. Keys that
+/// name no kind are ignored.
+pub fn from_minimal(minimal: &MinimalResult) -> ExtractionResult {
+    let mut result = ExtractionResult {
+        total_count: minimal.total_count,
+        processing_time_ms: minimal.processing_time_ms,
+        timed_out: minimal.timed_out,
+        preview_truncated: minimal.preview_truncated,
+        text_len: minimal.text_len,
+        errors: minimal.errors.clone(),
+        ..Default::default()
+    };
+    for (selector, entities) in &minimal.entities {
+        if let Some(kind) = EntityKind::from_selector(selector) {
+            *result.field_mut(kind) = entities.iter().map(|e| full(e, kind.as_str())).collect();
+        }
+    }
+    for (entity_type, entities) in &minimal.custom {
+        result.custom.extend(entities.iter().map(|e| full(e, entity_type)));
+    }
+    result.custom.sort_by(|a, b| a.order_key().cmp(&b.order_key()));
+    result.renumber();
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::extract_all;
+
+    #[test]
+    fn test_round_trip_keeps_entities() {
+        let text = "On January 15, 2024, Jeffrey Epstein transferred $5 million to Acme Holdings Inc \
+                    in Palm Beach, FL 33480. Contact jeff@example.com.";
+        let result = extract_all(text);
+        let json = serde_json::to_string(&to_minimal(&result)).unwrap();
+        let decoded = from_minimal(&serde_json::from_str(&json).unwrap());
+
+        let mut expected = result.clone();
+        for entity in expected.entities_mut() {
+            (entity.context, entity.metadata) = (None, None);
+        }
+        assert_eq!(decoded, expected);
+
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(value["persons"][0], serde_json::json!(["Jeffrey Epstein", 21, 36, 0.75]));
+        assert!(value.get("phones").is_none());
+        assert!(json.len() * 2 < serde_json::to_string(&result).unwrap().len());
+    }
+}
@@ -0,0 +1,70 @@
+//! NFC normalization with a map back to original byte offsets
+
+use crate::Entity;
+use unicode_normalization::char::canonical_combining_class;
+use unicode_normalization::{is_nfc, UnicodeNormalization};
+
+/// `(normalized, original)` byte offsets at every segment boundary, ascending
+pub(crate) struct OffsetMap {
+    bounds: Vec<(usize, usize)>,
+}
+
+impl OffsetMap {
+    /// Original offset of the segment containing normalized offset `norm`
+    fn start(&self, norm: usize) -> usize {
+        let i = self.bounds.partition_point(|&(n, _)| n <= norm);
+        self.bounds[i - 1].1
+    }
+
+    /// Original offset of the first segment boundary at or after `norm`
+    fn end(&self, norm: usize) -> usize {
+        let i = self.bounds.partition_point(|&(n, _)| n < norm);
+        self.bounds[i.min(self.bounds.len() - 1)].1
+    }
+
+    /// Point an entity found in the normalized text back at `original`
+    pub(crate) fn remap(&self, entity: &mut Entity, original: &str, context_chars: usize) {
+        let start = self.start(entity.start);
+        let end = self.end(entity.end);
+        entity.start = start;
+        entity.end = end;
+        entity.value = original[start..end].to_string();
+        if entity.context.is_some() {
+            entity.context = Some(crate::context_window(original, start, end, context_chars));
+        }
+    }
+}
+
+/// A char that starts a new composition segment. Combining marks and
+/// Hangul medial/final jamo attach to the preceding char instead.
+fn is_segment_start(c: char) -> bool {
+    canonical_combining_class(c) == 0
+        && !('\u{1161}'..='\u{1175}').contains(&c)
+        && !('\u{11A8}'..='\u{11C2}').contains(&c)
+}
+
+/// NFC-normalize `text`, or `None` if it is already in NFC
+///
+/// Each segment (a starter plus its combining marks) is normalized on its
+/// own, so every normalized char maps back to exactly one original segment.
+pub(crate) fn to_nfc(text: &str) -> Option<(String, OffsetMap)> {
+    if is_nfc(text) {
+        return None;
+    }
+
+    let mut out = String::with_capacity(text.len());
+    let mut bounds = vec![(0, 0)];
+    let mut seg_start = 0;
+
+    for (i, c) in text.char_indices() {
+        if i > seg_start && is_segment_start(c) {
+            out.extend(text[seg_start..i].nfc());
+            bounds.push((out.len(), i));
+            seg_start = i;
+        }
+    }
+    out.extend(text[seg_start..].nfc());
+    bounds.push((out.len(), text.len()));
+
+    Some((out, OffsetMap { bounds }))
+}
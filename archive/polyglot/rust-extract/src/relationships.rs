@@ -0,0 +1,147 @@
+//! Subject-verb-object relationships between persons and organizations
+//!
+//! A relationship is two adjacent person or organization entities with
+//! only a verb from the list between them: "Acme Inc acquired Globex Corp",
+//! "Jeffrey Epstein met with Bill Clinton". The passive form swaps the
+//! roles, so "Globex Corp was acquired by Acme Inc" is the same edge. An
+//! auxiliary ("had"), one -ly adverb and one preposition may surround the
+//! verb; anything else between the two, a comma included, is no link.
+//! Later mentions of a deduplicated name are found by its text.
+
+use serde::{Deserialize, Serialize};
+
+use crate::{Entity, ExtractionResult};
+
+/// Verbs `extract_relationships` links by, matched as written
+pub const DEFAULT_RELATIONSHIP_VERBS: &[&str] = &[
+    "acquired", "bought", "called", "emailed", "employed", "founded", "funded", "hired", "introduced",
+    "married", "met", "owned", "paid", "represented", "sued", "visited", "wired",
+];
+
+/// Gap beyond which two entities are never related
+const MAX_GAP_BYTES: usize = 48;
+
+const AUXILIARIES: &[&str] = &["has", "had", "have", "was", "were", "is", "are", "been", "being"];
+
+const PREPOSITIONS: &[&str] = &["with", "to", "from", "for", "on", "in"];
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Relationship {
+    pub subject: Entity,
+    /// The verb, lowercased
+    pub predicate: String,
+    pub object: Entity,
+}
+
+/// The verb of the text between two entities, and whether it is passive
+fn link(gap: &str, verbs: &[&str]) -> Option<(String, bool)> {
+    if gap.len() > MAX_GAP_BYTES {
+        return None;
+    }
+    let words: Vec<String> = gap.split_whitespace().map(str::to_lowercase).collect();
+    let mut words: &[String] = &words;
+    if words.iter().any(|w| !w.chars().all(char::is_alphabetic)) {
+        return None;
+    }
+    let passive = words.last().is_some_and(|w| w == "by");
+    if passive {
+        words = &words[..words.len() - 1];
+    }
+    while words.first().is_some_and(|w| AUXILIARIES.contains(&w.as_str())) {
+        words = &words[1..];
+    }
+    if words.first().is_some_and(|w| w.ends_with("ly")) {
+        words = &words[1..];
+    }
+    if !passive && words.len() == 2 && PREPOSITIONS.contains(&words[1].as_str()) {
+        words = &words[..1];
+    }
+    match words {
+        [verb] if verbs.iter().any(|v| v.eq_ignore_ascii_case(verb)) => Some((verb.clone(), passive)),
+        _ => None,
+    }
+}
+
+/// Relationships among `result`'s persons and organizations using
+/// `DEFAULT_RELATIONSHIP_VERBS`
+pub fn extract_relationships(text: &str, result: &ExtractionResult) -> Vec<Relationship> {
+    extract_relationships_with(text, result, DEFAULT_RELATIONSHIP_VERBS)
+}
+
+/// Every mention of each entity's value: persons and organizations are
+/// deduplicated by value, so later mentions have no entity of their own.
+/// Those get a copy with their offsets and no `context`.
+pub(crate) fn mentions(text: &str, entities: &[&Entity]) -> Vec<Entity> {
+    let mut found = Vec::new();
+    for entity in entities {
+        for (start, value) in text.match_indices(entity.value.as_str()) {
+            let end = start + value.len();
+            let bounded = !text[..start].ends_with(char::is_alphanumeric) && !text[end..].starts_with(char::is_alphanumeric);
+            if !bounded {
+                continue;
+            }
+            let mut mention = (*entity).clone();
+            if start != entity.start {
+                (mention.start, mention.end, mention.context) = (start, end, None);
+            }
+            found.push(mention);
+        }
+    }
+    // At one start the longest first, then organizations before persons
+    found.sort_by(|a, b| (a.start, std::cmp::Reverse(a.end), &a.entity_type).cmp(&(b.start, std::cmp::Reverse(b.end), &b.entity_type)));
+    found
+}
+
+/// Relationships linked by `verbs`, in document order; `result` must have
+/// been extracted from `text`
+pub fn extract_relationships_with(text: &str, result: &ExtractionResult, verbs: &[&str]) -> Vec<Relationship> {
+    let entities: Vec<&Entity> = result.persons.iter().chain(&result.organizations).collect();
+    // Of overlapping mentions, the first stands
+    let mut spans: Vec<Entity> = Vec::new();
+    for mention in mentions(text, &entities) {
+        if spans.last().is_none_or(|last| mention.start >= last.end) {
+            spans.push(mention);
+        }
+    }
+
+    spans.windows(2)
+        .filter_map(|pair| {
+            let (first, second) = (&pair[0], &pair[1]);
+            let (predicate, passive) = link(text.get(first.end..second.start)?, verbs)?;
+            let (subject, object) = if passive { (second, first) } else { (first, second) };
+            Some(Relationship { subject: subject.clone(), predicate, object: object.clone() })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::extract_all;
+
+    #[test]
+    fn test_acquired_active_and_passive() {
+        let text = "Acme Inc acquired Globex Corp in 2019. In 2021 Initech LLC was quietly acquired by Acme Inc. \
+                    Jeffrey Epstein met with Bill Clinton, and Ghislaine Maxwell praised Bill Clinton.";
+        let result = extract_all(text);
+        let relationships = extract_relationships(text, &result);
+        let triples: Vec<(&str, &str, &str)> = relationships.iter()
+            .map(|r| (r.subject.value.as_str(), r.predicate.as_str(), r.object.value.as_str()))
+            .collect();
+        assert_eq!(triples, vec![
+            ("Acme Inc", "acquired", "Globex Corp"),
+            ("Acme Inc", "acquired", "Initech LLC"),
+            ("Jeffrey Epstein", "met", "Bill Clinton"),
+        ]);
+
+        let passive = &relationships[1];
+        assert_eq!(passive.subject.start, text.rfind("Acme Inc").unwrap());
+        assert!(passive.subject.context.is_none());
+        // "Acme Inc" is also read as a person; the organization wins the span
+        assert_eq!(passive.subject.entity_type, "organization");
+
+        let praised = extract_relationships_with(text, &result, &["praised"]);
+        assert_eq!(praised.len(), 1);
+        assert_eq!(praised[0].subject.value, "Ghislaine Maxwell");
+    }
+}
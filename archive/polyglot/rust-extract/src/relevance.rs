@@ -0,0 +1,69 @@
+//! Cross-type relevance ranking
+//!
+//! `confidence` only says how sure a pattern is; relevance also accounts for
+//! how noteworthy the type is, so a wallet address or a large wire outranks a
+//! generic date even when both matched with similar confidence.
+
+use crate::Entity;
+use std::collections::HashMap;
+
+/// Built-in weight for an entity type, used when no override is given
+pub fn default_type_weight(entity_type: &str) -> f64 {
+    match entity_type {
+        "crypto" => 1.0,
+        "person" => 1.0,
+        "organization" => 0.9,
+        "amount" => 0.8,
+        "email" => 0.8,
+        "phone" => 0.7,
+        "location" => 0.6,
+        "url" => 0.5,
+        "date" => 0.4,
+        _ => 0.5,
+    }
+}
+
+/// Relevance using the built-in type weights
+pub fn relevance(entity: &Entity) -> f64 {
+    relevance_with(entity, &HashMap::new())
+}
+
+/// `confidence × type weight`, boosted by log10 of the magnitude for amounts
+///
+/// Each order of magnitude adds 10% on top of the weight, so $10M scores
+/// 1.7x an amount of $1.
+pub fn relevance_with(entity: &Entity, type_weights: &HashMap<String, f64>) -> f64 {
+    let weight = type_weights.get(&entity.entity_type)
+        .copied()
+        .unwrap_or_else(|| default_type_weight(&entity.entity_type));
+
+    let magnitude = if entity.entity_type == "amount" {
+        let value = entity.metadata.as_ref()
+            .and_then(|m| m.get("normalized"))
+            .and_then(|v| v.parse::<f64>().ok())
+            .unwrap_or(0.0);
+        1.0 + value.max(1.0).log10() / 10.0
+    } else {
+        1.0
+    };
+
+    entity.confidence * weight * magnitude
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::extract_all;
+
+    #[test]
+    fn test_large_amount_outranks_date() {
+        let result = extract_all("On 2023-04-11 the fund moved $10 million offshore.");
+        let amount = result.amounts.iter().find(|e| e.value.contains("million")).unwrap();
+        let date = &result.dates[0];
+        assert!(relevance(amount) > relevance(date));
+
+        // Overrides win over the defaults
+        let weights = HashMap::from([("date".to_string(), 10.0)]);
+        assert!(relevance_with(date, &weights) > relevance_with(amount, &weights));
+    }
+}
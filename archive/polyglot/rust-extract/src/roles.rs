@@ -0,0 +1,105 @@
+//! Job titles from appositives
+//!
+//! Bios and press releases name a person's role right after them: "Jane
+//! Doe, CEO of Globex Inc", "John Smith, the general counsel, said". The
+//! clause after the comma, up to the next punctuation, is the title when it
+//! holds a word from `TITLE_WORDS`; an organization right after its "of" or
+//! "at" is the employer. Persons and organizations come from the result,
+//! later mentions of deduplicated names included.
+
+use serde::{Deserialize, Serialize};
+
+use crate::relationships::mentions;
+use crate::{Entity, ExtractionResult};
+
+/// Words a title has one of; without one the clause is no title ("Jane
+/// Doe, Bill Clinton and ...")
+const TITLE_WORDS: &[&str] = &[
+    "ceo", "cfo", "coo", "cto", "president", "chairman", "chairwoman", "chair", "director", "founder",
+    "co-founder", "partner", "attorney", "lawyer", "counsel", "secretary", "treasurer", "manager",
+    "executive", "officer", "senator", "governor", "professor", "spokesman", "spokeswoman", "spokesperson",
+    "head", "owner", "chief", "principal", "trustee", "assistant", "agent", "pilot", "editor",
+];
+
+/// Longest title, in words
+const MAX_TITLE_WORDS: usize = 6;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PersonRole {
+    pub person: Entity,
+    /// As written, without a leading article: "CEO", "general counsel"
+    pub title: String,
+    pub org: Option<Entity>,
+}
+
+/// The title in `clause`, without "the"/"a"/"an", if it looks like one
+fn title(clause: &str) -> Option<String> {
+    let clause = clause.trim();
+    let clause = ["the ", "The ", "a ", "an "].iter().find_map(|article| clause.strip_prefix(article)).unwrap_or(clause);
+    let words: Vec<&str> = clause.split_whitespace().collect();
+    let plain = words.iter().all(|w| w.chars().all(|c| c.is_alphabetic() || matches!(c, '-' | '&')));
+    let titled = words.iter().any(|w| TITLE_WORDS.contains(&w.to_lowercase().as_str()));
+    (!words.is_empty() && words.len() <= MAX_TITLE_WORDS && plain && titled).then(|| words.join(" "))
+}
+
+/// Titles of `result`'s persons, in document order; `result` must have
+/// been extracted from `text`
+pub fn extract_roles(text: &str, result: &ExtractionResult) -> Vec<PersonRole> {
+    let persons: Vec<&Entity> = result.persons.iter().collect();
+    let orgs: Vec<&Entity> = result.organizations.iter().collect();
+    let orgs = mentions(text, &orgs);
+    let mut persons = mentions(text, &persons);
+    // A person inside an organization's name ("Jane Doe Foundation") isn't one
+    persons.retain(|p| !orgs.iter().any(|o| o.start <= p.start && p.end <= o.end));
+    persons.dedup_by(|b, a| b.start < a.end);
+
+    persons.into_iter()
+        .filter_map(|person| {
+            let after = text[person.end..].strip_prefix(',')?;
+            let rest = after.trim_start();
+            let clause_start = text.len() - rest.len();
+            let clause_end = clause_start + rest.find([',', '.', ';', ':', '(', ')', '\n']).unwrap_or(rest.len());
+
+            // The employer is an organization starting inside the clause,
+            // right after its "of"/"at"
+            let employer = orgs.iter().find_map(|org| {
+                if org.start <= clause_start || clause_end <= org.start {
+                    return None;
+                }
+                let head = text[clause_start..org.start].trim_end();
+                let head = head.strip_suffix(" of").or_else(|| head.strip_suffix(" at"))?;
+                Some((head, org))
+            });
+            match employer {
+                Some((head, org)) => Some(PersonRole { title: title(head)?, person, org: Some(org.clone()) }),
+                None => {
+                    let clause = &text[clause_start..clause_end];
+                    let head = [" of ", " at "].iter().find_map(|p| clause.find(p).map(|i| &clause[..i])).unwrap_or(clause);
+                    Some(PersonRole { title: title(head)?, person, org: None })
+                }
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::extract_all;
+
+    #[test]
+    fn test_title_of_org() {
+        let text = "Jane Doe, CEO of Globex Inc, met Jeffrey Epstein, the general counsel. \
+                    Bill Clinton, Ghislaine Maxwell and others flew.";
+        let result = extract_all(text);
+        let roles = extract_roles(text, &result);
+        let found: Vec<(&str, &str, Option<&str>)> = roles.iter()
+            .map(|r| (r.person.value.as_str(), r.title.as_str(), r.org.as_ref().map(|o| o.value.as_str())))
+            .collect();
+        assert_eq!(found, vec![
+            ("Jane Doe", "CEO", Some("Globex Inc")),
+            ("Jeffrey Epstein", "general counsel", None),
+        ]);
+        assert_eq!(roles[0].org.as_ref().unwrap().entity_type, "organization");
+    }
+}
@@ -0,0 +1,123 @@
+//! Entities bucketed by sentence
+//!
+//! The segmenter is deliberately simple: a sentence ends at `.`, `!` or `?`
+//! (plus any closing quotes or brackets) followed by whitespace, or at a
+//! blank line. A period followed by a lowercase word ("Acme Inc. paid"),
+//! or after a title ("Dr.") or a single capital ("J."), doesn't end one, so
+//! "Dr. Jane Doe" and "John F. Kennedy" stay whole.
+
+use lazy_static::lazy_static;
+use regex::Regex;
+
+use crate::{compile_pattern, Entity, ExtractionResult};
+
+lazy_static! {
+    static ref SENTENCE_END: Regex =
+        compile_pattern(r#"[.!?]+["'”’)\]]*\s+|\r?\n[ \t]*\r?\n\s*"#).unwrap();
+}
+
+/// Words a period follows without ending the sentence, lowercased: the
+/// next word is a name or a number
+const ABBREVIATIONS: &[&str] = &[
+    "mr", "mrs", "ms", "dr", "prof", "rev", "hon", "sen", "rep", "gov", "gen", "st", "no", "vs", "v",
+];
+
+/// Whether the period ending `before` belongs to an abbreviation
+fn abbreviated(before: &str) -> bool {
+    let word = before.rsplit(char::is_whitespace).next().unwrap_or("");
+    let word = word.trim_start_matches(|c: char| !c.is_alphanumeric()).trim_end_matches('.');
+    let mut chars = word.chars();
+    let initial = matches!((chars.next(), chars.next()), (Some(c), None) if c.is_uppercase());
+    initial || ABBREVIATIONS.contains(&word.to_lowercase().as_str())
+}
+
+/// Byte offset where each sentence starts; empty for blank text
+pub(crate) fn sentence_starts(text: &str) -> Vec<usize> {
+    let first = text.len() - text.trim_start().len();
+    if first == text.len() {
+        return Vec::new();
+    }
+    let mut starts = vec![first];
+    for m in SENTENCE_END.find_iter(text) {
+        if m.end() == text.len() || m.start() < first {
+            continue;
+        }
+        // A single period, not "!", "?" or an ellipsis
+        let terminal = m.as_str().trim_end().trim_end_matches(['"', '\'', '”', '’', ')', ']']);
+        if terminal == "." {
+            let next_lowercase = text[m.end()..].starts_with(char::is_lowercase);
+            if next_lowercase || abbreviated(&text[..m.start()]) {
+                continue;
+            }
+        }
+        starts.push(m.end());
+    }
+    starts
+}
+
+/// Each sentence of `text`, trimmed, with the entities of `result` that
+/// start inside it, in document order
+///
+/// An entity straddling a boundary goes with the sentence holding its
+/// start. Every sentence is listed, those without entities too.
+pub fn group_by_sentence(text: &str, result: &ExtractionResult) -> Vec<(String, Vec<Entity>)> {
+    let starts = sentence_starts(text);
+    let mut groups: Vec<(String, Vec<Entity>)> = starts.iter()
+        .enumerate()
+        .map(|(i, &start)| {
+            let end = starts.get(i + 1).copied().unwrap_or(text.len());
+            (text[start..end].trim().to_string(), Vec::new())
+        })
+        .collect();
+    if groups.is_empty() {
+        return groups;
+    }
+
+    let mut entities: Vec<&Entity> = result.entities().collect();
+    entities.sort_by(|a, b| a.order_key().cmp(&b.order_key()));
+    for entity in entities {
+        let index = starts.partition_point(|&start| start <= entity.start).saturating_sub(1);
+        groups[index].1.push(entity.clone());
+    }
+    groups
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::extract_all;
+
+    #[test]
+    fn test_two_sentences() {
+        let text = "Jeffrey Epstein wired $5 million to Acme Corp. Later, Dr. Jane Doe flew to Palm Beach on January 15, 2024.";
+        let mut result = extract_all(text);
+        // Straddles the boundary, so it goes with the first sentence
+        let boundary = text.find("Corp.").unwrap();
+        result.custom.push(Entity {
+            id: 0,
+            value: text[boundary..boundary + 12].to_string(),
+            entity_type: "custom".to_string(),
+            start: boundary,
+            end: boundary + 12,
+            utf16_start: None,
+            utf16_end: None,
+            confidence: 1.0,
+            context: None,
+            metadata: None,
+        });
+
+        let groups = group_by_sentence(text, &result);
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups[0].0, "Jeffrey Epstein wired $5 million to Acme Corp.");
+        assert!(groups[1].0.starts_with("Later, Dr. Jane Doe"));
+
+        let values = |i: usize| groups[i].1.iter().map(|e| e.value.as_str()).collect::<Vec<_>>();
+        for value in ["Jeffrey Epstein", "$5 million", "Acme Corp", "Corp. Later,"] {
+            assert!(values(0).contains(&value), "{value} not in {:?}", values(0));
+        }
+        for value in ["Palm Beach", "January 15, 2024"] {
+            assert!(values(1).contains(&value), "{value} not in {:?}", values(1));
+        }
+        assert!(group_by_sentence("  ", &result).is_empty());
+    }
+}
@@ -0,0 +1,102 @@
+//! Per-field extraction over structured records (JSON, CSV)
+//!
+//! Every string leaf is extracted on its own and keyed by where it came
+//! from, so an entity can be traced back to "the `notes` field of record 17".
+//! JSON leaves are keyed by JSON pointer (`/records/17/notes`); CSV cells use
+//! the same form with the row index and column name (`/17/notes`).
+
+use crate::{extract_with_options, ExtractError, ExtractOptions, ExtractionResult};
+use rayon::prelude::*;
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// RFC 6901 escaping of a single pointer segment
+fn escape_segment(segment: &str) -> String {
+    segment.replace('~', "~0").replace('/', "~1")
+}
+
+fn collect_leaves<'v>(value: &'v Value, pointer: String, leaves: &mut Vec<(String, &'v str)>) {
+    match value {
+        Value::String(text) if !text.is_empty() => leaves.push((pointer, text)),
+        Value::Array(items) => {
+            for (i, item) in items.iter().enumerate() {
+                collect_leaves(item, format!("{}/{}", pointer, i), leaves);
+            }
+        }
+        Value::Object(fields) => {
+            for (name, field) in fields {
+                collect_leaves(field, format!("{}/{}", pointer, escape_segment(name)), leaves);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn extract_fields(fields: Vec<(String, &str)>, options: &ExtractOptions) -> HashMap<String, ExtractionResult> {
+    fields.into_par_iter()
+        .map(|(key, text)| (key, extract_with_options(text, options)))
+        .collect()
+}
+
+/// Extract each non-empty string leaf of `value`, keyed by JSON pointer
+///
+/// Numbers, booleans and nulls are skipped; a bare string value is keyed
+/// by the empty pointer `""`.
+pub fn extract_structured(value: &Value, options: &ExtractOptions) -> HashMap<String, ExtractionResult> {
+    let mut leaves = Vec::new();
+    collect_leaves(value, String::new(), &mut leaves);
+    extract_fields(leaves, options)
+}
+
+/// Extract each non-empty cell of a CSV document with a header row, keyed
+/// by `/<row>/<column>` (rows counted from 0 after the header)
+pub fn extract_csv(csv_text: &str, options: &ExtractOptions) -> Result<HashMap<String, ExtractionResult>, ExtractError> {
+    let mut reader = csv::Reader::from_reader(csv_text.as_bytes());
+    let headers: Vec<String> = reader.headers()?.iter().map(escape_segment).collect();
+
+    let records = reader.records().collect::<Result<Vec<_>, _>>()?;
+    let cells: Vec<(String, &str)> = records.iter()
+        .enumerate()
+        .flat_map(|(row, record)| {
+            record.iter()
+                .zip(&headers)
+                .filter(|(cell, _)| !cell.is_empty())
+                .map(move |(cell, column)| (format!("/{}/{}", row, column), cell))
+        })
+        .collect();
+
+    Ok(extract_fields(cells, options))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nested_json_fields() {
+        let record = serde_json::json!({
+            "id": 17,
+            "subject": "Wire from John Smith",
+            "parties": [{"email": "jeff@example.com"}, {"email": null}],
+            "notes/internal": "Paid $5 million on January 15, 2024",
+        });
+        let results = extract_structured(&record, &ExtractOptions::default());
+
+        assert_eq!(results.len(), 3);
+        assert!(results["/subject"].persons.iter().any(|e| e.value == "John Smith"));
+        assert_eq!(results["/parties/0/email"].emails[0].value, "jeff@example.com");
+        let notes = &results["/notes~1internal"];
+        assert!(!notes.amounts.is_empty());
+        assert!(!notes.dates.is_empty());
+    }
+
+    #[test]
+    fn test_csv_cells() {
+        let csv_text = "id,notes\n1,nothing here\n2,Call John Smith at jeff@example.com\n";
+        let results = extract_csv(csv_text, &ExtractOptions::default()).unwrap();
+
+        assert_eq!(results["/1/notes"].emails[0].value, "jeff@example.com");
+        assert_eq!(results["/1/id"].total_count, 0);
+        assert!(extract_csv("a,b\n1,2,3\n", &ExtractOptions::default()).is_err());
+    }
+}
@@ -0,0 +1,145 @@
+//! Corpus-level statistics over a batch of extraction results
+
+use crate::ExtractionResult;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+/// Number of most frequent values kept per entity type by `aggregate`
+pub const SUMMARY_TOP_K: usize = 10;
+
+/// Equal-width bins over [0, 1] in `BatchSummary::confidence_histograms`
+pub const CONFIDENCE_BINS: usize = 10;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ValueCount {
+    /// First spelling seen for this value
+    pub value: String,
+    pub normalized: String,
+    pub count: usize,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TimingStats {
+    pub total_ms: u64,
+    pub mean_ms: f64,
+    pub min_ms: u64,
+    pub max_ms: u64,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BatchSummary {
+    pub documents: usize,
+    pub total_entities: usize,
+    pub totals_by_type: BTreeMap<String, usize>,
+    /// Most frequent normalized values per type, highest count first
+    pub top_values: BTreeMap<String, Vec<ValueCount>>,
+    /// Entities per type by confidence, `CONFIDENCE_BINS` counts from low
+    /// to high; bin `i` holds [i/10, (i+1)/10), the last one 1.0 as well
+    #[serde(default)]
+    pub confidence_histograms: BTreeMap<String, Vec<usize>>,
+    pub timing: TimingStats,
+}
+
+/// Histogram bin for `confidence`; out-of-range values go to the end bins
+fn confidence_bin(confidence: f64) -> usize {
+    ((confidence.clamp(0.0, 1.0) * CONFIDENCE_BINS as f64) as usize).min(CONFIDENCE_BINS - 1)
+}
+
+/// Summarize a batch, keeping the `SUMMARY_TOP_K` most frequent values per type
+pub fn aggregate(results: &[ExtractionResult]) -> BatchSummary {
+    aggregate_top_k(results, SUMMARY_TOP_K)
+}
+
+/// Summarize a batch, keeping the `k` most frequent values per type
+///
+/// Frequencies are counted on `Entity::normalized_value`, so "New York" and
+/// "new york" are one entry. Ties are broken alphabetically so the output is
+/// stable.
+pub fn aggregate_top_k(results: &[ExtractionResult], k: usize) -> BatchSummary {
+    let mut totals_by_type: BTreeMap<String, usize> = BTreeMap::new();
+    let mut confidence_histograms: BTreeMap<String, Vec<usize>> = BTreeMap::new();
+    // type -> normalized -> (first spelling, count)
+    let mut frequencies: HashMap<&str, HashMap<String, (&str, usize)>> = HashMap::new();
+
+    for result in results {
+        for entity in result.entities() {
+            *totals_by_type.entry(entity.entity_type.clone()).or_default() += 1;
+            confidence_histograms.entry(entity.entity_type.clone())
+                .or_insert_with(|| vec![0; CONFIDENCE_BINS])[confidence_bin(entity.confidence)] += 1;
+            frequencies.entry(&entity.entity_type)
+                .or_default()
+                .entry(entity.normalized_value())
+                .or_insert((&entity.value, 0))
+                .1 += 1;
+        }
+    }
+
+    let top_values = frequencies.into_iter()
+        .map(|(entity_type, values)| {
+            let mut counts: Vec<ValueCount> = values.into_iter()
+                .map(|(normalized, (value, count))| ValueCount { value: value.to_string(), normalized, count })
+                .collect();
+            counts.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.normalized.cmp(&b.normalized)));
+            counts.truncate(k);
+            (entity_type.to_string(), counts)
+        })
+        .collect();
+
+    let times: Vec<u64> = results.iter().map(|r| r.processing_time_ms).collect();
+    let total_ms: u64 = times.iter().sum();
+    let timing = TimingStats {
+        total_ms,
+        mean_ms: if times.is_empty() { 0.0 } else { total_ms as f64 / times.len() as f64 },
+        min_ms: times.iter().copied().min().unwrap_or(0),
+        max_ms: times.iter().copied().max().unwrap_or(0),
+    };
+
+    BatchSummary {
+        documents: results.len(),
+        total_entities: totals_by_type.values().sum(),
+        totals_by_type,
+        top_values,
+        confidence_histograms,
+        timing,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::extract_batch;
+
+    #[test]
+    fn test_aggregate_top_person() {
+        let results = extract_batch(&[
+            "Ghislaine Maxwell flew to London with Jeffrey Epstein.",
+            "Jeffrey Epstein bought the island in 1998.",
+            "Flight logs list Jeffrey  Epstein and Ghislaine Maxwell again.",
+        ]).into_iter().map(Result::unwrap).collect::<Vec<_>>();
+        let summary = aggregate(&results);
+
+        assert_eq!(summary.documents, 3);
+        let top = &summary.top_values["person"][0];
+        assert_eq!(top.normalized, "jeffrey epstein");
+        assert_eq!(top.count, 3);
+        assert_eq!(summary.total_entities, results.iter().map(|r| r.total_count).sum::<usize>());
+    }
+
+    #[test]
+    fn test_confidence_histograms() {
+        let results = extract_batch(&[
+            "Jeffrey Epstein wired $5 million to Acme Holdings on January 15, 2024.",
+            "Call 555-123-4567 or write to jeff@example.com about the FBI.",
+            "Nothing here.",
+        ]).into_iter().map(Result::unwrap).collect::<Vec<_>>();
+        let summary = aggregate(&results);
+
+        let binned: usize = summary.confidence_histograms.values().flatten().sum();
+        assert_eq!(binned, summary.total_entities);
+        for (entity_type, bins) in &summary.confidence_histograms {
+            assert_eq!(bins.len(), CONFIDENCE_BINS);
+            assert_eq!(bins.iter().sum::<usize>(), summary.totals_by_type[entity_type]);
+        }
+        assert_eq!((confidence_bin(0.0), confidence_bin(0.85), confidence_bin(1.0)), (0, 8, 9));
+    }
+}
@@ -0,0 +1,202 @@
+//! Amounts laid out in tables
+//!
+//! Financial exhibits put who was paid what in rows and columns, which the
+//! amount extractor alone flattens. Here each amount keeps its row label
+//! (the leftmost cell) and column header (the cell above it in the first
+//! row). Tables are runs of lines delimited by `|` or tabs with the same
+//! number of cells, or lines whose cells are separated by two or more
+//! spaces and line up under the header. A blank line ends a table.
+
+use lazy_static::lazy_static;
+use regex::Regex;
+
+use crate::{
+    assign_line_columns, assign_utf16_offsets, compile_pattern, context_window, drop_contained_spans, extract_types_with,
+    line_starts, Entity, EntityKind, ExtractOptions,
+};
+
+lazy_static! {
+    // A cell of a space-aligned row: words separated by single spaces
+    static ref ALIGNED_CELL: Regex = compile_pattern(r"\S+(?: \S+)*").unwrap();
+    // A Markdown header rule cell: ---, :--, --:
+    static ref RULE_CELL: Regex = compile_pattern(r"^:?-+:?$").unwrap();
+}
+
+/// A cell: byte offset in the document, and its trimmed text
+type Cell<'t> = (usize, &'t str);
+
+#[derive(PartialEq)]
+enum Layout {
+    Delimited(char, usize),
+    Aligned,
+}
+
+fn trimmed(offset: usize, cell: &str) -> Cell<'_> {
+    let start = cell.len() - cell.trim_start().len();
+    (offset + start, cell.trim())
+}
+
+fn delimited_cells(offset: usize, line: &str) -> Option<(Layout, Vec<Cell<'_>>)> {
+    let delimiter = ['|', '\t'].into_iter().find(|&d| line.contains(d))?;
+    let mut body = line.trim_end();
+    let mut body_offset = offset;
+    if delimiter == '|' {
+        let lead = body.len() - body.trim_start().len();
+        body = body.trim_start().strip_prefix('|').map_or(body, |rest| {
+            body_offset += lead + 1;
+            rest
+        });
+        body = body.strip_suffix('|').unwrap_or(body);
+    }
+    let mut cells = Vec::new();
+    let mut at = body_offset;
+    for cell in body.split(delimiter) {
+        cells.push(trimmed(at, cell));
+        at += cell.len() + delimiter.len_utf8();
+    }
+    (cells.len() >= 2).then_some((Layout::Delimited(delimiter, cells.len()), cells))
+}
+
+fn aligned_cells(offset: usize, line: &str) -> Option<(Layout, Vec<Cell<'_>>)> {
+    let cells: Vec<Cell> = ALIGNED_CELL.find_iter(line).map(|m| (offset + m.start(), m.as_str())).collect();
+    (cells.len() >= 2).then_some((Layout::Aligned, cells))
+}
+
+/// Runs of lines with the same layout, header row first; rule rows dropped
+fn tables(text: &str) -> Vec<(Layout, Vec<Vec<Cell<'_>>>)> {
+    let mut tables: Vec<(Layout, Vec<Vec<Cell>>)> = Vec::new();
+    let mut current: Option<(Layout, Vec<Vec<Cell>>)> = None;
+    let mut offset = 0;
+    for raw in text.split_inclusive('\n') {
+        let line = raw.trim_end_matches(['\n', '\r']);
+        let row = delimited_cells(offset, line).or_else(|| aligned_cells(offset, line));
+        offset += raw.len();
+
+        match (row, &mut current) {
+            (Some((_, cells)), Some(_)) if cells.iter().all(|(_, c)| RULE_CELL.is_match(c)) => {}
+            (Some((layout, cells)), Some((current_layout, rows))) if layout == *current_layout => rows.push(cells),
+            (row, _) => {
+                tables.extend(current.take().filter(|(_, rows)| rows.len() >= 2));
+                current = row.map(|(layout, cells)| (layout, vec![cells]));
+            }
+        }
+    }
+    tables.extend(current.filter(|(_, rows)| rows.len() >= 2));
+    tables
+}
+
+/// Char columns a cell covers on its line
+fn columns(text: &str, (start, cell): Cell) -> (usize, usize) {
+    let line_start = text[..start].rfind('\n').map_or(0, |i| i + 1);
+    let from = text[line_start..start].chars().count();
+    (from, from + cell.chars().count())
+}
+
+/// Header cell an aligned cell sits under: the one overlapping it most,
+/// or the nearest if none does
+fn aligned_header<'t>(text: &str, header: &[Cell<'t>], cell: Cell) -> Option<usize> {
+    let (from, to) = columns(text, cell);
+    header.iter()
+        .enumerate()
+        .min_by_key(|(_, &h)| {
+            let (h_from, h_to) = columns(text, h);
+            let overlap = to.min(h_to).saturating_sub(from.max(h_from));
+            let distance = h_from.saturating_sub(to).max(from.saturating_sub(h_to));
+            (std::cmp::Reverse(overlap), distance)
+        })
+        .map(|(i, _)| i)
+}
+
+/// Amounts in `text`'s tables, each with its row label and column header
+///
+/// Offsets are into `text`. A cell holding "$5 million" yields that one
+/// amount, not also the "$5" inside it. Cells with no amount, the header
+/// row and the label column yield nothing.
+pub fn extract_amount_table(text: &str, options: &ExtractOptions) -> Vec<(String, String, Entity)> {
+    // The options that work in document offsets are applied here, once the
+    // amounts are shifted out of their cells
+    let cell_options = ExtractOptions {
+        exclude_ranges: Vec::new(),
+        scan_within: None,
+        transcript_timestamps: false,
+        tag_paragraphs: false,
+        ..options.clone()
+    };
+    let scanned: Option<Vec<(usize, usize)>> = options.scan_within.as_ref()
+        .map(|regex| regex.find_iter(text).map(|m| (m.start(), m.end())).collect());
+    let kept = |start: usize, end: usize| {
+        !options.exclude_ranges.iter().any(|&(from, to)| start < to && from < end)
+            && scanned.as_ref().is_none_or(|spans| spans.iter().any(|&(from, to)| from <= start && end <= to))
+    };
+    let mut found = Vec::new();
+    for (layout, rows) in tables(text) {
+        let header = &rows[0];
+        for row in &rows[1..] {
+            let label = row[0].1;
+            for (i, &cell) in row.iter().enumerate().skip(1) {
+                let column = match layout {
+                    Layout::Delimited(..) => Some(i),
+                    Layout::Aligned => aligned_header(text, header, cell).filter(|&c| c > 0),
+                };
+                let Some(column) = column else { continue };
+
+                let mut amounts = extract_types_with(cell.1, &["amounts"], &cell_options).amounts;
+                drop_contained_spans(&mut amounts);
+                for mut amount in amounts {
+                    amount.start += cell.0;
+                    amount.end += cell.0;
+                    if !kept(amount.start, amount.end) {
+                        continue;
+                    }
+                    amount.context = options.wants_context(EntityKind::Amount)
+                        .then(|| context_window(text, amount.start, amount.end, options.context_chars));
+                    found.push((label.to_string(), header[column].1.to_string(), amount));
+                }
+            }
+        }
+    }
+    // Cell-relative until now
+    if options.utf16_offsets {
+        assign_utf16_offsets(found.iter_mut().map(|(_, _, amount)| amount), text);
+    }
+    if options.line_columns {
+        assign_line_columns(found.iter_mut().map(|(_, _, amount)| amount), text, &line_starts(text));
+    }
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pipe_and_aligned_tables() {
+        let text = "Exhibit 12: payments\n\
+                    | Payee | 2003 | 2004 |\n\
+                    |-------|------|------|\n\
+                    | Jean Luc | $100,000 | $2.5 million |\n\
+                    | Leslie Groff | $50,000 | none |\n\
+                    \n\
+                    Vendor        Q1          Q2\n\
+                    Acme Corp     $1,200      $3,400\n";
+        let table = extract_amount_table(text, &ExtractOptions::default());
+        let found: Vec<(&str, &str, &str)> = table.iter()
+            .map(|(label, header, amount)| {
+                assert_eq!(&text[amount.start..amount.end], amount.value);
+                (label.as_str(), header.as_str(), amount.value.as_str())
+            })
+            .collect();
+        assert_eq!(found, vec![
+            ("Jean Luc", "2003", "$100,000"),
+            ("Jean Luc", "2004", "$2.5 million"),
+            ("Leslie Groff", "2003", "$50,000"),
+            ("Acme Corp", "Q1", "$1,200"),
+            ("Acme Corp", "Q2", "$3,400"),
+        ]);
+
+        // Exclusions are in document offsets, not the cell's
+        let options = ExtractOptions { exclude_ranges: vec![(0, 3), (text.find("$50,000").unwrap(), text.len())], ..Default::default() };
+        let values: Vec<String> = extract_amount_table(text, &options).into_iter().map(|(_, _, amount)| amount.value).collect();
+        assert_eq!(values, ["$100,000", "$2.5 million"]);
+    }
+}
@@ -0,0 +1,82 @@
+//! Amounts paired with their dates
+//!
+//! Ledgers, bank statements and wire logs put one transaction on a line:
+//! "2020-03-15  $50,000  wire to Acme Inc". Each amount is paired with the
+//! date nearest to it on its line or, for a line without one, the last date
+//! of the line before ("March 3, 2004:" above its entries). Dates and
+//! amounts come from the result, later mentions of deduplicated values
+//! included.
+
+use serde::{Deserialize, Serialize};
+
+use crate::relationships::mentions;
+use crate::{Entity, ExtractionResult};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Transaction {
+    /// None when neither the line nor the one before has a date
+    pub date: Option<Entity>,
+    pub amount: Entity,
+    /// The amount's line, trimmed
+    pub line: String,
+}
+
+/// Byte range of the line holding `offset`, without its newline
+fn line_at(text: &str, offset: usize) -> (usize, usize) {
+    let start = text[..offset].rfind('\n').map_or(0, |i| i + 1);
+    let end = text[offset..].find('\n').map_or(text.len(), |i| offset + i);
+    (start, end)
+}
+
+/// Every amount of `result` with its date, in document order; `result`
+/// must have been extracted from `text`
+pub fn extract_transactions(text: &str, result: &ExtractionResult) -> Vec<Transaction> {
+    let amounts: Vec<&Entity> = result.amounts.iter().collect();
+    let dates: Vec<&Entity> = result.dates.iter().collect();
+    let mut amounts = mentions(text, &amounts);
+    amounts.dedup_by(|b, a| b.start < a.end);
+    let mut dates = mentions(text, &dates);
+    dates.dedup_by(|b, a| b.start < a.end);
+    // A year read inside an amount ("$2,020") is no date of it
+    dates.retain(|d| !amounts.iter().any(|a| a.start < d.end && d.start < a.end));
+
+    amounts.into_iter()
+        .map(|amount| {
+            let (line_start, line_end) = line_at(text, amount.start);
+            let distance = |d: &&Entity| if d.end <= amount.start { amount.start - d.end } else { d.start.saturating_sub(amount.end) };
+            let on_line = dates.iter()
+                .filter(|d| line_start <= d.start && d.end <= line_end)
+                .min_by_key(distance);
+            let date = on_line.or_else(|| {
+                let (previous_start, _) = line_at(text, line_start.checked_sub(1)?);
+                dates.iter().rev().find(|d| previous_start <= d.start && d.end < line_start)
+            });
+            Transaction { date: date.cloned(), line: text[line_start..line_end].trim().to_string(), amount }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::extract_all;
+
+    #[test]
+    fn test_ledger_lines() {
+        let text = "2020-03-15  $50,000  wire to Acme Holdings LLC\n\
+                    2020-04-02  $12,500.00  wire to Southern Trust Company\n\
+                    Fees of $250 were waived.";
+        let result = extract_all(text);
+        let transactions = extract_transactions(text, &result);
+        let pairs: Vec<(&str, Option<&str>)> = transactions.iter()
+            .map(|t| (t.amount.value.as_str(), t.date.as_ref().map(|d| d.value.as_str())))
+            .collect();
+        assert_eq!(pairs, vec![
+            ("$50,000", Some("2020-03-15")),
+            ("$12,500.00", Some("2020-04-02")),
+            // No date on its line: the one before's
+            ("$250", Some("2020-04-02")),
+        ]);
+        assert_eq!(transactions[1].line, "2020-04-02  $12,500.00  wire to Southern Trust Company");
+    }
+}
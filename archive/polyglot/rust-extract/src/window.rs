@@ -0,0 +1,150 @@
+//! Splitting a large document into overlapping windows
+//!
+//! Batches schedule work by window rather than by document, so one huge
+//! document spreads across threads instead of pinning one while the rest
+//! idle. Each window owns a disjoint range of the document and keeps only
+//! entities starting there; the overlap on either side gives matches and
+//! context near a cut the same surroundings they have in the whole text.
+//!
+//! Whole-document heuristics (acronym repeat counts, the person
+//! sentence-start check) only see their window, so results near those
+//! thresholds can differ from an unsplit run. Likewise a window opening
+//! inside a fenced code block reads it as prose for `skip_code_blocks`.
+
+use crate::{EntityKind, ExtractionResult};
+use std::collections::HashSet;
+
+/// Bytes of text either side of a window's owned range
+pub(crate) const WINDOW_OVERLAP_BYTES: usize = 4 * 1024;
+
+/// Smallest owned range worth a separate task
+pub(crate) const MIN_WINDOW_BYTES: usize = 256 * 1024;
+
+/// Kinds whose extractor keeps only the first occurrence of each value
+pub(crate) const VALUE_DEDUPED: &[EntityKind] = &[
+    EntityKind::Date,
+    EntityKind::Person,
+    EntityKind::Organization,
+    EntityKind::Amount,
+    EntityKind::Location,
+];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct Window {
+    /// Extracted range, overlap included
+    pub start: usize,
+    pub end: usize,
+    /// Range whose entities this window reports
+    pub own_start: usize,
+    pub own_end: usize,
+}
+
+/// Largest whitespace position at or before `pos`, or `pos` backed off to
+/// a char boundary if there is none within the overlap
+pub(crate) fn cut_point(text: &str, pos: usize) -> usize {
+    let mut pos = pos.min(text.len());
+    while !text.is_char_boundary(pos) {
+        pos -= 1;
+    }
+    let floor = pos.saturating_sub(WINDOW_OVERLAP_BYTES);
+    match text[floor..pos].rfind(char::is_whitespace) {
+        Some(i) => floor + i,
+        None => pos,
+    }
+}
+
+pub(crate) fn char_floor(text: &str, mut pos: usize) -> usize {
+    while !text.is_char_boundary(pos) {
+        pos -= 1;
+    }
+    pos
+}
+
+pub(crate) fn char_ceil(text: &str, mut pos: usize) -> usize {
+    while !text.is_char_boundary(pos) {
+        pos += 1;
+    }
+    pos
+}
+
+/// Windows owning about `target` bytes each; one window if the text is
+/// under twice that
+pub(crate) fn plan(text: &str, target: usize) -> Vec<Window> {
+    let target = target.max(MIN_WINDOW_BYTES);
+    if text.len() < 2 * target {
+        return vec![Window { start: 0, end: text.len(), own_start: 0, own_end: text.len() }];
+    }
+
+    let mut windows = Vec::new();
+    let mut own_start = 0;
+    while own_start < text.len() {
+        let own_end = if text.len() - own_start < 2 * target {
+            text.len()
+        } else {
+            cut_point(text, own_start + target)
+        };
+        windows.push(Window {
+            start: char_floor(text, own_start.saturating_sub(WINDOW_OVERLAP_BYTES)),
+            end: char_ceil(text, (own_end + WINDOW_OVERLAP_BYTES).min(text.len())),
+            own_start,
+            own_end,
+        });
+        own_start = own_end;
+    }
+    windows
+}
+
+/// Combine per-window results (window-relative offsets, in window order)
+/// into one result in document offsets
+pub(crate) fn merge(parts: Vec<(Window, ExtractionResult)>) -> ExtractionResult {
+    let mut merged = ExtractionResult::default();
+    let mut seen: HashSet<(EntityKind, String)> = HashSet::new();
+
+    for (window, mut part) in parts {
+        merged.timed_out |= part.timed_out;
+        merged.errors.append(&mut part.errors);
+        for &kind in EntityKind::ALL {
+            let dedup = VALUE_DEDUPED.contains(&kind);
+            for mut entity in std::mem::take(part.field_mut(kind)) {
+                entity.start += window.start;
+                entity.end += window.start;
+                let owned = (window.own_start..window.own_end).contains(&entity.start);
+                if owned && (!dedup || seen.insert((kind, entity.value.to_lowercase()))) {
+                    merged.field_mut(kind).push(entity);
+                }
+            }
+        }
+        for mut entity in std::mem::take(&mut part.custom) {
+            entity.start += window.start;
+            entity.end += window.start;
+            if (window.own_start..window.own_end).contains(&entity.start) {
+                merged.custom.push(entity);
+            }
+        }
+    }
+
+    merged.recount();
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plan_covers_text_at_whitespace() {
+        let text = "Jeffrey Epstein wired funds. ".repeat(40_000);
+        let windows = plan(&text, MIN_WINDOW_BYTES);
+
+        assert!(windows.len() >= 4);
+        assert_eq!(windows[0].own_start, 0);
+        assert_eq!(windows.last().unwrap().own_end, text.len());
+        for pair in windows.windows(2) {
+            assert_eq!(pair[0].own_end, pair[1].own_start);
+            assert!(text[pair[0].own_end..].starts_with(' '));
+        }
+        assert!(windows.iter().all(|w| w.start <= w.own_start && w.own_end <= w.end));
+
+        assert_eq!(plan("short", MIN_WINDOW_BYTES).len(), 1);
+    }
+}
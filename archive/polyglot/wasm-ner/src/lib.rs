@@ -6,6 +6,7 @@
 use lazy_static::lazy_static;
 use regex::Regex;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use wasm_bindgen::prelude::*;
 
 // =============================================================================
@@ -18,6 +19,14 @@ pub struct HighlightedEntity {
     pub entity_type: String,
     pub start: usize,
     pub end: usize,
+    pub confidence: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct TypeCount {
+    pub count: usize,
+    /// Mean confidence of the matches; 0 when there are none
+    pub avg_confidence: f64,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -75,127 +84,161 @@ fn escape_html(text: &str) -> String {
         .replace('\'', "&#x27;")
 }
 
-fn highlight_matches(text: &str, regex: &Regex, entity_type: &str, class: &str) -> (String, Vec<HighlightedEntity>) {
-    let mut entities = Vec::new();
-    let mut result = String::new();
-    let mut last_end = 0;
-
-    for cap in regex.captures_iter(text) {
-        if let Some(m) = cap.get(1) {
-            // Add text before match
-            result.push_str(&escape_html(&text[last_end..m.start()]));
-
-            // Add highlighted match
-            let matched = m.as_str();
-            result.push_str(&format!(
-                r#"<span class="entity {}" data-type="{}">{}</span>"#,
-                class,
-                entity_type,
-                escape_html(matched)
-            ));
-
-            entities.push(HighlightedEntity {
-                text: matched.to_string(),
-                entity_type: entity_type.to_string(),
-                start: m.start(),
-                end: m.end(),
-            });
-
-            last_end = m.end();
-        }
-    }
-
-    result.push_str(&escape_html(&text[last_end..]));
-    (result, entities)
+/// A highlightable entity type. Confidences match the base confidences
+/// of the server-side `l-extract` extractors.
+struct EntityType {
+    name: &'static str,
+    class: &'static str,
+    confidence: f64,
+    regex: &'static Regex,
 }
 
-// =============================================================================
-// WASM EXPORTS
-// =============================================================================
-
-/// Highlight all entities in text, returning HTML with spans
-#[wasm_bindgen]
-pub fn highlight_entities(text: &str) -> JsValue {
-    let mut all_entities: Vec<(usize, usize, String, String, String)> = Vec::new();
+lazy_static! {
+    /// Indexed by type id; see `OFFSET_TYPES`
+    static ref ENTITY_TYPES: [EntityType; 6] = [
+        EntityType { name: "person", class: "entity-person", confidence: 0.75, regex: &PERSON_RE },
+        EntityType { name: "organization", class: "entity-org", confidence: 0.80, regex: &ORG_RE },
+        EntityType { name: "amount", class: "entity-amount", confidence: 0.90, regex: &AMOUNT_RE },
+        EntityType { name: "date", class: "entity-date", confidence: 0.85, regex: &DATE_RE },
+        EntityType { name: "location", class: "entity-location", confidence: 0.70, regex: &LOCATION_RE },
+        EntityType { name: "email", class: "entity-email", confidence: 0.95, regex: &EMAIL_RE },
+    ];
+}
 
-    // Collect all matches with their positions
-    for cap in PERSON_RE.captures_iter(text) {
-        if let Some(m) = cap.get(1) {
-            all_entities.push((m.start(), m.end(), m.as_str().to_string(), "person".to_string(), "entity-person".to_string()));
+/// Type ids used by `extract_offsets`, indexed by id:
+///
+/// | id | type         |
+/// |----|--------------|
+/// | 0  | person       |
+/// | 1  | organization |
+/// | 2  | amount       |
+/// | 3  | date         |
+/// | 4  | location     |
+/// | 5  | email        |
+///
+/// New types are only ever appended, so ids stay stable for cached legends.
+pub const OFFSET_TYPES: [&str; 6] = ["person", "organization", "amount", "date", "location", "email"];
+
+/// Every match of the given types, as `(type_id, entity)`, in type order
+fn candidates(text: &str, type_ids: std::ops::Range<usize>) -> Vec<(usize, HighlightedEntity)> {
+    let mut entities = Vec::new();
+    for type_id in type_ids {
+        let entity_type = &ENTITY_TYPES[type_id];
+        for cap in entity_type.regex.captures_iter(text) {
+            if let Some(m) = cap.get(1) {
+                entities.push((type_id, HighlightedEntity {
+                    text: m.as_str().to_string(),
+                    entity_type: entity_type.name.to_string(),
+                    start: m.start(),
+                    end: m.end(),
+                    confidence: entity_type.confidence,
+                }));
+            }
         }
     }
+    entities
+}
 
-    for cap in ORG_RE.captures_iter(text) {
-        if let Some(m) = cap.get(1) {
-            all_entities.push((m.start(), m.end(), m.as_str().to_string(), "organization".to_string(), "entity-org".to_string()));
-        }
-    }
+/// Non-overlapping subset, sorted by start. Where spans overlap the
+/// higher-confidence one is kept; ties go to the earlier start, then to
+/// the lower type id.
+fn resolve_overlaps(mut entities: Vec<(usize, HighlightedEntity)>) -> Vec<(usize, HighlightedEntity)> {
+    entities.sort_by(|(a_id, a), (b_id, b)| {
+        b.confidence.total_cmp(&a.confidence)
+            .then(a.start.cmp(&b.start))
+            .then(a_id.cmp(b_id))
+    });
 
-    for cap in AMOUNT_RE.captures_iter(text) {
-        if let Some(m) = cap.get(1) {
-            all_entities.push((m.start(), m.end(), m.as_str().to_string(), "amount".to_string(), "entity-amount".to_string()));
+    // Accepted spans keyed by start; neighbours decide whether a span fits
+    let mut taken: BTreeMap<usize, usize> = BTreeMap::new();
+    let mut kept = Vec::new();
+    for (type_id, entity) in entities {
+        let clashes_before = taken.range(..=entity.start).next_back().is_some_and(|(_, &end)| end > entity.start);
+        let clashes_after = taken.range(entity.start..).next().is_some_and(|(&start, _)| start < entity.end);
+        if !clashes_before && !clashes_after {
+            taken.insert(entity.start, entity.end);
+            kept.push((type_id, entity));
         }
     }
+    kept.sort_by_key(|(_, e)| e.start);
+    kept
+}
 
-    for cap in DATE_RE.captures_iter(text) {
-        if let Some(m) = cap.get(1) {
-            all_entities.push((m.start(), m.end(), m.as_str().to_string(), "date".to_string(), "entity-date".to_string()));
-        }
+/// Flat `[start, end, type_id, ...]` triples for non-overlapping entities.
+///
+/// Offsets are UTF-16 code units, matching JS string indices and DOM
+/// `Range` offsets. Overlaps are resolved like `highlight_entities`.
+fn entity_offsets(text: &str) -> Vec<i32> {
+    let spans = resolve_overlaps(candidates(text, 0..ENTITY_TYPES.len()));
+
+    // Spans are sorted, so byte offsets convert to UTF-16 in one pass
+    let mut flat = Vec::new();
+    let mut byte_pos = 0;
+    let mut utf16_pos = 0;
+    for (type_id, entity) in spans {
+        utf16_pos += text[byte_pos..entity.start].encode_utf16().count();
+        let utf16_start = utf16_pos;
+        utf16_pos += text[entity.start..entity.end].encode_utf16().count();
+        flat.extend([utf16_start as i32, utf16_pos as i32, type_id as i32]);
+        byte_pos = entity.end;
     }
+    flat
+}
 
-    for cap in LOCATION_RE.captures_iter(text) {
-        if let Some(m) = cap.get(1) {
-            all_entities.push((m.start(), m.end(), m.as_str().to_string(), "location".to_string(), "entity-location".to_string()));
-        }
+/// Matches and mean confidence per type, keyed by plural type name
+/// ("persons"). Matches are counted before overlap resolution, so a span
+/// read as two types counts for both.
+fn type_counts(text: &str) -> BTreeMap<String, TypeCount> {
+    let mut counts: BTreeMap<String, TypeCount> = ENTITY_TYPES.iter()
+        .map(|t| (format!("{}s", t.name), TypeCount { count: 0, avg_confidence: 0.0 }))
+        .collect();
+    for (type_id, entity) in candidates(text, 0..ENTITY_TYPES.len()) {
+        let counted = counts.get_mut(&format!("{}s", ENTITY_TYPES[type_id].name)).unwrap();
+        counted.count += 1;
+        // Summed here, divided below
+        counted.avg_confidence += entity.confidence;
     }
-
-    for cap in EMAIL_RE.captures_iter(text) {
-        if let Some(m) = cap.get(1) {
-            all_entities.push((m.start(), m.end(), m.as_str().to_string(), "email".to_string(), "entity-email".to_string()));
-        }
+    for counted in counts.values_mut().filter(|c| c.count > 0) {
+        counted.avg_confidence /= counted.count as f64;
     }
+    counts
+}
 
-    // Sort by start position
-    all_entities.sort_by_key(|e| e.0);
+// =============================================================================
+// WASM EXPORTS
+// =============================================================================
 
-    // Remove overlapping entities (keep first)
-    let mut filtered: Vec<(usize, usize, String, String, String)> = Vec::new();
-    let mut last_end = 0;
-    for entity in all_entities {
-        if entity.0 >= last_end {
-            last_end = entity.1;
-            filtered.push(entity);
-        }
-    }
+/// Highlight all entities in text, returning HTML with spans
+///
+/// Overlapping matches keep the higher-confidence entity.
+#[wasm_bindgen]
+pub fn highlight_entities(text: &str) -> JsValue {
+    let filtered = resolve_overlaps(candidates(text, 0..ENTITY_TYPES.len()));
 
     // Build HTML
     let mut html = String::new();
     let mut last_pos = 0;
     let mut entities_out = Vec::new();
 
-    for (start, end, matched, entity_type, class) in &filtered {
-        html.push_str(&escape_html(&text[last_pos..*start]));
+    for (type_id, entity) in filtered {
+        let class = ENTITY_TYPES[type_id].class;
+        html.push_str(&escape_html(&text[last_pos..entity.start]));
         html.push_str(&format!(
             r#"<span class="entity {}" data-type="{}" title="{}">{}</span>"#,
             class,
-            entity_type,
-            entity_type,
-            escape_html(matched)
+            entity.entity_type,
+            entity.entity_type,
+            escape_html(&entity.text)
         ));
-        entities_out.push(HighlightedEntity {
-            text: matched.clone(),
-            entity_type: entity_type.clone(),
-            start: *start,
-            end: *end,
-        });
-        last_pos = *end;
+        last_pos = entity.end;
+        entities_out.push(entity);
     }
     html.push_str(&escape_html(&text[last_pos..]));
 
     let result = HighlightResult {
         html,
-        entities: entities_out.clone(),
         count: entities_out.len(),
+        entities: entities_out,
     };
 
     serde_wasm_bindgen::to_value(&result).unwrap()
@@ -204,79 +247,35 @@ pub fn highlight_entities(text: &str) -> JsValue {
 /// Extract entities without highlighting
 #[wasm_bindgen]
 pub fn extract_entities(text: &str) -> JsValue {
-    let mut entities = Vec::new();
-
-    for cap in PERSON_RE.captures_iter(text) {
-        if let Some(m) = cap.get(1) {
-            entities.push(HighlightedEntity {
-                text: m.as_str().to_string(),
-                entity_type: "person".to_string(),
-                start: m.start(),
-                end: m.end(),
-            });
-        }
-    }
-
-    for cap in ORG_RE.captures_iter(text) {
-        if let Some(m) = cap.get(1) {
-            entities.push(HighlightedEntity {
-                text: m.as_str().to_string(),
-                entity_type: "organization".to_string(),
-                start: m.start(),
-                end: m.end(),
-            });
-        }
-    }
-
-    for cap in AMOUNT_RE.captures_iter(text) {
-        if let Some(m) = cap.get(1) {
-            entities.push(HighlightedEntity {
-                text: m.as_str().to_string(),
-                entity_type: "amount".to_string(),
-                start: m.start(),
-                end: m.end(),
-            });
-        }
-    }
-
-    for cap in DATE_RE.captures_iter(text) {
-        if let Some(m) = cap.get(1) {
-            entities.push(HighlightedEntity {
-                text: m.as_str().to_string(),
-                entity_type: "date".to_string(),
-                start: m.start(),
-                end: m.end(),
-            });
-        }
-    }
-
-    for cap in LOCATION_RE.captures_iter(text) {
-        if let Some(m) = cap.get(1) {
-            entities.push(HighlightedEntity {
-                text: m.as_str().to_string(),
-                entity_type: "location".to_string(),
-                start: m.start(),
-                end: m.end(),
-            });
-        }
-    }
-
+    // Every type but email
+    let entities: Vec<HighlightedEntity> = candidates(text, 0..5).into_iter().map(|(_, e)| e).collect();
     serde_wasm_bindgen::to_value(&entities).unwrap()
 }
 
+/// Entity offsets only, for highlighters that wrap DOM ranges themselves
+///
+/// Returns `{ offsets: Int32Array, types: string[] }` where `offsets` holds
+/// `[start, end, type_id]` triples in UTF-16 units and `types[type_id]` is
+/// the type name (see `OFFSET_TYPES`). Skips building HTML and per-entity
+/// objects, so large documents cross the WASM boundary as one buffer.
+#[wasm_bindgen]
+pub fn extract_offsets(text: &str) -> JsValue {
+    let offsets = js_sys::Int32Array::from(entity_offsets(text).as_slice());
+    let types: js_sys::Array = OFFSET_TYPES.iter().map(|t| JsValue::from_str(t)).collect();
+
+    let result = js_sys::Object::new();
+    js_sys::Reflect::set(&result, &"offsets".into(), &offsets).unwrap();
+    js_sys::Reflect::set(&result, &"types".into(), &types).unwrap();
+    result.into()
+}
+
 /// Count entities by type
+///
+/// Returns `{ persons: { count, avg_confidence }, organizations: ..., ... }`
+/// with every type present, for quality indicators next to the counts.
 #[wasm_bindgen]
 pub fn count_entities(text: &str) -> JsValue {
-    let counts = serde_json::json!({
-        "persons": PERSON_RE.captures_iter(text).count(),
-        "organizations": ORG_RE.captures_iter(text).count(),
-        "amounts": AMOUNT_RE.captures_iter(text).count(),
-        "dates": DATE_RE.captures_iter(text).count(),
-        "locations": LOCATION_RE.captures_iter(text).count(),
-        "emails": EMAIL_RE.captures_iter(text).count(),
-    });
-
-    serde_wasm_bindgen::to_value(&counts).unwrap()
+    serde_wasm_bindgen::to_value(&type_counts(text)).unwrap()
 }
 
 // =============================================================================
@@ -289,3 +288,57 @@ pub fn main() {
     #[cfg(feature = "console_error_panic_hook")]
     console_error_panic_hook::set_once();
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_offsets_are_triples() {
+        let text = "John Smith of Acme Inc paid $5,000 in Miami on 2024-01-15. Mail jeff@example.com";
+        let flat = entity_offsets(text);
+        assert!(!flat.is_empty());
+        assert_eq!(flat.len() % 3, 0);
+        assert!(flat.chunks(3).all(|t| t[0] < t[1] && (t[2] as usize) < OFFSET_TYPES.len()));
+    }
+
+    #[test]
+    fn test_offsets_use_utf16_units() {
+        // "é" is two bytes but one UTF-16 unit
+        let text = "Café owner John Smith";
+        let flat = entity_offsets(text);
+        let person = flat.chunks(3).find(|t| OFFSET_TYPES[t[2] as usize] == "person").unwrap();
+        assert_eq!(person[..2], [11, 21]);
+    }
+
+    #[test]
+    fn test_counts_carry_average_confidence() {
+        let counts = type_counts("John Smith of Acme Inc paid $5,000 and $7,500 in Miami.");
+        assert_eq!(counts["amounts"], TypeCount { count: 2, avg_confidence: 0.90 });
+        assert_eq!(counts["emails"], TypeCount { count: 0, avg_confidence: 0.0 });
+
+        let json = serde_json::to_value(&counts).unwrap();
+        assert_eq!(json.as_object().unwrap().len(), OFFSET_TYPES.len());
+        for (name, counted) in json.as_object().unwrap() {
+            assert!(counted["count"].is_u64(), "{name}");
+            assert!(counted["avg_confidence"].is_f64(), "{name}");
+        }
+        assert_eq!(json["locations"]["count"], 1);
+    }
+
+    #[test]
+    fn test_overlap_keeps_higher_confidence() {
+        let kept = |text: &str| -> Vec<(String, String, f64)> {
+            resolve_overlaps(candidates(text, 0..ENTITY_TYPES.len())).into_iter()
+                .map(|(_, e)| (e.entity_type, e.text, e.confidence))
+                .collect()
+        };
+
+        // "Acme Bank" matches as person and organization, but the email that
+        // starts inside it is more reliable than either
+        assert_eq!(kept("Acme Bank@example.com"), [("email".to_string(), "Bank@example.com".to_string(), 0.95)]);
+
+        // Same start: organization beats the person and location readings
+        assert_eq!(kept("Paris Trust"), [("organization".to_string(), "Paris Trust".to_string(), 0.80)]);
+    }
+}